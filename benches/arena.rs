@@ -0,0 +1,95 @@
+use avl_tree_set::{AvlTreeSet, AvlTreeSetArena};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+mod common;
+
+use common::{shuffled, SIZES};
+
+fn insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert_random");
+
+    for &len in SIZES.iter() {
+        let values = shuffled(len);
+
+        group.bench_with_input(BenchmarkId::new("boxed", len), &values, |b, values| {
+            b.iter(|| {
+                let mut set = AvlTreeSet::new();
+                set.extend(values.iter().cloned());
+                set
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("arena", len), &values, |b, values| {
+            b.iter(|| {
+                let mut set = AvlTreeSetArena::with_capacity(values.len());
+                set.extend(values.iter().cloned());
+                set
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("contains");
+
+    for &len in SIZES.iter() {
+        let values = shuffled(len);
+        let boxed = values.iter().cloned().collect::<AvlTreeSet<_>>();
+        let arena = values.iter().cloned().collect::<AvlTreeSetArena<_>>();
+
+        group.bench_with_input(BenchmarkId::new("boxed", len), &values, |b, values| {
+            b.iter(|| values.iter().filter(|value| boxed.contains(black_box(value))).count())
+        });
+        group.bench_with_input(BenchmarkId::new("arena", len), &values, |b, values| {
+            b.iter(|| values.iter().filter(|value| arena.contains(black_box(value))).count())
+        });
+    }
+
+    group.finish();
+}
+
+fn remove(c: &mut Criterion) {
+    let mut group = c.benchmark_group("remove_all");
+
+    for &len in SIZES.iter() {
+        let values = shuffled(len);
+        let boxed = values.iter().cloned().collect::<AvlTreeSet<_>>();
+        let arena = values.iter().cloned().collect::<AvlTreeSetArena<_>>();
+
+        group.bench_with_input(BenchmarkId::new("boxed", len), &values, |b, values| {
+            b.iter_batched(
+                || boxed.clone(),
+                |mut set| values.iter().for_each(|value| { set.remove(value); }),
+                criterion::BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("arena", len), &values, |b, values| {
+            b.iter_batched(
+                || arena.clone(),
+                |mut set| values.iter().for_each(|value| { set.remove(value); }),
+                criterion::BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn iterate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iter");
+
+    for &len in SIZES.iter() {
+        let values = shuffled(len);
+        let boxed = values.iter().cloned().collect::<AvlTreeSet<_>>();
+        let arena = values.iter().cloned().collect::<AvlTreeSetArena<_>>();
+
+        group.bench_function(BenchmarkId::new("boxed", len), |b| b.iter(|| boxed.iter().sum::<u64>()));
+        group.bench_function(BenchmarkId::new("arena", len), |b| b.iter(|| arena.iter().sum::<u64>()));
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, insert, lookup, remove, iterate);
+criterion_main!(benches);
@@ -0,0 +1,137 @@
+use avl_tree_set::AvlTreeSet;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use std::collections::BTreeSet;
+
+mod common;
+
+use common::{shuffled, SIZES};
+
+fn insert_random(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert_random");
+
+    for &len in SIZES.iter() {
+        let values = shuffled(len);
+
+        group.bench_with_input(BenchmarkId::new("avl", len), &values, |b, values| {
+            b.iter(|| {
+                let mut set = AvlTreeSet::new();
+                set.extend(values.iter().cloned());
+                set
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("btree", len), &values, |b, values| {
+            b.iter(|| {
+                let mut set = BTreeSet::new();
+                set.extend(values.iter().cloned());
+                set
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn insert_sorted(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert_sorted");
+
+    for &len in SIZES.iter() {
+        group.bench_function(BenchmarkId::new("avl", len), |b| {
+            b.iter(|| {
+                let mut set = AvlTreeSet::new();
+                set.extend(0..len as u64);
+                set
+            })
+        });
+        group.bench_function(BenchmarkId::new("btree", len), |b| {
+            b.iter(|| {
+                let mut set = BTreeSet::new();
+                set.extend(0..len as u64);
+                set
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("contains");
+
+    for &len in SIZES.iter() {
+        let values = shuffled(len);
+        let avl = values.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree = values.iter().cloned().collect::<BTreeSet<_>>();
+
+        group.bench_with_input(BenchmarkId::new("avl", len), &values, |b, values| {
+            b.iter(|| values.iter().filter(|value| avl.contains(black_box(value))).count())
+        });
+        group.bench_with_input(BenchmarkId::new("btree", len), &values, |b, values| {
+            b.iter(|| values.iter().filter(|value| btree.contains(black_box(value))).count())
+        });
+    }
+
+    group.finish();
+}
+
+fn remove(c: &mut Criterion) {
+    let mut group = c.benchmark_group("remove_all");
+
+    for &len in SIZES.iter() {
+        let values = shuffled(len);
+        let avl = values.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree = values.iter().cloned().collect::<BTreeSet<_>>();
+
+        group.bench_with_input(BenchmarkId::new("avl", len), &values, |b, values| {
+            b.iter_batched(
+                || avl.clone(),
+                |mut set| values.iter().for_each(|value| { set.remove(value); }),
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("btree", len), &values, |b, values| {
+            b.iter_batched(
+                || btree.clone(),
+                |mut set| values.iter().for_each(|value| { set.remove(value); }),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn iterate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iter");
+
+    for &len in SIZES.iter() {
+        let avl = (0..len as u64).collect::<AvlTreeSet<_>>();
+        let btree = (0..len as u64).collect::<BTreeSet<_>>();
+
+        group.bench_function(BenchmarkId::new("avl", len), |b| b.iter(|| avl.iter().sum::<u64>()));
+        group.bench_function(BenchmarkId::new("btree", len), |b| b.iter(|| btree.iter().sum::<u64>()));
+    }
+
+    group.finish();
+}
+
+fn range(c: &mut Criterion) {
+    let mut group = c.benchmark_group("range_scan");
+
+    for &len in SIZES.iter() {
+        let avl = (0..len as u64).collect::<AvlTreeSet<_>>();
+        let btree = (0..len as u64).collect::<BTreeSet<_>>();
+        let (start, end) = (len as u64 / 4, len as u64 / 2);
+
+        group.bench_function(BenchmarkId::new("avl", len), |b| {
+            b.iter(|| avl.range(black_box(start)..black_box(end)).sum::<u64>())
+        });
+        group.bench_function(BenchmarkId::new("btree", len), |b| {
+            b.iter(|| btree.range(black_box(start)..black_box(end)).sum::<u64>())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, insert_random, insert_sorted, lookup, remove, iterate, range);
+criterion_main!(benches);
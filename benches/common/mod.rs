@@ -0,0 +1,15 @@
+/// A fixed xorshift sequence, so every run works on the same values.
+pub fn shuffled(len: usize) -> Vec<u64> {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        })
+        .collect()
+}
+
+pub const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
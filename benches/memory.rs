@@ -0,0 +1,63 @@
+//! Reports the bytes each set allocates per element, measured by counting
+//! every allocation made while it is built. Run with
+//! `cargo bench --bench memory`.
+
+use avl_tree_set::AvlTreeSet;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+mod common;
+
+use common::{shuffled, SIZES};
+
+/// Wraps the system allocator to track the bytes currently allocated.
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// The bytes still allocated after `build` returns.
+fn retained_by<S, F: FnOnce() -> S>(build: F) -> (S, usize) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let set = build();
+    (set, ALLOCATED.load(Ordering::Relaxed) - before)
+}
+
+fn main() {
+    println!("bytes per u64 element (the value itself is 8 bytes)");
+    println!("{:>8} {:>8} {:>8}", "len", "avl", "btree");
+
+    for &len in SIZES.iter() {
+        let values = shuffled(len);
+
+        let (avl, avl_bytes) = retained_by(|| {
+            let mut set = AvlTreeSet::new();
+            set.extend(values.iter().cloned());
+            set
+        });
+        let (btree, btree_bytes) = retained_by(|| values.iter().cloned().collect::<BTreeSet<_>>());
+        assert_eq!(avl.heap_size(), avl_bytes);
+
+        println!(
+            "{:>8} {:>8.1} {:>8.1}",
+            len,
+            avl_bytes as f64 / avl.len() as f64,
+            btree_bytes as f64 / btree.len() as f64,
+        );
+    }
+}
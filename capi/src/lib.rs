@@ -0,0 +1,4 @@
+//! Links the functions of `avl_tree_set::ffi` into a `cdylib` and a
+//! `staticlib`. Their declarations are in `include/avl_tree_set.h`.
+
+pub use avl_tree_set::ffi::*;
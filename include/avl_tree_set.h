@@ -0,0 +1,49 @@
+/* The declarations of src/ffi.rs, written and kept in step with it by hand. */
+
+#ifndef AVL_TREE_SET_H
+#define AVL_TREE_SET_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/* An opaque set of byte strings, ordered lexicographically. */
+typedef struct AvlSetBytes AvlSetBytes;
+
+/* An opaque set of `i64` values. */
+typedef struct AvlSetI64 AvlSetI64;
+
+/* Called once per value by `avl_set_i64_for_each`, in ascending order.
+ * Returning `false` stops the iteration. */
+typedef bool (*AvlSetI64Visitor)(int64_t value, void *data);
+
+/* Called once per value by `avl_set_bytes_for_each`, in ascending order,
+ * with a pointer that is only valid for the duration of the call.
+ * Returning `false` stops the iteration. */
+typedef bool (*AvlSetBytesVisitor)(const uint8_t *bytes, size_t len, void *data);
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+AvlSetI64 *avl_set_i64_new(void);
+void avl_set_i64_free(AvlSetI64 *set);
+bool avl_set_i64_insert(AvlSetI64 *set, int64_t value);
+bool avl_set_i64_contains(const AvlSetI64 *set, int64_t value);
+bool avl_set_i64_remove(AvlSetI64 *set, int64_t value);
+size_t avl_set_i64_len(const AvlSetI64 *set);
+void avl_set_i64_for_each(const AvlSetI64 *set, AvlSetI64Visitor visit, void *data);
+
+AvlSetBytes *avl_set_bytes_new(void);
+void avl_set_bytes_free(AvlSetBytes *set);
+bool avl_set_bytes_insert(AvlSetBytes *set, const uint8_t *bytes, size_t len);
+bool avl_set_bytes_contains(const AvlSetBytes *set, const uint8_t *bytes, size_t len);
+bool avl_set_bytes_remove(AvlSetBytes *set, const uint8_t *bytes, size_t len);
+size_t avl_set_bytes_len(const AvlSetBytes *set);
+void avl_set_bytes_for_each(const AvlSetBytes *set, AvlSetBytesVisitor visit, void *data);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* AVL_TREE_SET_H */
@@ -0,0 +1,750 @@
+use crate::balance::{self, Nodes};
+use crate::bounded::CapacityExceeded;
+use crate::Side;
+use alloc::vec::Vec;
+use core::array;
+use core::borrow::Borrow;
+use core::cmp::{self, Ordering};
+use core::convert::TryFrom;
+use core::fmt::{self, Debug};
+use core::iter::{FromIterator, FusedIterator};
+
+/// A link to a node in an `AvlTreeSetArena` or `AvlArraySet`, as an index
+/// into its nodes. Half the size of a `Box` on 64-bit targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct NodeRef(u32);
+
+#[derive(Debug, Clone)]
+struct ArenaNode<T> {
+    value: T,
+    left: Option<NodeRef>,
+    right: Option<NodeRef>,
+    height: usize,
+}
+
+impl<T> ArenaNode<T> {
+    fn child_mut(&mut self, side: Side) -> &mut Option<NodeRef> {
+        match side {
+            Side::Left => &mut self.left,
+            Side::Right => &mut self.right,
+        }
+    }
+}
+
+/// Where an `IndexTree` keeps its nodes: densely, in the first `len`
+/// slots. `IndexTree` is written once against this and shared by the `Vec`
+/// of an `AvlTreeSetArena` and the inline array of an `AvlArraySet`.
+trait Slots {
+    type Value;
+
+    fn len(&self) -> usize;
+
+    fn slot(&self, index: usize) -> &ArenaNode<Self::Value>;
+
+    fn slot_mut(&mut self, index: usize) -> &mut ArenaNode<Self::Value>;
+
+    /// Appends `node`. Callers check that there is room first.
+    fn push(&mut self, node: ArenaNode<Self::Value>);
+
+    /// Removes the node at `index`, moving the last node into its slot.
+    fn swap_remove(&mut self, index: usize) -> ArenaNode<Self::Value>;
+}
+
+impl<T> Slots for Vec<ArenaNode<T>> {
+    type Value = T;
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn slot(&self, index: usize) -> &ArenaNode<T> {
+        &self[index]
+    }
+
+    fn slot_mut(&mut self, index: usize) -> &mut ArenaNode<T> {
+        &mut self[index]
+    }
+
+    fn push(&mut self, node: ArenaNode<T>) {
+        self.push(node);
+    }
+
+    fn swap_remove(&mut self, index: usize) -> ArenaNode<T> {
+        self.swap_remove(index)
+    }
+}
+
+/// The inline nodes of an `AvlArraySet`. The first `len` slots are
+/// occupied and the rest are `None`.
+#[derive(Debug, Clone)]
+struct ArraySlots<T, const N: usize> {
+    slots: [Option<ArenaNode<T>>; N],
+    len: usize,
+}
+
+impl<T, const N: usize> Slots for ArraySlots<T, N> {
+    type Value = T;
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn slot(&self, index: usize) -> &ArenaNode<T> {
+        self.slots[..self.len][index].as_ref().unwrap()
+    }
+
+    fn slot_mut(&mut self, index: usize) -> &mut ArenaNode<T> {
+        self.slots[..self.len][index].as_mut().unwrap()
+    }
+
+    fn push(&mut self, node: ArenaNode<T>) {
+        self.slots[self.len] = Some(node);
+        self.len += 1;
+    }
+
+    fn swap_remove(&mut self, index: usize) -> ArenaNode<T> {
+        self.len -= 1;
+        self.slots.swap(index, self.len);
+        self.slots[self.len].take().unwrap()
+    }
+}
+
+/// An AVL tree whose nodes live in `S` and link to each other by index.
+#[derive(Debug, Clone)]
+struct IndexTree<S> {
+    nodes: S,
+    root: Option<NodeRef>,
+}
+
+/// An AVL tree set that keeps every node in one `Vec` and links them by
+/// index rather than boxing each node. Nodes stay densely packed: removing
+/// a value moves the last node into the freed slot and relinks it.
+///
+/// Because freed slots are reused, a set that keeps adding and removing
+/// values around a steady size (a sliding window, say) stops allocating
+/// once the vector has grown to fit it. `AvlTreeSet::with_node_pool` gets
+/// the same from boxed nodes, but only for values it drops itself; here
+/// `take`, which hands its value back, frees a slot for reuse too.
+#[derive(Debug, Clone)]
+pub struct AvlTreeSetArena<T: Ord> {
+    tree: IndexTree<Vec<ArenaNode<T>>>,
+}
+
+pub struct AvlTreeSetArenaIter<'a, T: Ord> {
+    tree: &'a IndexTree<Vec<ArenaNode<T>>>,
+    prev_nodes: Vec<NodeRef>,
+    remaining: usize,
+}
+
+impl<T: Ord> AvlTreeSetArena<T> {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Creates an empty set with room for `capacity` values before the node
+    /// vector has to grow.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { tree: IndexTree { nodes: Vec::with_capacity(capacity), root: None } }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.nodes.len()
+    }
+
+    /// The number of values the set can hold without allocating.
+    pub fn capacity(&self) -> usize {
+        self.tree.nodes.capacity()
+    }
+
+    /// Makes room for at least `additional` more values.
+    pub fn reserve(&mut self, additional: usize) {
+        self.tree.nodes.reserve(additional);
+    }
+
+    /// Releases the slots not currently holding a value.
+    pub fn shrink_to_fit(&mut self) {
+        self.tree.nodes.shrink_to_fit();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.nodes.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.tree.nodes.clear();
+        self.tree.root = None;
+    }
+
+    pub fn insert(&mut self, value: T) -> bool {
+        self.tree.insert(value)
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(value).is_some()
+    }
+
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.get(value)
+    }
+
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.take(value).is_some()
+    }
+
+    pub fn take<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.take(value)
+    }
+
+    pub fn iter(&self) -> AvlTreeSetArenaIter<'_, T> {
+        let mut iter = AvlTreeSetArenaIter { tree: &self.tree, prev_nodes: Vec::new(), remaining: self.len() };
+        iter.push_left_edge(self.tree.root);
+        iter
+    }
+}
+
+impl<T: Ord, S: Slots<Value = T>> IndexTree<S> {
+    fn insert(&mut self, value: T) -> bool {
+        let (root, inserted) = self.insert_below(self.root, value);
+        self.root = Some(root);
+
+        inserted
+    }
+
+    fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut current = self.root;
+
+        while let Some(node) = current {
+            let node = self.node(node);
+
+            match node.value.borrow().cmp(value) {
+                Ordering::Less => current = node.right,
+                Ordering::Equal => { return Some(&node.value); }
+                Ordering::Greater => current = node.left,
+            }
+        }
+
+        None
+    }
+
+    fn take<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (root, removed) = self.remove_below(self.root?, value);
+        self.root = root;
+
+        removed.map(|removed| self.free(removed))
+    }
+
+    fn node(&self, node: NodeRef) -> &ArenaNode<T> {
+        self.nodes.slot(node.0 as usize)
+    }
+
+    fn node_mut(&mut self, node: NodeRef) -> &mut ArenaNode<T> {
+        self.nodes.slot_mut(node.0 as usize)
+    }
+
+    fn height(&self, tree: Option<NodeRef>) -> usize {
+        tree.map_or(0, |node| self.node(node).height)
+    }
+
+    fn update_height(&mut self, node: NodeRef) {
+        let height = cmp::max(self.height(self.node(node).left), self.height(self.node(node).right)) + 1;
+        self.node_mut(node).height = height;
+    }
+
+    fn alloc(&mut self, value: T) -> NodeRef {
+        let node = NodeRef(u32::try_from(self.nodes.len()).expect("arena is full"));
+        self.nodes.push(ArenaNode { value, left: None, right: None, height: 1 });
+
+        node
+    }
+
+    /// Frees the slot of the detached `node` by moving the last node into
+    /// it, then repoints the link that referred to the moved node.
+    fn free(&mut self, node: NodeRef) -> T {
+        let removed = self.nodes.swap_remove(node.0 as usize);
+        let moved = NodeRef(self.nodes.len() as u32);
+
+        if node != moved {
+            *self.link_to(moved, node) = Some(node);
+        }
+
+        removed.value
+    }
+
+    /// Finds the link still pointing at `old` for the node that now lives at
+    /// `new`, by searching for that node's value from the root.
+    fn link_to<'a>(&'a mut self, old: NodeRef, new: NodeRef) -> &'a mut Option<NodeRef>
+    where
+        T: 'a,
+    {
+        let mut owner = None;
+        let mut current = self.root.unwrap();
+
+        while current != old {
+            let go_left = self.node(current).value > self.node(new).value;
+            owner = Some((current, go_left));
+
+            current = if go_left {
+                self.node(current).left.unwrap()
+            } else {
+                self.node(current).right.unwrap()
+            };
+        }
+
+        match owner {
+            None => &mut self.root,
+            Some((owner, true)) => &mut self.node_mut(owner).left,
+            Some((owner, false)) => &mut self.node_mut(owner).right,
+        }
+    }
+
+    /// Updates the height of `node` and rotates it back into balance,
+    /// returning the new root of its subtree.
+    fn rebalance(&mut self, node: NodeRef) -> NodeRef {
+        balance::rebalance(self, node).0
+    }
+
+    fn insert_below(&mut self, tree: Option<NodeRef>, value: T) -> (NodeRef, bool) {
+        let node = match tree {
+            Some(node) => node,
+            None => { return (self.alloc(value), true); }
+        };
+
+        let inserted = match self.node(node).value.cmp(&value) {
+            Ordering::Less => {
+                let (right, inserted) = self.insert_below(self.node(node).right, value);
+                self.node_mut(node).right = Some(right);
+                inserted
+            }
+            Ordering::Equal => false,
+            Ordering::Greater => {
+                let (left, inserted) = self.insert_below(self.node(node).left, value);
+                self.node_mut(node).left = Some(left);
+                inserted
+            }
+        };
+
+        if inserted {
+            (self.rebalance(node), true)
+        } else {
+            (node, false)
+        }
+    }
+
+    /// Removes `value` from the subtree at `node`, returning the subtree's
+    /// new root and the detached node that held the value.
+    fn remove_below<Q>(&mut self, node: NodeRef, value: &Q) -> (Option<NodeRef>, Option<NodeRef>)
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let removed = match self.node(node).value.borrow().cmp(value) {
+            Ordering::Less => {
+                let right = match self.node(node).right {
+                    Some(right) => right,
+                    None => { return (Some(node), None); }
+                };
+                let (right, removed) = self.remove_below(right, value);
+                self.node_mut(node).right = right;
+                removed
+            }
+            Ordering::Greater => {
+                let left = match self.node(node).left {
+                    Some(left) => left,
+                    None => { return (Some(node), None); }
+                };
+                let (left, removed) = self.remove_below(left, value);
+                self.node_mut(node).left = left;
+                removed
+            }
+            Ordering::Equal => {
+                let ArenaNode { left, right, .. } = *self.node(node);
+
+                let replacement = match (left, right) {
+                    (Some(_), Some(right)) => {
+                        let (right, successor) = self.remove_min(right);
+                        self.node_mut(successor).left = left;
+                        self.node_mut(successor).right = right;
+
+                        Some(self.rebalance(successor))
+                    }
+                    _ => left.or(right),
+                };
+
+                return (replacement, Some(node));
+            }
+        };
+
+        match removed {
+            Some(_) => (Some(self.rebalance(node)), removed),
+            None => (Some(node), None),
+        }
+    }
+
+    /// Detaches the minimum of the subtree at `node`, returning the
+    /// subtree's new root and the detached node.
+    fn remove_min(&mut self, node: NodeRef) -> (Option<NodeRef>, NodeRef) {
+        match self.node(node).left {
+            None => (self.node(node).right, node),
+            Some(left) => {
+                let (left, min) = self.remove_min(left);
+                self.node_mut(node).left = left;
+
+                (Some(self.rebalance(node)), min)
+            }
+        }
+    }
+}
+
+/// The arenas link nodes by index, so they rotate through the same
+/// `balance::rebalance` as boxed trees by relinking indices in their slots.
+impl<T: Ord, S: Slots<Value = T>> Nodes<NodeRef> for IndexTree<S> {
+    fn child_height(&self, node: &NodeRef, side: Side) -> usize {
+        let node = self.node(*node);
+
+        match side {
+            Side::Left => self.height(node.left),
+            Side::Right => self.height(node.right),
+        }
+    }
+
+    fn take_child(&mut self, node: &mut NodeRef, side: Side) -> Option<NodeRef> {
+        self.node_mut(*node).child_mut(side).take()
+    }
+
+    fn set_child(&mut self, node: &mut NodeRef, side: Side, child: Option<NodeRef>) {
+        *self.node_mut(*node).child_mut(side) = child;
+    }
+
+    fn update(&mut self, node: &mut NodeRef) {
+        self.update_height(*node);
+    }
+}
+
+impl<T: Ord> Default for AvlTreeSetArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T: Ord> AvlTreeSetArenaIter<'a, T> {
+    fn push_left_edge(&mut self, mut current: Option<NodeRef>) {
+        while let Some(node) = current {
+            self.prev_nodes.push(node);
+            current = self.tree.node(node).left;
+        }
+    }
+}
+
+impl<'a, T: Ord> Iterator for AvlTreeSetArenaIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tree = self.tree;
+        let node = tree.node(self.prev_nodes.pop()?);
+        self.push_left_edge(node.right);
+        self.remaining -= 1;
+
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T: Ord> ExactSizeIterator for AvlTreeSetArenaIter<'a, T> {}
+
+impl<'a, T: Ord> FusedIterator for AvlTreeSetArenaIter<'a, T> {}
+
+impl<'a, T: Ord> IntoIterator for &'a AvlTreeSetArena<T> {
+    type Item = &'a T;
+    type IntoIter = AvlTreeSetArenaIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Ord> FromIterator<T> for AvlTreeSetArena<T> {
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T: Ord> Extend<T> for AvlTreeSetArena<T> {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+/// The most nodes on any path from the root of an `AvlArraySet`. An AVL
+/// tree of height h holds at least Fib(h + 2) - 1 nodes, so this covers
+/// every set its `u32` links can address.
+const MAX_ARRAY_HEIGHT: usize = 48;
+
+/// A set of at most `N` values that never allocates: its nodes live in an
+/// inline array, linked by index through the `IndexTree` an
+/// `AvlTreeSetArena` uses, which makes it usable on targets without a heap.
+/// Slots are `Option`s rather than `MaybeUninit`s, costing a tag per slot,
+/// because the crate doesn't use `unsafe` outside its FFI bindings.
+#[derive(Clone)]
+pub struct AvlArraySet<T: Ord, const N: usize> {
+    tree: IndexTree<ArraySlots<T, N>>,
+}
+
+pub struct AvlArraySetIter<'a, T: Ord, const N: usize> {
+    tree: &'a IndexTree<ArraySlots<T, N>>,
+    prev_nodes: [Option<NodeRef>; MAX_ARRAY_HEIGHT],
+    depth: usize,
+    remaining: usize,
+}
+
+impl<T: Ord, const N: usize> AvlArraySet<T, N> {
+    pub fn new() -> Self {
+        let slots = ArraySlots { slots: array::from_fn(|_| None), len: 0 };
+        Self { tree: IndexTree { nodes: slots, root: None } }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.nodes.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of values the set can hold, `N`.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == N
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Inserts `value`, returning whether it was new, or hands it back if
+    /// the set is full and doesn't hold it yet.
+    pub fn insert(&mut self, value: T) -> Result<bool, CapacityExceeded<T>> {
+        if self.is_full() && !self.contains(&value) {
+            return Err(CapacityExceeded(value));
+        }
+
+        Ok(self.tree.insert(value))
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(value).is_some()
+    }
+
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.get(value)
+    }
+
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.take(value).is_some()
+    }
+
+    pub fn take<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.take(value)
+    }
+
+    pub fn iter(&self) -> AvlArraySetIter<'_, T, N> {
+        let mut iter = AvlArraySetIter {
+            tree: &self.tree,
+            prev_nodes: [None; MAX_ARRAY_HEIGHT],
+            depth: 0,
+            remaining: self.len(),
+        };
+
+        iter.push_left_edge(self.tree.root);
+        iter
+    }
+}
+
+impl<T: Ord, const N: usize> Default for AvlArraySet<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord + Debug, const N: usize> Debug for AvlArraySet<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T: Ord, const N: usize> AvlArraySetIter<'a, T, N> {
+    fn push_left_edge(&mut self, mut current: Option<NodeRef>) {
+        while let Some(node) = current {
+            self.prev_nodes[self.depth] = Some(node);
+            self.depth += 1;
+            current = self.tree.node(node).left;
+        }
+    }
+}
+
+impl<'a, T: Ord, const N: usize> Iterator for AvlArraySetIter<'a, T, N> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.depth = self.depth.checked_sub(1)?;
+
+        let tree = self.tree;
+        let node = tree.node(self.prev_nodes[self.depth].take()?);
+        self.push_left_edge(node.right);
+        self.remaining -= 1;
+
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T: Ord, const N: usize> ExactSizeIterator for AvlArraySetIter<'a, T, N> {}
+
+impl<'a, T: Ord, const N: usize> FusedIterator for AvlArraySetIter<'a, T, N> {}
+
+impl<'a, T: Ord, const N: usize> IntoIterator for &'a AvlArraySet<T, N> {
+    type Item = &'a T;
+    type IntoIter = AvlArraySetIter<'a, T, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod properties {
+    use super::*;
+    use itertools::equal;
+    use std::collections::BTreeSet;
+
+    fn is_balanced<T: Ord, S: Slots<Value = T>>(tree: &IndexTree<S>, subtree: Option<NodeRef>) -> bool {
+        let node = match subtree {
+            Some(node) => node,
+            None => { return true; }
+        };
+        let ArenaNode { left, right, height, .. } = *tree.node(node);
+
+        height == cmp::max(tree.height(left), tree.height(right)) + 1
+            && tree.height(left).abs_diff(tree.height(right)) <= 1
+            && is_balanced(tree, left)
+            && is_balanced(tree, right)
+    }
+
+    #[quickcheck]
+    fn insert_parity(input: Vec<u16>) -> bool {
+        let mut arena_set = AvlTreeSetArena::new();
+        let mut btree_set = BTreeSet::new();
+
+        input.into_iter().all(|x| arena_set.insert(x) == btree_set.insert(x))
+            && arena_set.len() == btree_set.len()
+            && equal(arena_set.iter(), btree_set.iter())
+            && is_balanced(&arena_set.tree, arena_set.tree.root)
+    }
+
+    #[quickcheck]
+    fn remove_parity(input: Vec<u8>, removals: Vec<u8>) -> bool {
+        let mut arena_set = input.iter().cloned().collect::<AvlTreeSetArena<_>>();
+        let mut btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        removals.iter().all(|x| {
+            arena_set.take(x) == btree_set.take(x)
+                && arena_set.contains(x) == btree_set.contains(x)
+                && is_balanced(&arena_set.tree, arena_set.tree.root)
+        }) && equal(arena_set.iter(), btree_set.iter())
+    }
+
+    #[quickcheck]
+    fn array_set_parity(changes: Vec<(bool, u8)>) -> bool {
+        let mut array_set = AvlArraySet::<_, 16>::new();
+        let mut btree_set = BTreeSet::new();
+
+        changes.iter().all(|&(insert, x)| {
+            let consistent = if !insert {
+                array_set.remove(&x) == btree_set.remove(&x)
+            } else if btree_set.len() < 16 || btree_set.contains(&x) {
+                array_set.insert(x) == Ok(btree_set.insert(x))
+            } else {
+                array_set.insert(x) == Err(CapacityExceeded(x))
+            };
+
+            consistent && array_set.len() == btree_set.len() && is_balanced(&array_set.tree, array_set.tree.root)
+        }) && equal(array_set.iter(), btree_set.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sliding_window_reuses_slots() {
+        let mut set = AvlTreeSetArena::with_capacity(16);
+
+        for value in 0..1000 {
+            if value >= 16 {
+                set.remove(&(value - 16));
+            }
+            set.insert(value);
+        }
+
+        assert_eq!(set.len(), 16);
+        assert_eq!(set.capacity(), 16);
+        assert!(set.iter().cloned().eq(984..1000));
+
+        set.remove(&999);
+        set.shrink_to_fit();
+        assert_eq!(set.capacity(), 15);
+    }
+}
@@ -0,0 +1,752 @@
+use crate::{
+    above_start, below_end, find_node, insert_into, remove_by, AvlNode, AvlTree, AvlTreeSetIter, Metrics, Natural,
+};
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
+use core::iter::FromIterator;
+use core::ops::{Add, Bound, RangeBounds, Sub};
+
+/// Per-node metadata summarising a subtree, such as a sum, a count or a
+/// maximum. Every `AvlNode` carries one, which is recomputed bottom-up
+/// along with its height whenever an insertion, removal or rotation changes
+/// the subtree below it. Plain sets carry `()`.
+pub trait Augment<T> {
+    /// Combines a node's value with the summaries of its children.
+    fn augment(value: &T, left: Option<&Self>, right: Option<&Self>) -> Self;
+}
+
+impl<T> Augment<T> for () {
+    fn augment(_value: &T, _left: Option<&Self>, _right: Option<&Self>) -> Self {}
+}
+
+/// An augmentation whose summaries of two adjacent runs of values can be
+/// merged, making it a monoid over sorted runs. This is what range
+/// aggregation needs on top of `Augment`.
+pub trait RangeAugment<T>: Augment<T> + Clone {
+    /// Merges the summary of a run with the summary of the run right after
+    /// it. Must be associative.
+    fn combine(left: &Self, right: &Self) -> Self;
+}
+
+/// Sums the values in each subtree, for `sum_in_range`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sum<T>(pub T);
+
+impl<T: Copy + Add<Output = T>> Augment<T> for Sum<T> {
+    fn augment(value: &T, left: Option<&Self>, right: Option<&Self>) -> Self {
+        let sum = left.map_or(*value, |left| left.0 + *value);
+        Sum(right.map_or(sum, |right| sum + right.0))
+    }
+}
+
+impl<T: Copy + Add<Output = T>> RangeAugment<T> for Sum<T> {
+    fn combine(left: &Self, right: &Self) -> Self {
+        Sum(left.0 + right.0)
+    }
+}
+
+/// A value that carries a weight, for sets augmented with `WeightSum`.
+pub trait Weighted {
+    type Weight: Copy + Add<Output = Self::Weight>;
+
+    fn weight(&self) -> Self::Weight;
+}
+
+/// A value paired with a weight. It is ordered and compared by `value`
+/// alone, so a set holds at most one weight per value.
+#[derive(Debug, Clone, Copy)]
+pub struct WeightedValue<T, W> {
+    pub value: T,
+    pub weight: W,
+}
+
+impl<T: PartialEq, W> PartialEq for WeightedValue<T, W> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T: Eq, W> Eq for WeightedValue<T, W> {}
+
+impl<T: PartialOrd, W> PartialOrd for WeightedValue<T, W> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl<T: Ord, W> Ord for WeightedValue<T, W> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value.cmp(&other.value)
+    }
+}
+
+impl<T, W> Borrow<T> for WeightedValue<T, W> {
+    fn borrow(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, W: Copy + Add<Output = W>> Weighted for WeightedValue<T, W> {
+    type Weight = W;
+
+    fn weight(&self) -> W {
+        self.weight
+    }
+}
+
+/// Sums the weights in each subtree, for `select_by_weight` and
+/// `total_weight_in_range`. Any numeric type works as a weight, including
+/// arbitrary precision ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeightSum<W>(pub W);
+
+impl<T: Weighted<Weight = W>, W: Copy + Add<Output = W>> Augment<T> for WeightSum<W> {
+    fn augment(value: &T, left: Option<&Self>, right: Option<&Self>) -> Self {
+        let sum = left.map_or(value.weight(), |left| left.0 + value.weight());
+        WeightSum(right.map_or(sum, |right| sum + right.0))
+    }
+}
+
+impl<T: Weighted<Weight = W>, W: Copy + Add<Output = W>> RangeAugment<T> for WeightSum<W> {
+    fn combine(left: &Self, right: &Self) -> Self {
+        WeightSum(left.0 + right.0)
+    }
+}
+
+/// A content hash of each subtree, for comparing sets held in different
+/// places. It is a polynomial hash of the values in sorted order, built up
+/// from each node's value and its children's hashes like a Merkle tree, so
+/// it depends only on the values and never on the shape of the tree: equal
+/// sets have equal hashes however they were built. `aggregate_range` gives
+/// the hash of any range, for narrowing down where two replicas differ.
+///
+/// Values are hashed with FNV-1a, feeding every integer in as little-endian
+/// bytes and `usize` and `isize`, such as the length prefixes of strings
+/// and slices, as 64 bits wide. The hashes are then the same across runs
+/// and platforms for a given Rust version, but a type's `Hash` impl may
+/// change between versions, so replicas should be built alike.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MerkleHash {
+    hash: u64,
+    /// `MERKLE_BASE` to the power of the number of values covered.
+    power: u64,
+}
+
+const MERKLE_BASE: u64 = 0x9e37_79b9_7f4a_7c15;
+
+impl MerkleHash {
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    fn of_value<T: Hash>(value: &T) -> Self {
+        let mut hasher = Fnv1a(0xcbf2_9ce4_8422_2325);
+        value.hash(&mut hasher);
+        MerkleHash { hash: hasher.finish(), power: MERKLE_BASE }
+    }
+
+    fn then(&self, next: &Self) -> Self {
+        MerkleHash {
+            hash: self.hash.wrapping_add(next.hash.wrapping_mul(self.power)),
+            power: self.power.wrapping_mul(next.power),
+        }
+    }
+}
+
+impl<T: Hash> Augment<T> for MerkleHash {
+    fn augment(value: &T, left: Option<&Self>, right: Option<&Self>) -> Self {
+        let hash = Self::of_value(value);
+        let hash = left.map_or(hash, |left| left.then(&hash));
+        right.map_or(hash, |right| hash.then(right))
+    }
+}
+
+impl<T: Hash> RangeAugment<T> for MerkleHash {
+    fn combine(left: &Self, right: &Self) -> Self {
+        left.then(right)
+    }
+}
+
+struct Fnv1a(u64);
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_i16(&mut self, n: i16) {
+        self.write_u16(n as u16);
+    }
+
+    fn write_i32(&mut self, n: i32) {
+        self.write_u32(n as u32);
+    }
+
+    fn write_i64(&mut self, n: i64) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_i128(&mut self, n: i128) {
+        self.write_u128(n as u128);
+    }
+
+    fn write_isize(&mut self, n: isize) {
+        self.write_i64(n as i64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// An AVL tree set whose nodes each carry an `A` summarising their
+/// subtree, kept up to date by the same balancing code as `AvlTreeSet`.
+/// Descend from `root` to answer queries over the summaries, e.g. prefix
+/// sums or weighted sampling.
+#[derive(Debug, Clone)]
+pub struct AugmentedAvlTreeSet<T: Ord, A: Augment<T>> {
+    root: AvlTree<T, A>,
+    len: usize,
+    metrics: Metrics,
+}
+
+pub type AugmentedAvlTreeSetIter<'a, T, A> = AvlTreeSetIter<'a, T, A>;
+
+impl<T: Ord, A: Augment<T>> AugmentedAvlTreeSet<T, A> {
+    pub fn new() -> Self {
+        Self { root: None, len: 0, metrics: Metrics::default() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+    }
+
+    pub fn root(&self) -> Option<&AvlNode<T, A>> {
+        self.root.as_deref()
+    }
+
+    /// The summary of the whole set, or `None` if it is empty.
+    pub fn summary(&self) -> Option<&A> {
+        self.root().map(AvlNode::augment)
+    }
+
+    pub fn insert(&mut self, value: T) -> bool {
+        let inserted = insert_into(&mut self.root, value, &Natural, &mut self.metrics);
+        self.len += inserted as usize;
+
+        inserted
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(value).is_some()
+    }
+
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        find_node(&self.root, |current| current.borrow().cmp(value)).map(AvlNode::value)
+    }
+
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.take(value).is_some()
+    }
+
+    pub fn take<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let removed = remove_by(&mut self.root, &|current: &T| current.borrow().cmp(value), &mut self.metrics);
+        self.len -= removed.is_some() as usize;
+
+        removed
+    }
+
+    pub fn iter(&self) -> AugmentedAvlTreeSetIter<'_, T, A> {
+        AvlTreeSetIter::new(&self.root, self.len)
+    }
+}
+
+impl<T: Ord, A: RangeAugment<T>> AugmentedAvlTreeSet<T, A> {
+    /// Summarises the values in `range` in O(log n) by combining the stored
+    /// summaries of the O(log n) subtrees that exactly cover it, or returns
+    /// `None` if the range holds no values.
+    pub fn aggregate_range<Q, R>(&self, range: R) -> Option<A>
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        aggregate(&self.root, range.start_bound(), range.end_bound())
+    }
+}
+
+impl<T: Ord + Copy + Add<Output = T> + Default> AugmentedAvlTreeSet<T, Sum<T>> {
+    /// The sum of the values in `range` in O(log n), zero if it holds none.
+    pub fn sum_in_range<Q, R>(&self, range: R) -> T
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        self.aggregate_range(range).map_or_else(T::default, |sum| sum.0)
+    }
+}
+
+impl<T, W> AugmentedAvlTreeSet<T, WeightSum<W>>
+where
+    T: Ord + Weighted<Weight = W>,
+    W: Copy + Default + PartialOrd + Add<Output = W> + Sub<Output = W>,
+{
+    /// The sum of every value's weight in O(1), zero for an empty set.
+    pub fn total_weight(&self) -> W {
+        self.summary().map_or_else(W::default, |sum| sum.0)
+    }
+
+    /// The sum of the weights of the values in `range` in O(log n).
+    pub fn total_weight_in_range<Q, R>(&self, range: R) -> W
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        self.aggregate_range(range).map_or_else(W::default, |sum| sum.0)
+    }
+
+    /// The value at which the running total of the weights, in sorted order,
+    /// passes `weight`, in O(log n): the value whose weights before it sum
+    /// to at most `weight` and to more than `weight` with its own. Picking
+    /// `weight` uniformly from `0..total_weight()` samples the values in
+    /// proportion to their weights. Returns `None` if `weight` is at least
+    /// the total weight.
+    pub fn select_by_weight(&self, mut weight: W) -> Option<&T> {
+        let mut current = &self.root;
+
+        while let Some(node) = current {
+            let left = node.left.as_ref().map_or_else(W::default, |left| left.augment.0);
+
+            if weight < left {
+                current = &node.left;
+                continue;
+            }
+
+            weight = weight - left;
+            let own = node.value.weight();
+
+            if weight < own {
+                return Some(&node.value);
+            }
+
+            weight = weight - own;
+            current = &node.right;
+        }
+
+        None
+    }
+}
+
+impl<T: Ord + Hash> AugmentedAvlTreeSet<T, MerkleHash> {
+    /// The hash of the whole set in O(1), kept up to date on every
+    /// mutation. Equal sets hash equally. The empty set hashes to zero.
+    pub fn structural_hash(&self) -> u64 {
+        self.summary().map_or(0, MerkleHash::hash)
+    }
+
+    /// The changes that turn `self` into `other`. Each subtree of `self` is
+    /// compared by hash against the same range of `other`, and only ranges
+    /// whose hashes differ are walked, so similar sets are reconciled in
+    /// time proportional to the number of differences, times O(log² n).
+    pub fn delta<'a>(&'a self, other: &'a Self) -> TreeDelta<'a, T> {
+        let mut delta = TreeDelta { inserted: Vec::new(), removed: Vec::new() };
+        push_delta(&self.root, Bound::Unbounded, Bound::Unbounded, &other.root, &mut delta);
+        delta
+    }
+}
+
+/// The values by which two sets differ, returned by
+/// `AugmentedAvlTreeSet::delta`, each in ascending order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeDelta<'a, T> {
+    /// Values only in the other set, to be inserted into this one.
+    pub inserted: Vec<&'a T>,
+    /// Values only in this set, to be removed from it.
+    pub removed: Vec<&'a T>,
+}
+
+impl<'a, T> TreeDelta<'a, T> {
+    pub fn is_empty(&self) -> bool {
+        self.inserted.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Adds the differences between `tree` and the values of `other` between
+/// `start` and `end`, which bound every value in `tree`.
+fn push_delta<'a, T: Ord + Hash>(
+    tree: &'a AvlTree<T, MerkleHash>,
+    start: Bound<&'a T>,
+    end: Bound<&'a T>,
+    other: &'a AvlTree<T, MerkleHash>,
+    delta: &mut TreeDelta<'a, T>,
+) {
+    let node = match tree {
+        Some(node) => node,
+        None => {
+            push_range(other, start, end, &mut delta.inserted);
+            return;
+        }
+    };
+
+    if aggregate(other, start, end).as_ref() == Some(&node.augment) {
+        return;
+    }
+
+    push_delta(&node.left, start, Bound::Excluded(&node.value), other, delta);
+    if find_node(other, |current| current.cmp(&node.value)).is_none() {
+        delta.removed.push(&node.value);
+    }
+    push_delta(&node.right, Bound::Excluded(&node.value), end, other, delta);
+}
+
+/// Pushes the values of `tree` between `start` and `end` in ascending
+/// order, skipping subtrees that lie outside the range.
+fn push_range<'a, T: Ord>(tree: &'a AvlTree<T, MerkleHash>, start: Bound<&T>, end: Bound<&T>, out: &mut Vec<&'a T>) {
+    if let Some(node) = tree {
+        let above = above_start(&node.value, start, &Natural);
+        let below = below_end(&node.value, end, &Natural);
+
+        if above {
+            push_range(&node.left, start, end, out);
+        }
+        if above && below {
+            out.push(&node.value);
+        }
+        if below {
+            push_range(&node.right, start, end, out);
+        }
+    }
+}
+
+impl<T: Ord, A: Augment<T>> Default for AugmentedAvlTreeSet<T, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T: Ord, A: Augment<T>> IntoIterator for &'a AugmentedAvlTreeSet<T, A> {
+    type Item = &'a T;
+    type IntoIter = AugmentedAvlTreeSetIter<'a, T, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Ord, A: Augment<T>> FromIterator<T> for AugmentedAvlTreeSet<T, A> {
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T: Ord, A: Augment<T>> Extend<T> for AugmentedAvlTreeSet<T, A> {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+fn combine_all<A: Clone, F: Fn(&A, &A) -> A>(parts: &[Option<&A>], combine: F) -> Option<A> {
+    parts.iter().flatten().fold(None, |acc: Option<A>, part| match acc {
+        Some(acc) => Some(combine(&acc, part)),
+        None => Some((*part).clone()),
+    })
+}
+
+/// Combines the summaries of the values in `tree` between `start` and `end`.
+/// Descends until a node falls inside the range, then splits into the
+/// suffix of its left subtree and the prefix of its right one.
+fn aggregate<T, A, Q>(mut tree: &AvlTree<T, A>, start: Bound<&Q>, end: Bound<&Q>) -> Option<A>
+where
+    T: Borrow<Q>,
+    A: RangeAugment<T>,
+    Q: Ord + ?Sized,
+{
+    while let Some(node) = tree {
+        let value = node.value.borrow();
+
+        if !above_start(value, start, &Natural) {
+            tree = &node.right;
+        } else if !below_end(value, end, &Natural) {
+            tree = &node.left;
+        } else {
+            let own = A::augment(&node.value, None, None);
+            let left = aggregate_from(&node.left, start);
+            let right = aggregate_to(&node.right, end);
+
+            return combine_all(&[left.as_ref(), Some(&own), right.as_ref()], A::combine);
+        }
+    }
+
+    None
+}
+
+/// Combines the summaries of the values in `tree` not below `start`.
+fn aggregate_from<T, A, Q>(mut tree: &AvlTree<T, A>, start: Bound<&Q>) -> Option<A>
+where
+    T: Borrow<Q>,
+    A: RangeAugment<T>,
+    Q: Ord + ?Sized,
+{
+    let mut parts = Vec::new();
+
+    while let Some(node) = tree {
+        if above_start(node.value.borrow(), start, &Natural) {
+            let own = A::augment(&node.value, None, None);
+            parts.push(combine_all(&[Some(&own), node.right.as_ref().map(|right| &right.augment)], A::combine));
+            tree = &node.left;
+        } else {
+            tree = &node.right;
+        }
+    }
+
+    let parts = parts.iter().rev().map(Option::as_ref).collect::<Vec<_>>();
+    combine_all(&parts, A::combine)
+}
+
+/// Combines the summaries of the values in `tree` not above `end`.
+fn aggregate_to<T, A, Q>(mut tree: &AvlTree<T, A>, end: Bound<&Q>) -> Option<A>
+where
+    T: Borrow<Q>,
+    A: RangeAugment<T>,
+    Q: Ord + ?Sized,
+{
+    let mut parts = Vec::new();
+
+    while let Some(node) = tree {
+        if below_end(node.value.borrow(), end, &Natural) {
+            let own = A::augment(&node.value, None, None);
+            parts.push(combine_all(&[node.left.as_ref().map(|left| &left.augment), Some(&own)], A::combine));
+            tree = &node.right;
+        } else {
+            tree = &node.left;
+        }
+    }
+
+    combine_all(&parts.iter().map(Option::as_ref).collect::<Vec<_>>(), A::combine)
+}
+
+#[cfg(test)]
+pub(crate) fn is_balanced<T, A: Augment<T> + PartialEq>(tree: &AvlTree<T, A>) -> bool {
+    let node = match tree {
+        Some(node) => node,
+        None => { return true; }
+    };
+    let augment = A::augment(
+        &node.value,
+        node.left.as_ref().map(|left| &left.augment),
+        node.right.as_ref().map(|right| &right.augment),
+    );
+
+    node.height == node.left_height().max(node.right_height()) + 1
+        && node.balance_factor().abs() <= 1
+        && node.augment == augment
+        && is_balanced(&node.left)
+        && is_balanced(&node.right)
+}
+
+#[cfg(test)]
+mod properties {
+    use super::*;
+    use core::cmp;
+    use itertools::equal;
+    use std::collections::BTreeSet;
+
+    /// The number and sum of the values in a subtree.
+    #[derive(Debug, PartialEq)]
+    struct CountSum {
+        count: usize,
+        sum: u64,
+    }
+
+    impl Augment<u16> for CountSum {
+        fn augment(value: &u16, left: Option<&Self>, right: Option<&Self>) -> Self {
+            [left, right].iter().flatten().fold(CountSum { count: 1, sum: u64::from(*value) }, |acc, child| {
+                CountSum { count: acc.count + child.count, sum: acc.sum + child.sum }
+            })
+        }
+    }
+
+    /// Sums the values less than `bound` by descending from the root.
+    fn prefix_sum(set: &AugmentedAvlTreeSet<u16, CountSum>, bound: u16) -> u64 {
+        let mut sum = 0;
+        let mut current = set.root();
+
+        while let Some(node) = current {
+            if *node.value() < bound {
+                sum += u64::from(*node.value()) + node.left().as_ref().map_or(0, |left| left.augment().sum);
+                current = node.right().as_deref();
+            } else {
+                current = node.left().as_deref();
+            }
+        }
+
+        sum
+    }
+
+    #[quickcheck]
+    fn summary_parity(input: Vec<u16>, removals: Vec<u16>, bound: u16) -> bool {
+        let mut set = input.iter().cloned().collect::<AugmentedAvlTreeSet<_, CountSum>>();
+        let mut btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        let removed = removals.iter().all(|x| set.remove(x) == btree_set.remove(x) && is_balanced(&set.root));
+        let count = set.summary().map_or(0, |summary| summary.count);
+
+        removed
+            && count == btree_set.len()
+            && set.len() == btree_set.len()
+            && prefix_sum(&set, bound) == btree_set.range(..bound).map(|&x| u64::from(x)).sum::<u64>()
+            && equal(set.iter(), btree_set.iter())
+    }
+
+    #[quickcheck]
+    fn structural_hash_ignores_shape(input: Vec<u16>, removals: Vec<u16>) -> bool {
+        let mut grown = AugmentedAvlTreeSet::<_, MerkleHash>::new();
+        grown.extend(input.iter().cloned());
+
+        for x in &removals {
+            grown.remove(x);
+        }
+
+        let remaining = input.iter().filter(|x| !removals.contains(x)).cloned().collect::<BTreeSet<_>>();
+        let descending = remaining.iter().rev().cloned().collect::<AugmentedAvlTreeSet<_, MerkleHash>>();
+        let whole = grown.aggregate_range(..).map_or(0, |hash| hash.hash());
+
+        grown.structural_hash() == descending.structural_hash() && grown.structural_hash() == whole
+    }
+
+    #[quickcheck]
+    fn delta_parity(input: Vec<u8>, other: Vec<u8>) -> bool {
+        let (set, other_set) = (
+            input.iter().cloned().collect::<AugmentedAvlTreeSet<_, MerkleHash>>(),
+            other.iter().cloned().collect::<AugmentedAvlTreeSet<_, MerkleHash>>(),
+        );
+        let (btree_set, other_btree_set) = (input.iter().collect::<BTreeSet<_>>(), other.iter().collect::<BTreeSet<_>>());
+        let delta = set.delta(&other_set);
+
+        equal(delta.inserted, other_btree_set.difference(&btree_set).cloned())
+            && equal(delta.removed, btree_set.difference(&other_btree_set).cloned())
+            && set.delta(&set).is_empty()
+    }
+
+    #[quickcheck]
+    fn select_by_weight_parity(input: Vec<(u8, u8)>, weight: u16) -> bool {
+        let set = input
+            .iter()
+            .map(|&(value, weight)| WeightedValue { value, weight: u32::from(weight) })
+            .collect::<AugmentedAvlTreeSet<_, WeightSum<u32>>>();
+        let mut seen = BTreeSet::new();
+        let sorted = input.iter().filter(|&&(value, _)| seen.insert(value)).cloned().collect::<BTreeSet<_>>();
+
+        let mut before = 0;
+        let expected = sorted.iter().find(|&&(_, own)| {
+            before += u32::from(own);
+            u32::from(weight) < before
+        });
+
+        set.select_by_weight(u32::from(weight)).map(|selected| selected.value) == expected.map(|&(value, _)| value)
+            && set.total_weight() == sorted.iter().map(|&(_, own)| u32::from(own)).sum::<u32>()
+            && set.total_weight_in_range::<u8, _>(..10) == sorted.iter().filter(|&&(value, _)| value < 10).map(|&(_, own)| u32::from(own)).sum::<u32>()
+    }
+
+    #[quickcheck]
+    fn sum_in_range_parity(input: Vec<u16>, start: u16, end: u16) -> bool {
+        let set = input.iter().map(|&x| u64::from(x)).collect::<AugmentedAvlTreeSet<_, Sum<u64>>>();
+        let btree_set = input.iter().map(|&x| u64::from(x)).collect::<BTreeSet<_>>();
+        let (start, end) = (u64::from(cmp::min(start, end)), u64::from(cmp::max(start, end)));
+
+        set.sum_in_range(start..end) == btree_set.range(start..end).sum::<u64>()
+            && set.sum_in_range(start..=end) == btree_set.range(start..=end).sum::<u64>()
+            && set.sum_in_range(..end) == btree_set.range(..end).sum::<u64>()
+            && set.sum_in_range(start..) == btree_set.range(start..).sum::<u64>()
+            && set.aggregate_range(start..start).is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn structural_hash_tells_sets_apart() {
+        let hash = |values: &[&str]| values.iter().collect::<AugmentedAvlTreeSet<_, MerkleHash>>().structural_hash();
+
+        assert_eq!(hash(&[]), 0);
+        assert_eq!(hash(&["a", "b", "c"]), hash(&["c", "a", "b"]));
+        assert_ne!(hash(&["a", "b", "c"]), hash(&["a", "b"]));
+        assert_ne!(hash(&["a", "b"]), hash(&["ab"]));
+    }
+
+    #[test]
+    fn value_hashes_ignore_pointer_width() {
+        let mut bytes = Fnv1a(0xcbf2_9ce4_8422_2325);
+        bytes.write(&[2, 0, 0, 0, 0, 0, 0, 0, 7, 9]);
+
+        assert_eq!(MerkleHash::of_value(&vec![7u8, 9]).hash(), bytes.finish());
+    }
+
+    #[test]
+    fn delta_skips_equal_ranges() {
+        let set = (0..1000).collect::<AugmentedAvlTreeSet<u32, MerkleHash>>();
+        let mut other = set.clone();
+        other.remove(&10);
+        other.insert(2000);
+
+        let delta = set.delta(&other);
+        assert_eq!((delta.inserted, delta.removed), (vec![&2000], vec![&10]));
+    }
+}
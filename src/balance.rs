@@ -0,0 +1,72 @@
+//! The AVL balancing shared by every tree in the crate, written against
+//! `Nodes`, which says how a tree links its nodes together. Boxed trees
+//! link them by owning `Link`s and the arenas by indices into their slots,
+//! so each walks down and frees nodes in its own way, but both rotate and
+//! rebalance through `rebalance` here.
+
+use crate::{Rotation, Side};
+
+/// Where the nodes linked by `L` live, and what `rebalance` needs from
+/// them: their children's heights, a way to unlink and relink a child, and
+/// a way to refresh what a node caches about its subtree.
+pub(crate) trait Nodes<L> {
+    fn child_height(&self, node: &L, side: Side) -> usize;
+
+    fn take_child(&mut self, node: &mut L, side: Side) -> Option<L>;
+
+    fn set_child(&mut self, node: &mut L, side: Side, child: Option<L>);
+
+    /// Recomputes the height of `node`, and anything else it caches, from
+    /// its children.
+    fn update(&mut self, node: &mut L);
+
+    /// Called once for each node `rebalance` rotates back into balance.
+    fn rebalanced(&mut self, _rotation: Rotation) {}
+}
+
+fn balance_factor<L, N: Nodes<L>>(nodes: &N, node: &L) -> isize {
+    nodes.child_height(node, Side::Left) as isize - nodes.child_height(node, Side::Right) as isize
+}
+
+/// Rotates `node` down towards `side`, raising its child on the other side
+/// in its place, and returns that child.
+fn rotate<L, N: Nodes<L>>(nodes: &mut N, mut node: L, side: Side) -> L {
+    let mut raised = nodes.take_child(&mut node, side.opposite()).expect("a rotation raises a child");
+    let inner = nodes.take_child(&mut raised, side);
+
+    nodes.set_child(&mut node, side.opposite(), inner);
+    nodes.update(&mut node);
+    nodes.set_child(&mut raised, side, Some(node));
+    nodes.update(&mut raised);
+
+    raised
+}
+
+/// Refreshes `node` after a change below it and rotates it back into
+/// balance, returning the new root of its subtree and the rotation applied,
+/// if any. The double rotations first rotate the child on the heavy side.
+pub(crate) fn rebalance<L, N: Nodes<L>>(nodes: &mut N, mut node: L) -> (L, Option<Rotation>) {
+    nodes.update(&mut node);
+
+    let (heavy, single, double) = match balance_factor(nodes, &node) {
+        -2 => (Side::Right, Rotation::Left, Rotation::RightLeft),
+        2 => (Side::Left, Rotation::Right, Rotation::LeftRight),
+        _ => return (node, None),
+    };
+
+    let mut child = nodes.take_child(&mut node, heavy).expect("the heavy side has a child");
+    let leans_inward = match heavy {
+        Side::Left => balance_factor(nodes, &child) < 0,
+        Side::Right => balance_factor(nodes, &child) > 0,
+    };
+
+    if leans_inward {
+        child = rotate(nodes, child, heavy);
+    }
+    nodes.set_child(&mut node, heavy, Some(child));
+
+    let rotation = if leans_inward { double } else { single };
+    nodes.rebalanced(rotation);
+
+    (rotate(nodes, node, heavy.opposite()), Some(rotation))
+}
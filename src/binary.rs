@@ -0,0 +1,274 @@
+//! A compact, deterministic binary encoding for sets: a four byte header
+//! (`b"AVL"` and a format version), the number of values as a little-endian
+//! `u64`, then each value in ascending order as its `Encode` impl writes it.
+//! Equal sets always encode to the same bytes.
+
+use crate::{is_strictly_increasing, AvlTreeSet, Compare};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::{TryFrom, TryInto};
+use core::fmt;
+
+const MAGIC: &[u8; 3] = b"AVL";
+const VERSION: u8 = 1;
+
+/// Writes a value to the binary format.
+pub trait Encode {
+    fn encode(&self, out: &mut Vec<u8>);
+}
+
+/// Reads a value written by `Encode`, advancing `input` past it.
+pub trait Decode: Sized {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError>;
+}
+
+/// Why `AvlTreeSet::from_bytes` rejected its input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input doesn't start with the format's header.
+    BadMagic,
+    /// The input was written by a format version this build can't read.
+    UnsupportedVersion(u8),
+    /// The input ended in the middle of a value.
+    UnexpectedEnd,
+    /// A value's bytes don't encode a valid value of its type.
+    InvalidValue,
+    /// The values aren't strictly increasing, so they weren't written by
+    /// `to_bytes` with the same order.
+    Unsorted,
+    /// Bytes are left over after the last value.
+    TrailingBytes,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::BadMagic => f.write_str("input is not an encoded set"),
+            DecodeError::UnsupportedVersion(version) => write!(f, "unsupported format version {}", version),
+            DecodeError::UnexpectedEnd => f.write_str("input ended unexpectedly"),
+            DecodeError::InvalidValue => f.write_str("input holds an invalid value"),
+            DecodeError::Unsorted => f.write_str("encoded values are not strictly increasing"),
+            DecodeError::TrailingBytes => f.write_str("input has trailing bytes"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+impl<T: Encode, C> AvlTreeSet<T, C> {
+    /// Encodes the set in the binary format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        (self.len as u64).encode(&mut out);
+
+        for value in self.iter() {
+            value.encode(&mut out);
+        }
+
+        out
+    }
+}
+
+impl<T: Decode, C: Compare<T> + Default> AvlTreeSet<T, C> {
+    /// Decodes a set written by `to_bytes`, rebuilding a perfectly balanced
+    /// tree in linear time.
+    pub fn from_bytes(mut input: &[u8]) -> Result<Self, DecodeError> {
+        if input.len() < 4 || &input[..3] != MAGIC {
+            return Err(DecodeError::BadMagic);
+        }
+        if input[3] != VERSION {
+            return Err(DecodeError::UnsupportedVersion(input[3]));
+        }
+        input = &input[4..];
+
+        let values = decode_seq(&mut input)?;
+        if !input.is_empty() {
+            return Err(DecodeError::TrailingBytes);
+        }
+
+        let mut set = Self::with_comparator(C::default());
+        if !is_strictly_increasing(&values, &set.compare) {
+            return Err(DecodeError::Unsorted);
+        }
+
+        set.rebuild_sorted(values);
+        Ok(set)
+    }
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], DecodeError> {
+    if input.len() < len {
+        return Err(DecodeError::UnexpectedEnd);
+    }
+
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes)
+}
+
+/// Reads a `u64` count followed by that many values.
+fn decode_seq<T: Decode>(input: &mut &[u8]) -> Result<Vec<T>, DecodeError> {
+    let len = usize::try_from(u64::decode(input)?).map_err(|_| DecodeError::InvalidValue)?;
+    // Every value takes at least a byte, which bounds the allocation for a
+    // corrupt count without trusting it.
+    let mut values = Vec::with_capacity(len.min(input.len()));
+
+    for _ in 0..len {
+        values.push(T::decode(input)?);
+    }
+
+    Ok(values)
+}
+
+macro_rules! impl_int {
+    ($($int:ty),*) => {
+        $(
+            impl Encode for $int {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+            }
+
+            impl Decode for $int {
+                fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                    let bytes = take(input, core::mem::size_of::<$int>())?;
+                    Ok(<$int>::from_le_bytes(bytes.try_into().unwrap()))
+                }
+            }
+        )*
+    };
+}
+
+impl_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl Encode for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+}
+
+impl Decode for bool {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecodeError::InvalidValue),
+        }
+    }
+}
+
+impl Encode for char {
+    fn encode(&self, out: &mut Vec<u8>) {
+        u32::from(*self).encode(out);
+    }
+}
+
+impl Decode for char {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        char::from_u32(u32::decode(input)?).ok_or(DecodeError::InvalidValue)
+    }
+}
+
+impl Encode for str {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_bytes().encode(out);
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_str().encode(out);
+    }
+}
+
+impl Decode for String {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        String::from_utf8(Vec::<u8>::decode(input)?).map_err(|_| DecodeError::InvalidValue)
+    }
+}
+
+impl<T: Encode> Encode for [T] {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u64).encode(out);
+
+        for value in self {
+            value.encode(out);
+        }
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_slice().encode(out);
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        decode_seq(input)
+    }
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (**self).encode(out);
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok((A::decode(input)?, B::decode(input)?))
+    }
+}
+
+#[cfg(test)]
+mod properties {
+    use super::*;
+    use itertools::equal;
+
+    #[quickcheck]
+    fn round_trip(input: Vec<(i32, String)>) -> bool {
+        let set = input.into_iter().collect::<AvlTreeSet<_>>();
+        let decoded = AvlTreeSet::<(i32, String)>::from_bytes(&set.to_bytes()).unwrap();
+
+        equal(decoded.iter(), set.iter()) && decoded.check_invariants().is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_malformed_input() {
+        let bytes = (0..4u16).collect::<AvlTreeSet<_>>().to_bytes();
+        assert_eq!(bytes.len(), 4 + 8 + 4 * 2);
+        assert_eq!(AvlTreeSet::<u16>::from_bytes(&bytes).unwrap(), vec![0, 1, 2, 3]);
+
+        assert_eq!(AvlTreeSet::<u16>::from_bytes(b"JSON").unwrap_err(), DecodeError::BadMagic);
+        assert_eq!(
+            AvlTreeSet::<u16>::from_bytes(b"AVL\x02").unwrap_err(),
+            DecodeError::UnsupportedVersion(2)
+        );
+        assert_eq!(AvlTreeSet::<u16>::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err(), DecodeError::UnexpectedEnd);
+        assert_eq!(AvlTreeSet::<u32>::from_bytes(&bytes).unwrap_err(), DecodeError::UnexpectedEnd);
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(AvlTreeSet::<u16>::from_bytes(&trailing).unwrap_err(), DecodeError::TrailingBytes);
+
+        let mut unsorted = bytes;
+        unsorted.swap(12, 14);
+        assert_eq!(AvlTreeSet::<u16>::from_bytes(&unsorted).unwrap_err(), DecodeError::Unsorted);
+    }
+}
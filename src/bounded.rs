@@ -0,0 +1,163 @@
+use crate::{AvlTreeSet, Compare, Natural};
+use core::borrow::Borrow;
+use core::fmt;
+
+/// What a full `BoundedAvlTreeSet` does with a new value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverflowPolicy {
+    /// Refuse the value with `CapacityExceeded`.
+    Reject,
+    /// Insert the value, then evict the smallest value, which may be it.
+    EvictSmallest,
+    /// Insert the value, then evict the largest value, which may be it.
+    EvictLargest,
+}
+
+/// The outcome of a successful `BoundedAvlTreeSet::insert`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoundedInsert<T> {
+    /// The value was inserted and nothing had to make room for it.
+    Inserted,
+    /// An equal value was already present, so nothing changed.
+    Present,
+    /// The value was inserted and this value, possibly the same one, was
+    /// evicted to stay within the limit.
+    Evicted(T),
+}
+
+/// The error returned when a full set with `OverflowPolicy::Reject` is
+/// given a new value, holding that value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityExceeded<T>(pub T);
+
+impl<T> fmt::Display for CapacityExceeded<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("set is at its capacity limit")
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: fmt::Debug> std::error::Error for CapacityExceeded<T> {}
+
+/// A set that never holds more than a fixed number of values, handling
+/// overflow by its `OverflowPolicy`. Created by
+/// `AvlTreeSet::with_capacity_limit`.
+#[derive(Debug, Clone)]
+pub struct BoundedAvlTreeSet<T, C = Natural> {
+    set: AvlTreeSet<T, C>,
+    limit: usize,
+    policy: OverflowPolicy,
+}
+
+impl<T: Ord> AvlTreeSet<T> {
+    /// Creates an empty set that holds at most `limit` values and rejects
+    /// new values once full. Use `with_policy` to evict instead.
+    pub fn with_capacity_limit(limit: usize) -> BoundedAvlTreeSet<T> {
+        BoundedAvlTreeSet { set: Self::new(), limit, policy: OverflowPolicy::Reject }
+    }
+}
+
+impl<T, C: Compare<T>> BoundedAvlTreeSet<T, C> {
+    pub fn with_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn policy(&self) -> OverflowPolicy {
+        self.policy
+    }
+
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.set.len() >= self.limit
+    }
+
+    /// The values, for every read-only query a plain set supports.
+    pub fn as_set(&self) -> &AvlTreeSet<T, C> {
+        &self.set
+    }
+
+    pub fn into_inner(self) -> AvlTreeSet<T, C> {
+        self.set
+    }
+
+    /// Inserts `value` if it is new. Once the set is full, a new value is
+    /// refused or makes room by evicting a value, as the policy says.
+    pub fn insert(&mut self, value: T) -> Result<BoundedInsert<T>, CapacityExceeded<T>> {
+        if self.set.contains(&value) {
+            return Ok(BoundedInsert::Present);
+        }
+
+        let full = self.is_full();
+        if full && self.policy == OverflowPolicy::Reject {
+            return Err(CapacityExceeded(value));
+        }
+
+        self.set.insert(value);
+        if !full {
+            return Ok(BoundedInsert::Inserted);
+        }
+
+        let evicted = match self.policy {
+            OverflowPolicy::EvictLargest => self.set.pop_last(),
+            _ => self.set.pop_first(),
+        };
+
+        Ok(BoundedInsert::Evicted(evicted.unwrap()))
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.set.contains(value)
+    }
+
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.set.remove(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policies_handle_overflow() {
+        let mut rejecting = AvlTreeSet::with_capacity_limit(2);
+        assert_eq!(rejecting.insert(1), Ok(BoundedInsert::Inserted));
+        assert_eq!(rejecting.insert(2), Ok(BoundedInsert::Inserted));
+        assert_eq!(rejecting.insert(2), Ok(BoundedInsert::Present));
+        assert_eq!(rejecting.insert(3), Err(CapacityExceeded(3)));
+
+        let mut smallest = AvlTreeSet::with_capacity_limit(2).with_policy(OverflowPolicy::EvictSmallest);
+        smallest.insert(5).unwrap();
+        smallest.insert(7).unwrap();
+        assert_eq!(smallest.insert(6), Ok(BoundedInsert::Evicted(5)));
+        assert_eq!(smallest.insert(1), Ok(BoundedInsert::Evicted(1)));
+        assert_eq!(smallest.as_set(), &vec![6, 7]);
+
+        let mut largest = AvlTreeSet::with_capacity_limit(1).with_policy(OverflowPolicy::EvictLargest);
+        largest.insert(5).unwrap();
+        assert_eq!(largest.insert(3), Ok(BoundedInsert::Evicted(5)));
+        assert_eq!(largest.into_inner(), vec![3]);
+    }
+}
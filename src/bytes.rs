@@ -0,0 +1,51 @@
+use crate::{AvlTreeSet, AvlTreeSetRange};
+use alloc::vec::Vec;
+use core::ops::Bound;
+
+/// A set of binary keys in lexicographic byte order. The standard library
+/// compares `[u8]` slices with a single `memcmp`, so lookups by `&[u8]`
+/// take the fast path without any comparator of their own.
+pub type AvlByteSet = AvlTreeSet<Vec<u8>>;
+
+/// Lookups by borrowed byte slices, sparing callers the `Borrow` type
+/// annotations the generic methods need for `Vec<u8>` keys.
+impl AvlTreeSet<Vec<u8>> {
+    /// Inserts a copy of `key` if it isn't present yet, returning whether it
+    /// was new. Nothing is allocated for a key that is already present.
+    pub fn insert_bytes(&mut self, key: &[u8]) -> bool {
+        self.insert_with(key, || key.to_vec())
+    }
+
+    pub fn contains_bytes(&self, key: &[u8]) -> bool {
+        self.contains(key)
+    }
+
+    pub fn remove_bytes(&mut self, key: &[u8]) -> bool {
+        self.remove(key)
+    }
+
+    /// Iterates over the keys between `start` and `end` in ascending order.
+    pub fn range_bytes(&self, start: Bound<&[u8]>, end: Bound<&[u8]>) -> AvlTreeSetRange<'_, Vec<u8>> {
+        self.range::<[u8], _>((start, end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_keys_by_slice() {
+        let mut set = AvlByteSet::new();
+
+        for key in [&b"\x00\xff"[..], b"\x01", b"", b"\x00"].iter() {
+            assert!(set.insert_bytes(key));
+        }
+        assert!(!set.insert_bytes(b"\x01"));
+        assert!(set.contains_bytes(b""));
+        assert!(set.remove_bytes(b""));
+
+        let keys = set.range_bytes(Bound::Excluded(b"\x00"), Bound::Included(b"\x01")).collect::<Vec<_>>();
+        assert_eq!(keys, vec![&b"\x00\xff".to_vec(), &b"\x01".to_vec()]);
+    }
+}
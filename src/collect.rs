@@ -0,0 +1,215 @@
+use crate::{AvlTreeSet, Compare, Natural};
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+
+/// The error returned by `AvlTreeSet::try_collect_unique`, holding the
+/// first value that repeated an earlier one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DuplicateValue<T>(pub T);
+
+impl<T: fmt::Debug> fmt::Display for DuplicateValue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "value {:?} appears more than once", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: fmt::Debug> std::error::Error for DuplicateValue<T> {}
+
+/// The error returned by `AvlTreeSetBuilder::push` for a value that isn't
+/// greater than the last one pushed, holding the rejected value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfOrder<T>(pub T);
+
+impl<T: fmt::Debug> fmt::Display for OutOfOrder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "value {:?} is not greater than the previous value", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: fmt::Debug> std::error::Error for OutOfOrder<T> {}
+
+/// Builds a set from values pushed one at a time in strictly increasing
+/// order, then turns them into a perfectly balanced tree without a single
+/// rotation. Created by `AvlTreeSet::builder`.
+#[derive(Debug, Clone)]
+pub struct AvlTreeSetBuilder<T, C = Natural> {
+    values: Vec<T>,
+    compare: C,
+}
+
+impl<T, C: Compare<T>> AvlTreeSetBuilder<T, C> {
+    /// Creates a builder whose values are ordered by `compare`.
+    pub fn with_comparator(compare: C) -> Self {
+        Self { values: Vec::new(), compare }
+    }
+
+    /// Appends `value`, which has to be greater than every value pushed so
+    /// far. Otherwise the builder is left as it was and `value` is handed
+    /// back.
+    pub fn push(&mut self, value: T) -> Result<(), OutOfOrder<T>> {
+        if let Some(last) = self.values.last() {
+            if self.compare.compare(last, &value) != Ordering::Less {
+                return Err(OutOfOrder(value));
+            }
+        }
+
+        self.values.push(value);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The last value pushed, which the next one has to exceed.
+    pub fn last(&self) -> Option<&T> {
+        self.values.last()
+    }
+
+    pub fn build(self) -> AvlTreeSet<T, C> {
+        let mut set = AvlTreeSet::with_comparator(self.compare);
+        set.rebuild_sorted(self.values);
+        set
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for AvlTreeSetBuilder<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T: Ord> AvlTreeSet<T> {
+    /// Creates a builder for pushing values in strictly increasing order.
+    pub fn builder() -> AvlTreeSetBuilder<T> {
+        AvlTreeSetBuilder::with_comparator(Natural)
+    }
+
+    /// Collects values into a set like `collect` does, except that of equal
+    /// values the last one is kept instead of the first.
+    pub fn from_iter_keep_last<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut values = iter.into_iter().collect::<Vec<_>>();
+        values.reverse();
+
+        let mut set = Self::new();
+        set.rebuild_unsorted(values);
+        set
+    }
+
+    /// Collects values into a set, failing instead of dropping anything if
+    /// a value repeats. The error holds the first value, in iteration
+    /// order, that was equal to one before it.
+    pub fn try_collect_unique<I: IntoIterator<Item = T>>(iter: I) -> Result<Self, DuplicateValue<T>> {
+        let mut values = iter.into_iter().enumerate().collect::<Vec<_>>();
+        values.sort_by(|(_, a), (_, b)| a.cmp(b));
+
+        let duplicate = values
+            .windows(2)
+            .enumerate()
+            .filter(|(_, pair)| pair[0].1 == pair[1].1)
+            .min_by_key(|(_, pair)| pair[1].0)
+            .map(|(position, _)| position + 1);
+
+        if let Some(position) = duplicate {
+            return Err(DuplicateValue(values.swap_remove(position).1));
+        }
+
+        Ok(Self::from_sorted_iter(values.into_iter().map(|(_, value)| value)))
+    }
+
+    /// Collects values into a set like `collect` does, also returning the
+    /// values it dropped for being equal to an earlier one, in iteration
+    /// order.
+    pub fn dedup_merge<I: IntoIterator<Item = T>>(iter: I) -> (Self, Vec<T>) {
+        let mut values = iter.into_iter().enumerate().collect::<Vec<_>>();
+        values.sort_by(|(_, a), (_, b)| a.cmp(b));
+
+        let mut unique: Vec<T> = Vec::with_capacity(values.len());
+        let mut duplicates = Vec::new();
+
+        for (position, value) in values {
+            match unique.last() {
+                Some(last) if last.cmp(&value) == Ordering::Equal => duplicates.push((position, value)),
+                _ => unique.push(value),
+            }
+        }
+        duplicates.sort_by_key(|&(position, _)| position);
+
+        (Self::from_sorted_iter(unique), duplicates.into_iter().map(|(_, value)| value).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_policies() {
+        let pairs = vec![(1, 'a'), (2, 'b'), (1, 'c'), (3, 'd'), (2, 'e')];
+        let by_key = |pairs: &[(i32, char)]| pairs.iter().map(|&(key, tag)| Keyed(key, tag)).collect::<Vec<_>>();
+
+        let first = by_key(&pairs).into_iter().collect::<AvlTreeSet<_>>();
+        let last = AvlTreeSet::from_iter_keep_last(by_key(&pairs));
+
+        assert_eq!(first.iter().map(|k| k.1).collect::<String>(), "abd");
+        assert_eq!(last.iter().map(|k| k.1).collect::<String>(), "ced");
+
+        let DuplicateValue(duplicate) = AvlTreeSet::try_collect_unique(by_key(&pairs)).unwrap_err();
+        assert_eq!(duplicate.1, 'c');
+        assert_eq!(AvlTreeSet::try_collect_unique(vec![3, 1, 2]).unwrap(), vec![1, 2, 3]);
+
+        let (set, duplicates) = AvlTreeSet::dedup_merge(by_key(&pairs));
+        assert_eq!(set.iter().map(|k| k.1).collect::<String>(), "abd");
+        assert_eq!(duplicates.iter().map(|k| k.1).collect::<String>(), "ce");
+    }
+
+    #[test]
+    fn builder_rejects_out_of_order_values() {
+        let mut builder = AvlTreeSet::builder();
+
+        for value in 0..100 {
+            builder.push(value).unwrap();
+        }
+
+        assert_eq!(builder.push(99), Err(OutOfOrder(99)));
+        assert_eq!(builder.push(50), Err(OutOfOrder(50)));
+        assert_eq!((builder.len(), builder.last()), (100, Some(&99)));
+
+        let set = builder.build();
+        assert!(set.check_invariants().is_ok());
+        assert_eq!(set.height(), 7);
+        assert!(set.iter().eq(&(0..100).collect::<Vec<_>>()));
+    }
+
+    /// Ordered by the key alone, so values with the same key but different
+    /// tags count as duplicates.
+    #[derive(Debug)]
+    struct Keyed(i32, char);
+
+    impl PartialEq for Keyed {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Keyed {}
+
+    impl PartialOrd for Keyed {
+        fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Keyed {
+        fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! Small special-purpose collections built on `AvlTreeSet`.
+
+use crate::bounded::{BoundedAvlTreeSet, OverflowPolicy};
+use crate::{AvlTreeSet, AvlTreeSetIter};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::iter::Rev;
+
+/// Keeps the `n` largest distinct values seen so far, evicting the
+/// smallest with `pop_first` whenever a new value overflows it.
+#[derive(Debug, Clone)]
+pub struct BoundedTopSet<T: Ord> {
+    set: BoundedAvlTreeSet<T>,
+}
+
+impl<T: Ord> BoundedTopSet<T> {
+    pub fn new(n: usize) -> Self {
+        Self { set: AvlTreeSet::with_capacity_limit(n).with_policy(OverflowPolicy::EvictSmallest) }
+    }
+
+    /// The number of values kept at most.
+    pub fn n(&self) -> usize {
+        self.set.limit()
+    }
+
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    /// Offers `value`, returning whether it is now among the top `n`. An
+    /// equal value already kept counts as making the cut.
+    pub fn insert(&mut self, value: T) -> bool {
+        if self.set.contains(&value) {
+            return true;
+        }
+
+        let beaten = self.threshold().map_or(self.n() > 0, |threshold| value > *threshold);
+        if beaten {
+            let _ = self.set.insert(value);
+        }
+
+        beaten
+    }
+
+    /// The smallest value kept once the tracker is full, which a new value
+    /// has to beat to make the cut.
+    pub fn threshold(&self) -> Option<&T> {
+        if self.set.is_full() { self.set.as_set().first() } else { None }
+    }
+
+    /// Iterates over the kept values from the largest down.
+    pub fn iter(&self) -> Rev<AvlTreeSetIter<'_, T>> {
+        self.set.as_set().iter().rev()
+    }
+
+    /// The kept values from the largest down.
+    pub fn into_vec(self) -> Vec<T> {
+        let mut values = self.set.into_inner().into_sorted_vec();
+        values.reverse();
+        values
+    }
+}
+
+/// Deduplicates values into shared `Arc` handles: interning a value equal
+/// to one interned before returns a clone of the same handle. Lookups are
+/// O(log n) by `&T`, and the interned values can be listed in order.
+#[derive(Debug, Clone)]
+pub struct Interner<T: Ord + ?Sized> {
+    set: AvlTreeSet<Arc<T>>,
+}
+
+impl<T: Ord + ?Sized> Interner<T> {
+    pub fn new() -> Self {
+        Self { set: AvlTreeSet::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    /// The handle for `value`, copying it into a new `Arc` only the first
+    /// time it is interned. Works for `str` and slices as well as sized
+    /// values.
+    pub fn get_or_intern<'a>(&mut self, value: &'a T) -> Arc<T>
+    where
+        Arc<T>: From<&'a T>,
+    {
+        Arc::clone(self.set.get_or_insert_with(value, |_| Arc::from(value)))
+    }
+
+    /// The handle for `value` if it has been interned.
+    pub fn get(&self, value: &T) -> Option<&Arc<T>> {
+        self.set.get(value)
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        self.set.contains(value)
+    }
+
+    /// Iterates over the handles in ascending order of their values.
+    pub fn iter(&self) -> AvlTreeSetIter<'_, Arc<T>> {
+        self.set.iter()
+    }
+
+    /// Drops every value whose only handle is the interner's own, returning
+    /// how many were dropped.
+    pub fn remove_unused(&mut self) -> usize {
+        let len = self.set.len();
+        self.set.retain(|handle| Arc::strong_count(handle) > 1);
+        len - self.set.len()
+    }
+}
+
+impl<T: Ord> Interner<T> {
+    /// Like `get_or_intern`, taking an owned value, which is moved into the
+    /// new handle if it hasn't been interned yet.
+    pub fn get_or_intern_owned(&mut self, value: T) -> Arc<T> {
+        if let Some(handle) = self.set.get(&value) {
+            return Arc::clone(handle);
+        }
+
+        let handle = Arc::new(value);
+        self.set.insert(Arc::clone(&handle));
+        handle
+    }
+}
+
+impl<T: Ord + ?Sized> Default for Interner<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod properties {
+    use super::*;
+    use itertools::equal;
+    use std::collections::BTreeSet;
+
+    #[quickcheck]
+    fn keeps_the_largest_values(input: Vec<u8>, n: u8) -> bool {
+        let n = usize::from(n % 16);
+        let mut top = BoundedTopSet::new(n);
+        let reported = input.iter().all(|&x| top.insert(x) == top.set.contains(&x));
+
+        let expected = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        reported && equal(top.iter(), expected.iter().rev().take(n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_reports_making_the_cut() {
+        let mut top = BoundedTopSet::new(3);
+
+        assert!(top.insert(5));
+        assert!(top.insert(1));
+        assert!(top.insert(9));
+        assert_eq!(top.threshold(), Some(&1));
+        assert!(top.insert(7));
+        assert!(!top.insert(2));
+        assert!(top.insert(7));
+        assert_eq!(top.into_vec(), vec![9, 7, 5]);
+    }
+
+    #[test]
+    fn interner_shares_handles() {
+        let mut interner = Interner::<str>::new();
+
+        let pear = interner.get_or_intern("pear");
+        let apple = interner.get_or_intern("apple");
+        assert!(Arc::ptr_eq(&pear, &interner.get_or_intern("pear")));
+        assert_eq!(interner.iter().map(|handle| &**handle).collect::<Vec<_>>(), vec!["apple", "pear"]);
+
+        drop(apple);
+        assert_eq!(interner.remove_unused(), 1);
+        assert!(!interner.contains("apple") && interner.get("pear").is_some());
+
+        let mut numbers = Interner::new();
+        let seven = numbers.get_or_intern_owned(7);
+        assert!(Arc::ptr_eq(&seven, &numbers.get_or_intern_owned(7)));
+    }
+}
@@ -0,0 +1,300 @@
+use crate::{AvlTree, AvlTreeSet};
+use core::any::{Any, TypeId};
+use core::cmp::Ordering;
+use core::fmt;
+
+/// A total order over `T` used by a set in place of `T`'s own `Ord`.
+/// Closures and functions of the form `Fn(&T, &T) -> Ordering` are
+/// comparators too.
+pub trait Compare<T: ?Sized> {
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+/// The comparator sets use by default, which defers to `Ord`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Natural;
+
+impl<T: Ord + ?Sized> Compare<T> for Natural {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+impl<T: ?Sized, F: Fn(&T, &T) -> Ordering> Compare<T> for F {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self(a, b)
+    }
+}
+
+/// A comparator that reverses `Ord`, for sets that iterate largest first
+/// without wrapping every value in `core::cmp::Reverse`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Descending;
+
+impl<T: Ord + ?Sized> Compare<T> for Descending {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        b.cmp(a)
+    }
+}
+
+/// A comparator for `PartialOrd` types such as `f64`, for sets that only
+/// ever hold mutually comparable values. Insert through
+/// `AvlTreeSet::try_insert`, which rejects values like NaN instead of
+/// letting them break the tree; comparing two incomparable values
+/// directly panics.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TotalOrder;
+
+impl<T: PartialOrd + ?Sized> Compare<T> for TotalOrder {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.partial_cmp(b).expect("TotalOrder compared two incomparable values")
+    }
+}
+
+/// A comparator that orders values by a key derived from each one, as
+/// `sort_by_key` does. Values with equal keys count as equal.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ByKey<F>(pub F);
+
+impl<T: ?Sized, K: Ord, F: Fn(&T) -> K> Compare<T> for ByKey<F> {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.0)(a).cmp(&(self.0)(b))
+    }
+}
+
+/// A set ordered by the key `F` extracts from each value.
+pub type AvlTreeSetBy<T, F> = AvlTreeSet<T, ByKey<F>>;
+
+/// A set ordered largest first.
+pub type AvlTreeSetDesc<T> = AvlTreeSet<T, Descending>;
+
+/// An object-safe `Ord`, implemented for every `Ord + Debug` type, so keys
+/// of different types can share one `AvlTreeSet<Box<dyn DynOrd>>`. Values
+/// of the same type compare by their own `Ord`; values of different types
+/// by type. The order between types is arbitrary, but stays the same for
+/// as long as the program runs.
+pub trait DynOrd: Any + fmt::Debug {
+    fn as_any(&self) -> &dyn Any;
+
+    fn dyn_cmp(&self, other: &dyn DynOrd) -> Ordering;
+}
+
+impl<T: Ord + Any + fmt::Debug> DynOrd for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn dyn_cmp(&self, other: &dyn DynOrd) -> Ordering {
+        let other = other.as_any();
+
+        match other.downcast_ref::<T>() {
+            Some(other) => self.cmp(other),
+            None => TypeId::of::<T>().cmp(&other.type_id()),
+        }
+    }
+}
+
+impl dyn DynOrd {
+    /// The key as a `T`, if that is its type.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+}
+
+impl PartialEq for dyn DynOrd {
+    fn eq(&self, other: &Self) -> bool {
+        self.dyn_cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for dyn DynOrd {}
+
+impl PartialOrd for dyn DynOrd {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for dyn DynOrd {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.dyn_cmp(other)
+    }
+}
+
+/// The error returned by `AvlTreeSet::try_insert` for a value that can't be
+/// ordered against the set's values, holding the rejected value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Incomparable<T>(pub T);
+
+impl<T: fmt::Debug> fmt::Display for Incomparable<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "value {:?} is not comparable with the set's values", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: fmt::Debug> std::error::Error for Incomparable<T> {}
+
+impl<T: PartialOrd> AvlTreeSet<T, TotalOrder> {
+    /// Creates an empty set of `PartialOrd` values.
+    pub fn partially_ordered() -> Self {
+        Self::with_comparator(TotalOrder)
+    }
+
+    /// Inserts `value` as `insert` does, unless it is incomparable with
+    /// itself or with a value on its search path, in which case the set is
+    /// left untouched and `value` is handed back.
+    pub fn try_insert(&mut self, value: T) -> Result<bool, Incomparable<T>> {
+        if !comparable_along_path(&self.root, &value) {
+            return Err(Incomparable(value));
+        }
+
+        let inserted = self.insert_value(value);
+
+        self.finish("try_insert", inserted);
+        Ok(inserted)
+    }
+}
+
+impl<T: Ord> AvlTreeSet<T, Descending> {
+    /// Creates an empty set that iterates largest first. Values are
+    /// inserted and looked up as they are, with no wrapper. Ranges follow
+    /// the set's order too, so `range(..=4)` yields the values from the
+    /// largest down to 4.
+    pub const fn new_desc() -> Self {
+        Self::with_comparator(Descending)
+    }
+}
+
+impl<T: Ord> From<AvlTreeSet<T>> for AvlTreeSet<T, Descending> {
+    /// Reverses the order of `set` in O(n), keeping every value.
+    fn from(set: AvlTreeSet<T>) -> Self {
+        AvlTreeSet::from_sorted_iter_with(set.into_sorted_vec().into_iter().rev(), Descending)
+    }
+}
+
+impl<T: Ord> From<AvlTreeSet<T, Descending>> for AvlTreeSet<T> {
+    /// Reverses the order of `set` in O(n), keeping every value.
+    fn from(set: AvlTreeSet<T, Descending>) -> Self {
+        AvlTreeSet::from_sorted_iter(set.into_sorted_vec().into_iter().rev())
+    }
+}
+
+impl<T, F> AvlTreeSet<T, ByKey<F>> {
+    /// Creates an empty set ordered by the key `key` extracts from each
+    /// value, so a struct can be kept ordered by one of its fields.
+    pub fn by_key<K: Ord>(key: F) -> Self
+    where
+        F: Fn(&T) -> K,
+    {
+        Self::with_comparator(ByKey(key))
+    }
+
+    /// The value whose key equals `key`, if any.
+    pub fn get_by_key<K: Ord>(&self, key: &K) -> Option<&T>
+    where
+        F: Fn(&T) -> K,
+    {
+        let mut current_tree = &self.root;
+
+        while let Some(current_node) = current_tree {
+            current_tree = match (self.comparator().0)(&current_node.value).cmp(key) {
+                Ordering::Less => &current_node.right,
+                Ordering::Equal => { return Some(&current_node.value); }
+                Ordering::Greater => &current_node.left,
+            };
+        }
+
+        None
+    }
+
+    pub fn contains_key<K: Ord>(&self, key: &K) -> bool
+    where
+        F: Fn(&T) -> K,
+    {
+        self.get_by_key(key).is_some()
+    }
+}
+
+/// Walks the search path for `value`, returning `false` as soon as a
+/// comparison along it has no answer.
+fn comparable_along_path<T: PartialOrd>(mut current_tree: &AvlTree<T>, value: &T) -> bool {
+    if value.partial_cmp(value).is_none() {
+        return false;
+    }
+
+    while let Some(current_node) = current_tree {
+        current_tree = match current_node.value.partial_cmp(value) {
+            Some(Ordering::Less) => &current_node.right,
+            Some(Ordering::Equal) => { return true; }
+            Some(Ordering::Greater) => &current_node.left,
+            None => { return false; }
+        };
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_insert_rejects_nan() {
+        let mut set = AvlTreeSet::partially_ordered();
+
+        assert_eq!(set.try_insert(1.5), Ok(true));
+        assert_eq!(set.try_insert(-0.5), Ok(true));
+        assert_eq!(set.try_insert(1.5), Ok(false));
+        assert!(set.try_insert(f64::NAN).unwrap_err().0.is_nan());
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![&-0.5, &1.5]);
+    }
+
+    #[test]
+    fn by_key_orders_on_one_field() {
+        #[derive(Debug, PartialEq)]
+        struct User {
+            id: u32,
+            name: &'static str,
+        }
+
+        let mut users = AvlTreeSet::by_key(|user: &User| user.id);
+
+        assert!(users.insert(User { id: 7, name: "ada" }));
+        assert!(users.insert(User { id: 2, name: "bob" }));
+        assert!(!users.insert(User { id: 7, name: "eve" }));
+        assert_eq!(users.get_by_key(&7).map(|user| user.name), Some("ada"));
+        assert!(!users.contains_key(&3));
+        assert_eq!(users.iter().map(|user| user.id).collect::<Vec<_>>(), vec![2, 7]);
+    }
+
+    #[test]
+    fn descending_sets_iterate_largest_first() {
+        let mut set = AvlTreeSet::new_desc();
+        set.extend([3, 1, 4, 1, 5]);
+
+        assert!(set.contains(&4) && set.remove(&1));
+        assert_eq!(set.first(), Some(&5));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![&5, &4, &3]);
+        assert_eq!(set.range(..=4).collect::<Vec<_>>(), vec![&5, &4]);
+
+        let ascending: AvlTreeSet<_> = set.into();
+        assert_eq!(ascending.iter().collect::<Vec<_>>(), vec![&3, &4, &5]);
+        assert_eq!(AvlTreeSetDesc::from(ascending).into_sorted_vec(), vec![5, 4, 3]);
+    }
+
+    #[test]
+    fn dyn_ord_keys_of_mixed_types() {
+        let mut registry: AvlTreeSet<Box<dyn DynOrd>> = AvlTreeSet::new();
+
+        registry.extend([Box::new(3u32) as Box<dyn DynOrd>, Box::new("b"), Box::new(1u32), Box::new("a"), Box::new(3u32)]);
+
+        assert_eq!(registry.len(), 4);
+        assert!(registry.contains(&1u32 as &dyn DynOrd));
+        assert!(!registry.contains(&1u64 as &dyn DynOrd));
+
+        let numbers = registry.iter().filter_map(|key| key.downcast_ref::<u32>()).collect::<Vec<_>>();
+        let names = registry.iter().filter_map(|key| key.downcast_ref::<&str>()).collect::<Vec<_>>();
+        assert_eq!((numbers, names), (vec![&1, &3], vec![&"a", &"b"]));
+    }
+}
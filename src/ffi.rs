@@ -0,0 +1,234 @@
+//! A C interface over opaque set handles, for `i64` keys and for byte
+//! string keys. Every handle returned by a `*_new` function must be passed
+//! to the matching `*_free` exactly once. The declarations are in
+//! `include/avl_tree_set.h`, which is written by hand. Running `cbindgen`
+//! with the repository's `cbindgen.toml` gives an equivalent header to
+//! check it against.
+//!
+//! The `avl-tree-set-capi` package in `capi/` links these functions into a
+//! `cdylib` and a `staticlib` named `avl_tree_set_capi`, for C callers and
+//! for Python through cffi.
+//!
+//! Like `wasm`, this module is allowed to use `unsafe`, here only to move
+//! handles and byte strings across the boundary.
+#![allow(unsafe_code)]
+
+use crate::AvlTreeSet;
+use std::os::raw::c_void;
+use std::slice;
+
+/// An opaque set of `i64` values.
+pub struct AvlSetI64(AvlTreeSet<i64>);
+
+/// An opaque set of byte strings, ordered lexicographically.
+pub struct AvlSetBytes(AvlTreeSet<Vec<u8>>);
+
+/// Called once per value by `avl_set_i64_for_each`, in ascending order.
+/// Returning `false` stops the iteration.
+pub type AvlSetI64Visitor = extern "C" fn(value: i64, data: *mut c_void) -> bool;
+
+/// Called once per value by `avl_set_bytes_for_each`, in ascending order,
+/// with a pointer that is only valid for the duration of the call.
+/// Returning `false` stops the iteration.
+pub type AvlSetBytesVisitor = extern "C" fn(bytes: *const u8, len: usize, data: *mut c_void) -> bool;
+
+#[no_mangle]
+pub extern "C" fn avl_set_i64_new() -> *mut AvlSetI64 {
+    Box::into_raw(Box::new(AvlSetI64(AvlTreeSet::new())))
+}
+
+/// # Safety
+///
+/// `set` must be null or a handle from `avl_set_i64_new` that has not been
+/// freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn avl_set_i64_free(set: *mut AvlSetI64) {
+    if !set.is_null() {
+        drop(Box::from_raw(set));
+    }
+}
+
+/// Returns whether `value` was newly inserted.
+///
+/// # Safety
+///
+/// `set` must be a live handle from `avl_set_i64_new`.
+#[no_mangle]
+pub unsafe extern "C" fn avl_set_i64_insert(set: *mut AvlSetI64, value: i64) -> bool {
+    (*set).0.insert(value)
+}
+
+/// # Safety
+///
+/// `set` must be a live handle from `avl_set_i64_new`.
+#[no_mangle]
+pub unsafe extern "C" fn avl_set_i64_contains(set: *const AvlSetI64, value: i64) -> bool {
+    (*set).0.contains(&value)
+}
+
+/// Returns whether `value` was present.
+///
+/// # Safety
+///
+/// `set` must be a live handle from `avl_set_i64_new`.
+#[no_mangle]
+pub unsafe extern "C" fn avl_set_i64_remove(set: *mut AvlSetI64, value: i64) -> bool {
+    (*set).0.remove(&value)
+}
+
+/// # Safety
+///
+/// `set` must be a live handle from `avl_set_i64_new`.
+#[no_mangle]
+pub unsafe extern "C" fn avl_set_i64_len(set: *const AvlSetI64) -> usize {
+    (*set).0.len()
+}
+
+/// Calls `visit` with each value and `data` until it returns `false`.
+///
+/// # Safety
+///
+/// `set` must be a live handle from `avl_set_i64_new`, and `visit` must not
+/// modify or free it.
+#[no_mangle]
+pub unsafe extern "C" fn avl_set_i64_for_each(set: *const AvlSetI64, visit: AvlSetI64Visitor, data: *mut c_void) {
+    for &value in (*set).0.iter() {
+        if !visit(value, data) {
+            break;
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn avl_set_bytes_new() -> *mut AvlSetBytes {
+    Box::into_raw(Box::new(AvlSetBytes(AvlTreeSet::new())))
+}
+
+/// # Safety
+///
+/// `set` must be null or a handle from `avl_set_bytes_new` that has not
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn avl_set_bytes_free(set: *mut AvlSetBytes) {
+    if !set.is_null() {
+        drop(Box::from_raw(set));
+    }
+}
+
+/// Copies the `len` bytes at `bytes` into the set, returning whether they
+/// were newly inserted.
+///
+/// # Safety
+///
+/// `set` must be a live handle from `avl_set_bytes_new`, and `bytes` must
+/// point to `len` readable bytes, or may be null if `len` is zero.
+#[no_mangle]
+pub unsafe extern "C" fn avl_set_bytes_insert(set: *mut AvlSetBytes, bytes: *const u8, len: usize) -> bool {
+    (*set).0.insert(bytes_from_raw(bytes, len).to_vec())
+}
+
+/// # Safety
+///
+/// The same as for `avl_set_bytes_insert`.
+#[no_mangle]
+pub unsafe extern "C" fn avl_set_bytes_contains(set: *const AvlSetBytes, bytes: *const u8, len: usize) -> bool {
+    (*set).0.contains(bytes_from_raw(bytes, len))
+}
+
+/// Returns whether the bytes were present.
+///
+/// # Safety
+///
+/// The same as for `avl_set_bytes_insert`.
+#[no_mangle]
+pub unsafe extern "C" fn avl_set_bytes_remove(set: *mut AvlSetBytes, bytes: *const u8, len: usize) -> bool {
+    (*set).0.remove(bytes_from_raw(bytes, len))
+}
+
+/// # Safety
+///
+/// `set` must be a live handle from `avl_set_bytes_new`.
+#[no_mangle]
+pub unsafe extern "C" fn avl_set_bytes_len(set: *const AvlSetBytes) -> usize {
+    (*set).0.len()
+}
+
+/// Calls `visit` with each byte string and `data` until it returns `false`.
+///
+/// # Safety
+///
+/// `set` must be a live handle from `avl_set_bytes_new`, and `visit` must
+/// not modify or free it.
+#[no_mangle]
+pub unsafe extern "C" fn avl_set_bytes_for_each(set: *const AvlSetBytes, visit: AvlSetBytesVisitor, data: *mut c_void) {
+    for value in (*set).0.iter() {
+        if !visit(value.as_ptr(), value.len(), data) {
+            break;
+        }
+    }
+}
+
+unsafe fn bytes_from_raw<'a>(bytes: *const u8, len: usize) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(bytes, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" fn collect_i64(value: i64, data: *mut c_void) -> bool {
+        let values = unsafe { &mut *(data as *mut Vec<i64>) };
+        values.push(value);
+        values.len() < 2
+    }
+
+    extern "C" fn collect_bytes(bytes: *const u8, len: usize, data: *mut c_void) -> bool {
+        let values = unsafe { &mut *(data as *mut Vec<Vec<u8>>) };
+        values.push(unsafe { bytes_from_raw(bytes, len) }.to_vec());
+        true
+    }
+
+    #[test]
+    fn i64_handle_round_trip() {
+        unsafe {
+            let set = avl_set_i64_new();
+            assert!(avl_set_i64_insert(set, 3));
+            assert!(avl_set_i64_insert(set, -1));
+            assert!(avl_set_i64_insert(set, 7));
+            assert!(!avl_set_i64_insert(set, 3));
+            assert!(avl_set_i64_remove(set, 7));
+            assert!(avl_set_i64_insert(set, 5));
+            assert!(avl_set_i64_contains(set, -1));
+            assert_eq!(avl_set_i64_len(set), 3);
+
+            let mut values = Vec::<i64>::new();
+            avl_set_i64_for_each(set, collect_i64, &mut values as *mut _ as *mut c_void);
+            assert_eq!(values, vec![-1, 3]);
+
+            avl_set_i64_free(set);
+        }
+    }
+
+    #[test]
+    fn bytes_handle_round_trip() {
+        unsafe {
+            let set = avl_set_bytes_new();
+            for key in [&b"pear"[..], b"apple", b"", b"apple"].iter() {
+                avl_set_bytes_insert(set, key.as_ptr(), key.len());
+            }
+            assert!(avl_set_bytes_contains(set, std::ptr::null(), 0));
+            assert!(avl_set_bytes_remove(set, b"pear".as_ptr(), 4));
+            assert_eq!(avl_set_bytes_len(set), 2);
+
+            let mut values = Vec::<Vec<u8>>::new();
+            avl_set_bytes_for_each(set, collect_bytes, &mut values as *mut _ as *mut c_void);
+            assert_eq!(values, vec![b"".to_vec(), b"apple".to_vec()]);
+
+            avl_set_bytes_free(set);
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use crate::{above_start, below_end, AvlTreeSet, Natural};
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::iter::FusedIterator;
+use core::ops::RangeBounds;
+
+/// A read-only set packed into one contiguous array in BFS (Eytzinger)
+/// order: the root is at position 1 and the children of position `k` are
+/// at `2k` and `2k + 1`. Lookups walk down that implicit tree touching
+/// neighbouring cache lines near the top and never chase a pointer.
+/// Created with `AvlTreeSet::freeze`.
+#[derive(Clone)]
+pub struct FrozenAvlSet<T> {
+    /// `values[k - 1]` holds the value at position `k`.
+    values: Vec<T>,
+}
+
+pub struct FrozenAvlSetRange<'a, T> {
+    values: &'a [T],
+    front: usize,
+    back: usize,
+}
+
+pub struct FrozenAvlSetIter<'a, T> {
+    range: FrozenAvlSetRange<'a, T>,
+    remaining: usize,
+}
+
+impl<T: Ord> AvlTreeSet<T> {
+    /// Repacks the set into a `FrozenAvlSet` for query-heavy phases. Takes
+    /// O(n) time and one allocation.
+    pub fn freeze(self) -> FrozenAvlSet<T> {
+        let len = self.len;
+        let mut slots = Vec::with_capacity(len);
+        slots.resize_with(len, || None);
+
+        let mut position = leftmost(1, len);
+        for value in self {
+            slots[position - 1] = Some(value);
+            position = successor(position, len);
+        }
+
+        FrozenAvlSet { values: slots.into_iter().map(Option::unwrap).collect() }
+    }
+}
+
+impl<T: Ord> FrozenAvlSet<T> {
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(value).is_some()
+    }
+
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let position = self.first_where(|probe| probe.borrow() >= value);
+
+        self.value_at(position).filter(|&found| found.borrow() == value)
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> FrozenAvlSetRange<'_, T>
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let len = self.values.len();
+        let front = self.first_where(|probe| above_start(probe.borrow(), range.start_bound(), &Natural));
+        let back = match self.first_where(|probe| !below_end(probe.borrow(), range.end_bound(), &Natural)) {
+            0 => rightmost(1, len),
+            after => predecessor(after, len),
+        };
+
+        match (self.value_at(front), self.value_at(back)) {
+            (Some(first), Some(last)) if first <= last => FrozenAvlSetRange { values: &self.values, front, back },
+            _ => FrozenAvlSetRange { values: &self.values, front: 0, back: 0 },
+        }
+    }
+
+    pub fn iter(&self) -> FrozenAvlSetIter<'_, T> {
+        let len = self.values.len();
+        let range = FrozenAvlSetRange { values: &self.values, front: leftmost(1, len), back: rightmost(1, len) };
+
+        FrozenAvlSetIter { range, remaining: len }
+    }
+
+    /// Returns the position of the smallest value for which `pred` holds,
+    /// or 0 if there is none. `pred` must be false for a prefix of the
+    /// values in sorted order and true for the rest.
+    fn first_where<F: Fn(&T) -> bool>(&self, pred: F) -> usize {
+        let mut position = 1;
+
+        while position <= self.values.len() {
+            position = 2 * position + !pred(&self.values[position - 1]) as usize;
+        }
+
+        // The walk ends below the answer after one left turn followed by
+        // only right turns, so undo those right turns and the left one.
+        position >> (position.trailing_ones() + 1)
+    }
+
+    fn value_at(&self, position: usize) -> Option<&T> {
+        position.checked_sub(1).and_then(|index| self.values.get(index))
+    }
+}
+
+fn leftmost(mut position: usize, len: usize) -> usize {
+    if position > len {
+        return 0;
+    }
+    while 2 * position <= len {
+        position *= 2;
+    }
+    position
+}
+
+fn rightmost(mut position: usize, len: usize) -> usize {
+    if position > len {
+        return 0;
+    }
+    while 2 * position < len {
+        position = 2 * position + 1;
+    }
+    position
+}
+
+/// The position of the next value in sorted order, or 0 after the last.
+fn successor(position: usize, len: usize) -> usize {
+    if 2 * position < len {
+        return leftmost(2 * position + 1, len);
+    }
+    (position >> position.trailing_ones()) >> 1
+}
+
+/// The position of the previous value in sorted order, or 0 before the first.
+fn predecessor(position: usize, len: usize) -> usize {
+    if 2 * position <= len {
+        return rightmost(2 * position, len);
+    }
+    (position >> position.trailing_zeros()) >> 1
+}
+
+impl<'a, T> Iterator for FrozenAvlSetRange<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == 0 {
+            return None;
+        }
+
+        let value = &self.values[self.front - 1];
+        if self.front == self.back {
+            self.front = 0;
+            self.back = 0;
+        } else {
+            self.front = successor(self.front, self.values.len());
+        }
+
+        Some(value)
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for FrozenAvlSetRange<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.back == 0 {
+            return None;
+        }
+
+        let value = &self.values[self.back - 1];
+        if self.front == self.back {
+            self.front = 0;
+            self.back = 0;
+        } else {
+            self.back = predecessor(self.back, self.values.len());
+        }
+
+        Some(value)
+    }
+}
+
+impl<'a, T> FusedIterator for FrozenAvlSetRange<'a, T> {}
+
+impl<'a, T> Iterator for FrozenAvlSetIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = self.range.next()?;
+        self.remaining -= 1;
+
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for FrozenAvlSetIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let value = self.range.next_back()?;
+        self.remaining -= 1;
+
+        Some(value)
+    }
+}
+
+impl<'a, T> ExactSizeIterator for FrozenAvlSetIter<'a, T> {}
+
+impl<'a, T> FusedIterator for FrozenAvlSetIter<'a, T> {}
+
+impl<'a, T: Ord> IntoIterator for &'a FrozenAvlSet<T> {
+    type Item = &'a T;
+    type IntoIter = FrozenAvlSetIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod properties {
+    use super::*;
+    use itertools::equal;
+    use std::cmp;
+    use std::collections::BTreeSet;
+    use std::ops::Bound::{Excluded, Unbounded};
+
+    #[quickcheck]
+    fn frozen_set_parity(input: Vec<u8>, probes: Vec<u8>, start: u8, end: u8) -> bool {
+        let frozen_set = input.iter().cloned().collect::<AvlTreeSet<_>>().freeze();
+        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+        let (start, end) = (cmp::min(start, end), cmp::max(start, end));
+
+        frozen_set.len() == btree_set.len()
+            && probes.iter().all(|x| frozen_set.get(x) == btree_set.get(x))
+            && equal(frozen_set.iter(), btree_set.iter())
+            && equal(frozen_set.iter().rev(), btree_set.iter().rev())
+            && equal(frozen_set.range(start..end), btree_set.range(start..end))
+            && equal(frozen_set.range(start..=end).rev(), btree_set.range(start..=end).rev())
+            && equal(frozen_set.range((Excluded(start), Unbounded)), btree_set.range((Excluded(start), Unbounded)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn freeze_lays_values_out_breadth_first() {
+        let frozen_set = (1..=7).collect::<AvlTreeSet<_>>().freeze();
+
+        assert_eq!(frozen_set.values, vec![4, 2, 6, 1, 3, 5, 7]);
+        assert!(frozen_set.contains(&5));
+        assert!(!frozen_set.contains(&8));
+        assert_eq!(frozen_set.range(3..6).collect::<Vec<_>>(), vec![&3, &4, &5]);
+        assert_eq!(frozen_set.range(8..).next(), None);
+        assert_eq!(AvlTreeSet::<u8>::new().freeze().iter().next(), None);
+    }
+}
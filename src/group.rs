@@ -0,0 +1,63 @@
+use crate::{AvlTreeSet, AvlTreeSetIter};
+use alloc::vec::Vec;
+use core::iter::{FusedIterator, Peekable};
+
+/// Iterates over the runs of adjacent values that belong together. Created
+/// by `AvlTreeSet::group_adjacent_by`.
+pub struct AvlTreeSetGroups<'a, T, F> {
+    iter: Peekable<AvlTreeSetIter<'a, T>>,
+    same_group: F,
+}
+
+impl<T, C> AvlTreeSet<T, C> {
+    /// Splits the values, in ascending order, into runs where `same_group`
+    /// returns `true` for every pair of neighbours, like `slice::chunk_by`.
+    /// Only one run is held at a time, so with a key that agrees with the
+    /// set's order, e.g. events grouped by day, each run can be aggregated
+    /// as it comes.
+    pub fn group_adjacent_by<F>(&self, same_group: F) -> AvlTreeSetGroups<'_, T, F>
+    where
+        F: FnMut(&T, &T) -> bool,
+    {
+        AvlTreeSetGroups { iter: self.iter().peekable(), same_group }
+    }
+}
+
+impl<'a, T, F: FnMut(&T, &T) -> bool> Iterator for AvlTreeSetGroups<'a, T, F> {
+    type Item = Vec<&'a T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut group = alloc::vec![self.iter.next()?];
+        let same_group = &mut self.same_group;
+
+        while let Some(next) = self.iter.next_if(|next| same_group(group[group.len() - 1], next)) {
+            group.push(next);
+        }
+
+        Some(group)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.iter.size_hint();
+        (lower.min(1), upper)
+    }
+}
+
+impl<'a, T, F: FnMut(&T, &T) -> bool> FusedIterator for AvlTreeSetGroups<'a, T, F> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_of_adjacent_values() {
+        let events = AvlTreeSet::from([(1, "a"), (1, "b"), (2, "c"), (4, "d"), (4, "e"), (4, "f")]);
+
+        let per_day = events.group_adjacent_by(|a, b| a.0 == b.0).map(|group| (group[0].0, group.len()));
+        assert_eq!(per_day.collect::<Vec<_>>(), vec![(1, 2), (2, 1), (4, 3)]);
+
+        let runs = AvlTreeSet::from([1, 2, 3, 5, 6, 9]).group_adjacent_by(|a, b| a + 1 == *b).count();
+        assert_eq!(runs, 3);
+        assert!(AvlTreeSet::<i32>::new().group_adjacent_by(|_, _| true).next().is_none());
+    }
+}
@@ -0,0 +1,104 @@
+//! Priority queue views over a set, so one set can serve both ordered
+//! iteration and `BinaryHeap`-style use. Every operation is O(log n).
+
+use crate::{AvlTreeSet, Compare, Natural};
+
+/// A view of a set as a min-heap, created by `AvlTreeSet::as_min_heap`.
+#[derive(Debug)]
+pub struct AvlTreeSetMinHeap<'a, T, C = Natural> {
+    set: &'a mut AvlTreeSet<T, C>,
+}
+
+/// A view of a set as a max-heap, created by `AvlTreeSet::as_max_heap`.
+#[derive(Debug)]
+pub struct AvlTreeSetMaxHeap<'a, T, C = Natural> {
+    set: &'a mut AvlTreeSet<T, C>,
+}
+
+impl<T, C: Compare<T>> AvlTreeSet<T, C> {
+    pub fn as_min_heap(&mut self) -> AvlTreeSetMinHeap<'_, T, C> {
+        AvlTreeSetMinHeap { set: self }
+    }
+
+    pub fn as_max_heap(&mut self) -> AvlTreeSetMaxHeap<'_, T, C> {
+        AvlTreeSetMaxHeap { set: self }
+    }
+}
+
+impl<'a, T, C: Compare<T>> AvlTreeSetMinHeap<'a, T, C> {
+    /// The smallest value.
+    pub fn peek(&self) -> Option<&T> {
+        self.set.first()
+    }
+
+    /// Removes and returns the smallest value.
+    pub fn pop(&mut self) -> Option<T> {
+        self.set.pop_first()
+    }
+
+    /// Adds `value`, returning whether it was new. Unlike a `BinaryHeap`, a
+    /// value equal to one already queued is not queued twice.
+    pub fn push(&mut self, value: T) -> bool {
+        self.set.insert(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+}
+
+impl<'a, T, C: Compare<T>> AvlTreeSetMaxHeap<'a, T, C> {
+    /// The largest value.
+    pub fn peek(&self) -> Option<&T> {
+        self.set.last()
+    }
+
+    /// Removes and returns the largest value.
+    pub fn pop(&mut self) -> Option<T> {
+        self.set.pop_last()
+    }
+
+    /// Adds `value`, returning whether it was new. Unlike a `BinaryHeap`, a
+    /// value equal to one already queued is not queued twice.
+    pub fn push(&mut self, value: T) -> bool {
+        self.set.insert(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heaps_pop_from_opposite_ends() {
+        let mut set = AvlTreeSet::new();
+
+        let mut min_heap = set.as_min_heap();
+        assert!(min_heap.push(5));
+        assert!(min_heap.push(1));
+        assert!(!min_heap.push(5));
+        assert!(min_heap.push(9));
+        assert_eq!((min_heap.peek(), min_heap.len()), (Some(&1), 3));
+        assert_eq!(min_heap.pop(), Some(1));
+
+        let mut max_heap = set.as_max_heap();
+        assert_eq!(max_heap.peek(), Some(&9));
+        assert_eq!(max_heap.pop(), Some(9));
+        assert!(max_heap.push(7));
+        assert_eq!(max_heap.pop(), Some(7));
+
+        assert_eq!(set, vec![5]);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{AvlNode, AvlTreeMap, AvlTreeSet};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::mem;
+
+/// Reports the heap memory a value owns, not counting the value itself, in
+/// the style of `malloc_size_of`'s `MallocSizeOf`. Implemented for the sets
+/// and for common std types so nested collections can be measured deeply.
+pub trait HeapSizeOf {
+    fn heap_size_of(&self) -> usize;
+}
+
+macro_rules! impl_heap_size_of_for_inline_types {
+    ($($ty:ty),*) => {
+        $(
+            impl HeapSizeOf for $ty {
+                fn heap_size_of(&self) -> usize {
+                    0
+                }
+            }
+        )*
+    };
+}
+
+impl_heap_size_of_for_inline_types!(
+    (), bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64
+);
+
+impl HeapSizeOf for String {
+    fn heap_size_of(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: HeapSizeOf> HeapSizeOf for Box<T> {
+    fn heap_size_of(&self) -> usize {
+        mem::size_of::<T>() + (**self).heap_size_of()
+    }
+}
+
+impl<T: HeapSizeOf> HeapSizeOf for Option<T> {
+    fn heap_size_of(&self) -> usize {
+        self.as_ref().map_or(0, T::heap_size_of)
+    }
+}
+
+impl<T: HeapSizeOf> HeapSizeOf for Vec<T> {
+    fn heap_size_of(&self) -> usize {
+        self.capacity() * mem::size_of::<T>() + self.iter().map(T::heap_size_of).sum::<usize>()
+    }
+}
+
+impl<T: HeapSizeOf, C> HeapSizeOf for AvlTreeSet<T, C> {
+    fn heap_size_of(&self) -> usize {
+        self.heap_size() + self.iter().chain(self.pool.values()).map(T::heap_size_of).sum::<usize>()
+    }
+}
+
+impl<K: Ord + HeapSizeOf, V: HeapSizeOf> HeapSizeOf for AvlTreeMap<K, V> {
+    fn heap_size_of(&self) -> usize {
+        self.len() * mem::size_of::<AvlNode<crate::map::KeyValue<K, V>>>()
+            + self.iter().map(|(key, value)| key.heap_size_of() + value.heap_size_of()).sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heap_size_of_includes_owned_values() {
+        let (mut a, mut b) = (String::with_capacity(10), String::with_capacity(20));
+        a.push('a');
+        b.push('b');
+        let set = vec![a, b].into_iter().collect::<AvlTreeSet<_>>();
+
+        assert_eq!(set.heap_size_of(), set.heap_size() + 30);
+
+        let mut pooled = set.with_node_pool();
+        let nodes = pooled.heap_size();
+        pooled.remove("a");
+        assert_eq!(pooled.pooled_nodes(), 1);
+        assert!(pooled.heap_size() > nodes);
+        assert_eq!(pooled.heap_size_of(), pooled.heap_size() + 30);
+
+        pooled.shrink_to_fit();
+        assert_eq!(pooled.heap_size_of(), pooled.heap_size() + 20);
+    }
+}
@@ -0,0 +1,395 @@
+use crate::AvlTreeSet;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cmp::{self, Ordering};
+use core::iter::{FromIterator, FusedIterator};
+
+type Link<T> = Option<Arc<ImmutableNode<T>>>;
+
+#[derive(Debug)]
+struct ImmutableNode<T> {
+    value: T,
+    left: Link<T>,
+    right: Link<T>,
+    height: usize,
+}
+
+/// A persistent AVL tree set. Nodes are shared behind `Arc`s and never
+/// modified, so `insert` and `remove` leave the set alone and return a new
+/// one that copies only the path to the changed node. Cloning a set is a
+/// reference count bump, which makes snapshots cheap.
+#[derive(Debug, Clone)]
+pub struct ImmutableAvlTreeSet<T: Ord + Clone> {
+    root: Link<T>,
+    len: usize,
+}
+
+pub struct ImmutableAvlTreeSetIter<'a, T: Ord + Clone> {
+    prev_nodes: Vec<&'a ImmutableNode<T>>,
+    remaining: usize,
+}
+
+impl<T: Ord + Clone> ImmutableAvlTreeSet<T> {
+    pub fn new() -> Self {
+        Self { root: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Returns a set that also holds `value`. If `value` is already present
+    /// the returned set shares the whole tree with `self`.
+    pub fn insert(&self, value: T) -> Self {
+        match insert_below(&self.root, value) {
+            Some(root) => Self { root: Some(root), len: self.len + 1 },
+            None => self.clone(),
+        }
+    }
+
+    /// Returns a set without `value`. If `value` is absent the returned set
+    /// shares the whole tree with `self`.
+    pub fn remove<Q>(&self, value: &Q) -> Self
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match remove_below(&self.root, value) {
+            Some(root) => Self { root, len: self.len - 1 },
+            None => self.clone(),
+        }
+    }
+
+    /// Inserts `value` into this set, returning whether it was new. Only
+    /// the path to the new node is copied, so clones of the set taken
+    /// beforehand keep their values and share every other node.
+    pub fn insert_in_place(&mut self, value: T) -> bool {
+        match insert_below(&self.root, value) {
+            Some(root) => {
+                self.root = Some(root);
+                self.len += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes `value` from this set, returning whether it was present,
+    /// copying only the path to it as `insert_in_place` does.
+    pub fn remove_in_place<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match remove_below(&self.root, value) {
+            Some(root) => {
+                self.root = root;
+                self.len -= 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(value).is_some()
+    }
+
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut current = &self.root;
+
+        while let Some(node) = current {
+            match node.value.borrow().cmp(value) {
+                Ordering::Less => current = &node.right,
+                Ordering::Equal => { return Some(&node.value); }
+                Ordering::Greater => current = &node.left,
+            }
+        }
+
+        None
+    }
+
+    /// Returns `true` if both sets are the same snapshot, i.e. share their
+    /// root node. Sets that merely hold equal values compare `false`.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.root, &other.root) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    pub fn iter(&self) -> ImmutableAvlTreeSetIter<'_, T> {
+        let mut iter = ImmutableAvlTreeSetIter { prev_nodes: Vec::new(), remaining: self.len };
+        iter.push_left_edge(&self.root);
+        iter
+    }
+}
+
+fn height<T>(link: &Link<T>) -> usize {
+    link.as_ref().map_or(0, |node| node.height)
+}
+
+fn new_node<T>(value: T, left: Link<T>, right: Link<T>) -> Arc<ImmutableNode<T>> {
+    let height = cmp::max(height(&left), height(&right)) + 1;
+    Arc::new(ImmutableNode { value, left, right, height })
+}
+
+/// Builds a node from `value` and two subtrees whose heights differ by at
+/// most two, rotating copies of the taller side's nodes if needed.
+fn balanced_node<T: Clone>(value: T, left: Link<T>, right: Link<T>) -> Arc<ImmutableNode<T>> {
+    let (left_height, right_height) = (height(&left), height(&right));
+
+    if left_height > right_height + 1 {
+        let left = left.unwrap();
+
+        if height(&left.left) >= height(&left.right) {
+            let right = new_node(value, left.right.clone(), right);
+            new_node(left.value.clone(), left.left.clone(), Some(right))
+        } else {
+            let pivot = left.right.as_ref().unwrap();
+            let new_left = new_node(left.value.clone(), left.left.clone(), pivot.left.clone());
+            let new_right = new_node(value, pivot.right.clone(), right);
+            new_node(pivot.value.clone(), Some(new_left), Some(new_right))
+        }
+    } else if right_height > left_height + 1 {
+        let right = right.unwrap();
+
+        if height(&right.right) >= height(&right.left) {
+            let left = new_node(value, left, right.left.clone());
+            new_node(right.value.clone(), Some(left), right.right.clone())
+        } else {
+            let pivot = right.left.as_ref().unwrap();
+            let new_left = new_node(value, left, pivot.left.clone());
+            let new_right = new_node(right.value.clone(), pivot.right.clone(), right.right.clone());
+            new_node(pivot.value.clone(), Some(new_left), Some(new_right))
+        }
+    } else {
+        new_node(value, left, right)
+    }
+}
+
+/// Builds a perfectly balanced tree from the next `len` values of `values`,
+/// which are in ascending order.
+fn build_sorted<T, I: Iterator<Item = T>>(values: &mut I, len: usize) -> Link<T> {
+    if len == 0 {
+        return None;
+    }
+
+    let left = build_sorted(values, len / 2);
+    let value = values.next().unwrap();
+    let right = build_sorted(values, len - len / 2 - 1);
+    Some(new_node(value, left, right))
+}
+
+/// Returns the new root of `link` with `value` added, or `None` if the
+/// value is already present and nothing needs copying.
+fn insert_below<T: Ord + Clone>(link: &Link<T>, value: T) -> Option<Arc<ImmutableNode<T>>> {
+    let node = match link {
+        Some(node) => node,
+        None => { return Some(new_node(value, None, None)); }
+    };
+
+    match node.value.cmp(&value) {
+        Ordering::Less => insert_below(&node.right, value)
+            .map(|right| balanced_node(node.value.clone(), node.left.clone(), Some(right))),
+        Ordering::Equal => None,
+        Ordering::Greater => insert_below(&node.left, value)
+            .map(|left| balanced_node(node.value.clone(), Some(left), node.right.clone())),
+    }
+}
+
+/// Returns the new root of `link` with `value` gone, or `None` if the value
+/// is absent and nothing needs copying.
+fn remove_below<T, Q>(link: &Link<T>, value: &Q) -> Option<Link<T>>
+where
+    T: Ord + Clone + Borrow<Q>,
+    Q: Ord + ?Sized,
+{
+    let node = link.as_ref()?;
+
+    match node.value.borrow().cmp(value) {
+        Ordering::Less => remove_below(&node.right, value)
+            .map(|right| Some(balanced_node(node.value.clone(), node.left.clone(), right))),
+        Ordering::Greater => remove_below(&node.left, value)
+            .map(|left| Some(balanced_node(node.value.clone(), left, node.right.clone()))),
+        Ordering::Equal => Some(match (&node.left, &node.right) {
+            (Some(_), Some(right)) => {
+                let (right, successor) = remove_min(right);
+                Some(balanced_node(successor, node.left.clone(), right))
+            }
+            (left, right) => left.clone().or_else(|| right.clone()),
+        }),
+    }
+}
+
+/// Returns the subtree at `node` without its minimum, and a copy of that
+/// minimum.
+fn remove_min<T: Clone>(node: &ImmutableNode<T>) -> (Link<T>, T) {
+    match &node.left {
+        None => (node.right.clone(), node.value.clone()),
+        Some(left) => {
+            let (left, min) = remove_min(left);
+            (Some(balanced_node(node.value.clone(), left, node.right.clone())), min)
+        }
+    }
+}
+
+impl<T: Ord + Clone> Default for ImmutableAvlTreeSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T: Ord + Clone> ImmutableAvlTreeSetIter<'a, T> {
+    fn push_left_edge(&mut self, mut current: &'a Link<T>) {
+        while let Some(node) = current {
+            self.prev_nodes.push(node);
+            current = &node.left;
+        }
+    }
+}
+
+impl<'a, T: Ord + Clone> Iterator for ImmutableAvlTreeSetIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.prev_nodes.pop()?;
+        self.push_left_edge(&node.right);
+        self.remaining -= 1;
+
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T: Ord + Clone> ExactSizeIterator for ImmutableAvlTreeSetIter<'a, T> {}
+
+impl<'a, T: Ord + Clone> FusedIterator for ImmutableAvlTreeSetIter<'a, T> {}
+
+impl<'a, T: Ord + Clone> IntoIterator for &'a ImmutableAvlTreeSet<T> {
+    type Item = &'a T;
+    type IntoIter = ImmutableAvlTreeSetIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Moves the values of a mutable set into a persistent one in O(n), for
+/// when the set is about to be cloned often, e.g. once per undo step.
+impl<T: Ord + Clone> From<AvlTreeSet<T>> for ImmutableAvlTreeSet<T> {
+    fn from(set: AvlTreeSet<T>) -> Self {
+        let len = set.len();
+        Self { root: build_sorted(&mut set.into_iter(), len), len }
+    }
+}
+
+impl<T: Ord + Clone> FromIterator<T> for ImmutableAvlTreeSet<T> {
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> Self {
+        iter.into_iter().fold(Self::new(), |set, value| set.insert(value))
+    }
+}
+
+#[cfg(test)]
+mod properties {
+    use super::*;
+    use itertools::equal;
+    use std::collections::BTreeSet;
+
+    fn is_balanced<T>(link: &Link<T>) -> bool {
+        let node = match link {
+            Some(node) => node,
+            None => { return true; }
+        };
+
+        node.height == cmp::max(height(&node.left), height(&node.right)) + 1
+            && (height(&node.left) as isize - height(&node.right) as isize).abs() <= 1
+            && is_balanced(&node.left)
+            && is_balanced(&node.right)
+    }
+
+    #[quickcheck]
+    fn insert_parity(input: Vec<u16>) -> bool {
+        let mut set = ImmutableAvlTreeSet::new();
+        let mut btree_set = BTreeSet::new();
+
+        input.into_iter().all(|x| {
+            set = set.insert(x);
+            btree_set.insert(x);
+            set.len() == btree_set.len()
+        }) && equal(set.iter(), btree_set.iter())
+            && is_balanced(&set.root)
+    }
+
+    #[quickcheck]
+    fn remove_parity(input: Vec<u8>, removals: Vec<u8>) -> bool {
+        let mut set = input.iter().cloned().collect::<ImmutableAvlTreeSet<_>>();
+        let mut btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        removals.iter().all(|x| {
+            set = set.remove(x);
+            btree_set.remove(x);
+            set.len() == btree_set.len() && !set.contains(x) && is_balanced(&set.root)
+        }) && equal(set.iter(), btree_set.iter())
+    }
+
+    #[quickcheck]
+    fn snapshots_are_unchanged(input: Vec<u8>, changes: Vec<(bool, u8)>) -> bool {
+        let original = input.iter().cloned().collect::<ImmutableAvlTreeSet<_>>();
+        let expected = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        changes.iter().fold(original.clone(), |set, &(insert, x)| {
+            if insert { set.insert(x) } else { set.remove(&x) }
+        });
+
+        equal(original.iter(), expected.iter())
+    }
+
+    #[quickcheck]
+    fn in_place_parity(input: Vec<u8>, changes: Vec<(bool, u8)>) -> bool {
+        let mut set = ImmutableAvlTreeSet::from(input.iter().cloned().collect::<AvlTreeSet<_>>());
+        let mut btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+        let snapshot = set.clone();
+
+        changes.iter().all(|&(insert, x)| {
+            let changed = if insert { set.insert_in_place(x) } else { set.remove_in_place(&x) };
+            let expected = if insert { btree_set.insert(x) } else { btree_set.remove(&x) };
+            changed == expected && set.len() == btree_set.len() && is_balanced(&set.root)
+        }) && equal(set.iter(), btree_set.iter())
+            && equal(snapshot.iter(), input.iter().collect::<BTreeSet<_>>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unchanged_subtrees_are_shared() {
+        let set = (0..64).collect::<ImmutableAvlTreeSet<_>>();
+        let inserted = set.insert(100);
+        let (old_root, new_root) = (set.root.as_ref().unwrap(), inserted.root.as_ref().unwrap());
+
+        assert!(!set.ptr_eq(&inserted));
+        assert!(Arc::ptr_eq(old_root.left.as_ref().unwrap(), new_root.left.as_ref().unwrap()));
+        assert!(set.insert(10).ptr_eq(&set));
+        assert!(set.remove(&100).ptr_eq(&set));
+    }
+}
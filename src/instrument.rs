@@ -0,0 +1,221 @@
+//! Counters for observing how a set balances itself, collected with the
+//! `metrics` feature. Comparisons are counted in a pointer-sized atomic, so
+//! the feature needs a target with atomic read-modify-write operations.
+
+use crate::{tree_height, AvlTreeSet, Compare};
+use core::cmp::{self, Ordering};
+use core::fmt::{self, Debug};
+use core::sync::atomic::{self, AtomicUsize};
+
+/// How often each of the four AVL imbalance cases was fixed, named after
+/// the path from the unbalanced node to the heavy grandchild. The
+/// left-left and right-right cases take a single rotation, the left-right
+/// and right-left cases two.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RotationCounts {
+    pub left_left: u64,
+    pub right_right: u64,
+    pub left_right: u64,
+    pub right_left: u64,
+}
+
+impl RotationCounts {
+    /// The number of single rotations performed, counting a double
+    /// rotation as two.
+    pub fn rotations(&self) -> u64 {
+        self.left_left + self.right_right + 2 * (self.left_right + self.right_left)
+    }
+
+    #[cfg(feature = "rayon")]
+    pub(crate) fn add(&mut self, other: &Self) {
+        self.left_left += other.left_left;
+        self.right_right += other.right_right;
+        self.left_right += other.left_right;
+        self.right_left += other.right_left;
+    }
+}
+
+/// What a set has done since it was created or `reset_metrics` was last
+/// called, returned by `AvlTreeSet::stats`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SetStats {
+    pub rebalances: RotationCounts,
+    /// The greatest height the tree reached after any mutation, or its
+    /// current height if that is greater.
+    pub max_height: usize,
+    /// The comparisons made through the set's comparator, by lookups as
+    /// well as by mutations.
+    pub comparisons: u64,
+}
+
+impl<T, C> AvlTreeSet<T, C> {
+    pub fn stats(&self) -> SetStats {
+        SetStats {
+            rebalances: self.metrics.rebalances,
+            max_height: cmp::max(self.metrics.max_height, tree_height(&self.root)),
+            comparisons: self.compare.comparisons(),
+        }
+    }
+}
+
+/// The comparator a set holds under the `metrics` feature, counting the
+/// comparisons made through it for `stats`.
+pub(crate) struct Instrumented<C> {
+    compare: C,
+    comparisons: AtomicUsize,
+}
+
+pub(crate) const fn instrumented<C>(compare: C) -> Instrumented<C> {
+    Instrumented { compare, comparisons: AtomicUsize::new(0) }
+}
+
+pub(crate) fn uninstrumented<C>(compare: &Instrumented<C>) -> &C {
+    &compare.compare
+}
+
+impl<C> Instrumented<C> {
+    pub(crate) fn comparisons(&self) -> u64 {
+        self.comparisons.load(atomic::Ordering::Relaxed) as u64
+    }
+
+    pub(crate) fn reset(&self) {
+        self.comparisons.store(0, atomic::Ordering::Relaxed);
+    }
+}
+
+/// Clones start counting from the original's count, like the rest of a
+/// cloned set's metrics.
+impl<C: Clone> Clone for Instrumented<C> {
+    fn clone(&self) -> Self {
+        Instrumented { compare: self.compare.clone(), comparisons: AtomicUsize::new(self.comparisons.load(atomic::Ordering::Relaxed)) }
+    }
+}
+
+impl<C: Debug> Debug for Instrumented<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.compare.fmt(f)
+    }
+}
+
+impl<T: ?Sized, C: Compare<T>> Compare<T> for Instrumented<C> {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self.comparisons.fetch_add(1, atomic::Ordering::Relaxed);
+        self.compare.compare(a, b)
+    }
+}
+
+/// A comparator that counts the comparisons made through it, for seeing
+/// how many a set's operations take. Wrap the set's comparator in it with
+/// `AvlTreeSet::with_comparator(Counting::new(Natural))`, e.g. to count
+/// the comparisons of one stretch of code; `stats` counts them all.
+#[derive(Debug, Default)]
+pub struct Counting<C> {
+    compare: C,
+    count: AtomicUsize,
+}
+
+impl<C> Counting<C> {
+    pub fn new(compare: C) -> Self {
+        Self { compare, count: AtomicUsize::new(0) }
+    }
+
+    /// The number of comparisons made so far.
+    pub fn count(&self) -> u64 {
+        self.count.load(atomic::Ordering::Relaxed) as u64
+    }
+
+    pub fn reset(&self) {
+        self.count.store(0, atomic::Ordering::Relaxed);
+    }
+}
+
+/// Clones start counting from the original's count.
+impl<C: Clone> Clone for Counting<C> {
+    fn clone(&self) -> Self {
+        Self { compare: self.compare.clone(), count: AtomicUsize::new(self.count.load(atomic::Ordering::Relaxed)) }
+    }
+}
+
+impl<T: ?Sized, C: Compare<T>> Compare<T> for Counting<C> {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self.count.fetch_add(1, atomic::Ordering::Relaxed);
+        self.compare.compare(a, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Natural;
+
+    #[test]
+    fn stats_separate_rotation_cases() {
+        let rebalances = |values: Vec<i32>| {
+            let mut set = AvlTreeSet::new();
+            set.extend(values);
+            set.stats().rebalances
+        };
+
+        let none = RotationCounts::default();
+        assert_eq!(rebalances(vec![3, 2, 1]), RotationCounts { left_left: 1, ..none });
+        assert_eq!(rebalances(vec![1, 2, 3]), RotationCounts { right_right: 1, ..none });
+        assert_eq!(rebalances(vec![3, 1, 2]), RotationCounts { left_right: 1, ..none });
+        assert_eq!(rebalances(vec![1, 3, 2]), RotationCounts { right_left: 1, ..none });
+
+        let mut set = AvlTreeSet::new();
+        set.extend(0..100);
+        let stats = set.stats();
+        assert_eq!(stats.rebalances.rotations(), set.rotation_count());
+        assert_eq!(stats.max_height, set.height());
+
+        set.reset_metrics();
+        assert_eq!(set.stats().rebalances, none);
+    }
+
+    #[test]
+    fn stats_count_comparisons_and_peaks_of_every_mutation() {
+        let mut set = AvlTreeSet::from_sorted_iter(0..1023);
+        set.reset_metrics();
+
+        assert!(set.contains(&0));
+        assert_eq!(set.stats().comparisons, 10);
+        assert_eq!(set.clone().stats().comparisons, 10);
+
+        set.reset_metrics();
+        for value in 1023..2047 {
+            set.get_or_insert(value);
+        }
+        let peak = set.height();
+        set.retain(|value| value % 64 == 0);
+        assert!(set.height() < peak);
+        assert_eq!(set.stats().max_height, peak);
+        assert!(set.stats().comparisons >= 1024 * 10);
+    }
+
+    // The checks `paranoid` runs after every cursor operation compare too.
+    #[test]
+    #[cfg(not(feature = "paranoid"))]
+    fn insert_near_appends_in_one_comparison_each() {
+        let mut set = AvlTreeSet::from_sorted_iter(0..1023);
+        set.reset_metrics();
+
+        {
+            let mut cursor = set.upper_bound_mut(core::ops::Bound::Unbounded);
+            for value in 1023..2047 {
+                assert!(cursor.insert_near(value));
+            }
+        }
+        assert_eq!(set.stats().comparisons, 1024);
+        assert!(set.stats().rebalances.rotations() <= 1024);
+        assert!(set.iter().copied().eq(0..2047));
+    }
+
+    #[test]
+    fn counting_comparator_counts_lookups() {
+        let set = AvlTreeSet::from_sorted_iter_with(0..1023, Counting::new(Natural));
+        set.comparator().reset();
+
+        assert!(set.contains(&0));
+        assert_eq!(set.comparator().count(), 10);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::augment::Augment;
+use crate::{find_node, insert_into, remove_by, AvlNode, AvlTree, AvlTreeSetIter, Metrics, Natural};
+use alloc::vec::Vec;
+use core::cmp::{self, Ordering};
+use core::iter::{FromIterator, FusedIterator};
+use core::ops::Range;
+
+/// An interval ordered by its start, then its end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Interval<T>(Range<T>);
+
+impl<T: Ord> PartialOrd for Interval<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> Ord for Interval<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.start.cmp(&other.0.start).then_with(|| self.0.end.cmp(&other.0.end))
+    }
+}
+
+/// The greatest end of the intervals in a subtree.
+#[derive(Debug, Clone, PartialEq)]
+struct MaxEnd<T>(T);
+
+impl<T: Ord + Clone> Augment<Interval<T>> for MaxEnd<T> {
+    fn augment(value: &Interval<T>, left: Option<&Self>, right: Option<&Self>) -> Self {
+        let max = [left, right].iter().flatten().fold(&value.0.end, |max, end| cmp::max(max, &end.0));
+        MaxEnd(max.clone())
+    }
+}
+
+/// A set of half-open intervals that finds every stored interval
+/// overlapping a query in O(log n + k) for k results. Each node keeps the
+/// greatest end in its subtree, as the summary the shared balancing code
+/// keeps up to date, so searches skip subtrees that end too soon.
+#[derive(Debug, Clone)]
+pub struct IntervalTreeSet<T: Ord + Clone> {
+    root: AvlTree<Interval<T>, MaxEnd<T>>,
+    len: usize,
+    metrics: Metrics,
+}
+
+pub struct IntervalTreeSetIter<'a, T: Ord + Clone> {
+    inner: AvlTreeSetIter<'a, Interval<T>, MaxEnd<T>>,
+}
+
+pub struct IntervalTreeSetOverlapping<'a, T: Ord + Clone> {
+    prev_nodes: Vec<&'a AvlNode<Interval<T>, MaxEnd<T>>>,
+    query: Range<T>,
+}
+
+impl<T: Ord + Clone> IntervalTreeSet<T> {
+    pub fn new() -> Self {
+        Self { root: None, len: 0, metrics: Metrics::default() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+    }
+
+    pub fn insert(&mut self, interval: Range<T>) -> bool {
+        let inserted = insert_into(&mut self.root, Interval(interval), &Natural, &mut self.metrics);
+        self.len += inserted as usize;
+
+        inserted
+    }
+
+    pub fn contains(&self, interval: &Range<T>) -> bool {
+        let interval = Interval(interval.clone());
+        find_node(&self.root, |current| current.cmp(&interval)).is_some()
+    }
+
+    pub fn remove(&mut self, interval: &Range<T>) -> bool {
+        let interval = Interval(interval.clone());
+        let cmp = |current: &Interval<T>| current.cmp(&interval);
+        let removed = remove_by(&mut self.root, &cmp, &mut self.metrics).is_some();
+        self.len -= removed as usize;
+
+        removed
+    }
+
+    /// Iterates over the intervals in order of their start, then their end.
+    pub fn iter(&self) -> IntervalTreeSetIter<'_, T> {
+        IntervalTreeSetIter { inner: AvlTreeSetIter::new(&self.root, self.len) }
+    }
+
+    /// Iterates in order over the intervals that overlap `query`, i.e.
+    /// those that start before `query` ends and end after it starts.
+    pub fn overlapping(&self, query: Range<T>) -> IntervalTreeSetOverlapping<'_, T> {
+        let mut iter = IntervalTreeSetOverlapping { prev_nodes: Vec::new(), query };
+        iter.push_left_edge(&self.root);
+        iter
+    }
+}
+
+impl<T: Ord + Clone> Default for IntervalTreeSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T: Ord + Clone> Iterator for IntervalTreeSetIter<'a, T> {
+    type Item = &'a Range<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|interval| &interval.0)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T: Ord + Clone> DoubleEndedIterator for IntervalTreeSetIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|interval| &interval.0)
+    }
+}
+
+impl<'a, T: Ord + Clone> ExactSizeIterator for IntervalTreeSetIter<'a, T> {}
+
+impl<'a, T: Ord + Clone> FusedIterator for IntervalTreeSetIter<'a, T> {}
+
+impl<'a, T: Ord + Clone> IntervalTreeSetOverlapping<'a, T> {
+    /// Pushes the left edge below `current`, stopping at the first subtree
+    /// whose intervals all end at or before the query starts.
+    fn push_left_edge(&mut self, mut current: &'a AvlTree<Interval<T>, MaxEnd<T>>) {
+        while let Some(node) = current {
+            if node.augment.0 <= self.query.start {
+                break;
+            }
+
+            self.prev_nodes.push(node);
+            current = &node.left;
+        }
+    }
+}
+
+impl<'a, T: Ord + Clone> Iterator for IntervalTreeSetOverlapping<'a, T> {
+    type Item = &'a Range<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let node = self.prev_nodes.pop()?;
+            let interval = &node.value.0;
+
+            // Every later interval starts no earlier than this one.
+            if interval.start >= self.query.end {
+                self.prev_nodes.clear();
+                return None;
+            }
+
+            self.push_left_edge(&node.right);
+
+            if interval.end > self.query.start {
+                return Some(interval);
+            }
+        }
+    }
+}
+
+impl<'a, T: Ord + Clone> FusedIterator for IntervalTreeSetOverlapping<'a, T> {}
+
+impl<'a, T: Ord + Clone> IntoIterator for &'a IntervalTreeSet<T> {
+    type Item = &'a Range<T>;
+    type IntoIter = IntervalTreeSetIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Ord + Clone> FromIterator<Range<T>> for IntervalTreeSet<T> {
+    fn from_iter<I: IntoIterator<Item=Range<T>>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T: Ord + Clone> Extend<Range<T>> for IntervalTreeSet<T> {
+    fn extend<I: IntoIterator<Item=Range<T>>>(&mut self, iter: I) {
+        for interval in iter {
+            self.insert(interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod properties {
+    use super::*;
+    use itertools::equal;
+    use std::collections::BTreeSet;
+
+    fn to_range((start, end): (u8, u8)) -> Range<u8> {
+        cmp::min(start, end)..cmp::max(start, end)
+    }
+
+    #[quickcheck]
+    fn overlapping_parity(input: Vec<(u8, u8)>, removals: Vec<(u8, u8)>, query: (u8, u8)) -> bool {
+        let mut set = input.iter().cloned().map(to_range).collect::<IntervalTreeSet<_>>();
+        let mut btree_set = input.iter().cloned().map(to_range).map(Interval).collect::<BTreeSet<_>>();
+
+        let removed = removals.into_iter().map(to_range).all(|interval| {
+            set.remove(&interval) == btree_set.remove(&Interval(interval))
+                && crate::augment::is_balanced(&set.root)
+        });
+
+        let query = to_range(query);
+        let expected = btree_set.iter()
+            .map(|interval| &interval.0)
+            .filter(|interval| interval.start < query.end && interval.end > query.start);
+
+        removed
+            && set.len() == btree_set.len()
+            && equal(set.iter(), btree_set.iter().map(|interval| &interval.0))
+            && equal(set.iter().rev(), btree_set.iter().rev().map(|interval| &interval.0))
+            && equal(set.overlapping(query.clone()), expected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_skips_touching_intervals() {
+        let set = vec![0..5, 5..10, 3..4, 8..20, 12..15].into_iter().collect::<IntervalTreeSet<_>>();
+
+        assert_eq!(set.overlapping(4..9).collect::<Vec<_>>(), vec![&(0..5), &(5..10), &(8..20)]);
+        assert_eq!(set.overlapping(10..12).collect::<Vec<_>>(), vec![&(8..20)]);
+        assert_eq!(set.overlapping(20..30).count(), 0);
+    }
+}
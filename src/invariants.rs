@@ -0,0 +1,469 @@
+use crate::{AvlNode, AvlTree, AvlTreeSet, Compare, Side};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+
+/// A broken structural invariant found by `AvlTreeSet::check_invariants`.
+/// `path` holds the steps from the root down to the offending node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvariantViolation<'a, T> {
+    /// The value is not between its ancestors' values in sorted order.
+    Unordered { path: Vec<Side>, value: &'a T },
+    /// The stored height does not match the height of the subtree.
+    WrongHeight { path: Vec<Side>, value: &'a T, stored: usize, actual: usize },
+    /// The stored size does not match the number of values in the subtree.
+    WrongSize { path: Vec<Side>, value: &'a T, stored: usize, actual: usize },
+    /// The heights of the node's children differ by more than one.
+    Unbalanced { path: Vec<Side>, value: &'a T, balance_factor: isize },
+    /// The set's length does not match the number of values in the tree.
+    WrongLen { stored: usize, actual: usize },
+    /// The tree is taller than any AVL tree of `len` values can be.
+    TooTall { height: usize, len: usize },
+}
+
+/// Subtrees smaller than this are checked on one thread by
+/// `par_check_invariants`, where splitting would cost more than it saves.
+#[cfg(feature = "rayon")]
+const PARALLEL_CUTOFF: usize = 1 << 14;
+
+impl<T, C: Compare<T>> AvlTreeSet<T, C> {
+    /// Verifies the ordering, stored heights and sizes, and balance factor of
+    /// every node, along with the set's length and the AVL bound on the
+    /// tree's height, in O(n). The walk uses an explicit stack, so even a
+    /// corrupted, degenerate tree can't overflow the call stack.
+    pub fn check_invariants(&self) -> Result<(), InvariantViolation<'_, T>> {
+        let (height, actual) = check_tree(&self.root, None, None, &self.compare, &mut Vec::new())?;
+        self.check_totals(height, actual)
+    }
+
+    /// With the `paranoid` feature, checks the invariants after `operation`
+    /// has changed the set and panics if they are broken. This costs O(n)
+    /// per call, so it's for test and staging builds; without the feature
+    /// it compiles to nothing. The message leaves out the offending value,
+    /// since `T` needn't be `Debug`. Nothing is checked while a panic
+    /// unwinds, so a cursor dropped after a failed check doesn't panic
+    /// again and abort.
+    #[cfg(feature = "paranoid")]
+    pub(crate) fn verify(&self, operation: &str) {
+        if std::thread::panicking() {
+            return;
+        }
+
+        if let Err(violation) = self.check_invariants() {
+            panic!(
+                "{} left a set of {} values with height {} invalid: {}",
+                operation,
+                self.len,
+                self.height(),
+                Described(&violation)
+            );
+        }
+    }
+
+    #[cfg(not(feature = "paranoid"))]
+    #[inline(always)]
+    pub(crate) fn verify(&self, _operation: &str) {}
+
+    /// Like `verify`, while a cursor holds the set's values as the trees
+    /// `before` and `after` the gap it points at. Paths are relative to the
+    /// root of whichever tree holds the offending node.
+    #[cfg(feature = "paranoid")]
+    pub(crate) fn verify_around(&self, before: &AvlTree<T>, after: &AvlTree<T>, operation: &str) {
+        if std::thread::panicking() {
+            return;
+        }
+
+        if let Err(violation) = self.check_around(before, after) {
+            panic!(
+                "{} left the {} values around a cursor invalid: {}",
+                operation,
+                self.len,
+                Described(&violation)
+            );
+        }
+    }
+
+    #[cfg(feature = "paranoid")]
+    fn check_around<'a>(&self, before: &'a AvlTree<T>, after: &'a AvlTree<T>) -> Result<(), InvariantViolation<'a, T>> {
+        let boundary = crate::first_node(after).map(|node| &node.value);
+        let (before_height, before_len) = check_tree(before, None, boundary, &self.compare, &mut Vec::new())?;
+        let (after_height, after_len) = check_tree(after, None, None, &self.compare, &mut Vec::new())?;
+
+        check_height(before_height, before_len)?;
+        check_height(after_height, after_len)?;
+
+        if before_len + after_len != self.len {
+            return Err(InvariantViolation::WrongLen { stored: self.len, actual: before_len + after_len });
+        }
+
+        Ok(())
+    }
+
+    /// Like `check_invariants`, but checks the two subtrees of every large
+    /// node in parallel. When several nodes are broken, which of them is
+    /// reported may differ from `check_invariants`.
+    #[cfg(feature = "rayon")]
+    pub fn par_check_invariants(&self) -> Result<(), InvariantViolation<'_, T>>
+    where
+        T: Sync,
+        crate::Link<T>: Sync,
+        C: Sync,
+    {
+        let (height, actual) = par_check_tree(&self.root, None, None, &self.compare, Vec::new())?;
+        self.check_totals(height, actual)
+    }
+
+    fn check_totals(&self, height: usize, actual: usize) -> Result<(), InvariantViolation<'_, T>> {
+        if actual != self.len {
+            return Err(InvariantViolation::WrongLen { stored: self.len, actual });
+        }
+
+        check_height(height, actual)
+    }
+}
+
+fn check_height<'a, T>(height: usize, len: usize) -> Result<(), InvariantViolation<'a, T>> {
+    if len < min_len_for_height(height) {
+        return Err(InvariantViolation::TooTall { height, len });
+    }
+
+    Ok(())
+}
+
+/// The fewest values an AVL tree of `height` levels can hold: the sparsest
+/// such tree has sparsest subtrees of heights `height - 1` and
+/// `height - 2`, so these grow like the Fibonacci numbers and the height
+/// stays below about 1.44 log2(n + 2).
+fn min_len_for_height(height: usize) -> usize {
+    let (mut shorter, mut taller) = (0usize, 0usize);
+
+    for level in 0..height {
+        let next = if level == 0 { 1 } else { taller.saturating_add(shorter).saturating_add(1) };
+        shorter = taller;
+        taller = next;
+    }
+
+    taller
+}
+
+/// A step of the walk in `check_tree`: a subtree still to be entered, or a
+/// node whose children have both been checked.
+enum Frame<'a, T> {
+    Enter { tree: &'a AvlTree<T>, lower: Option<&'a T>, upper: Option<&'a T>, depth: usize, side: Option<Side> },
+    Exit { node: &'a AvlNode<T>, depth: usize },
+}
+
+/// Checks the subtree at `tree`, whose values must lie strictly between
+/// `lower` and `upper`, returning its actual height and size. `path` holds
+/// the steps from the root to `tree` and is restored before returning.
+fn check_tree<'a, T, C: Compare<T>>(
+    tree: &'a AvlTree<T>,
+    lower: Option<&'a T>,
+    upper: Option<&'a T>,
+    compare: &C,
+    path: &mut Vec<Side>,
+) -> Result<(usize, usize), InvariantViolation<'a, T>> {
+    let base = path.len();
+    let mut frames = vec![Frame::Enter { tree, lower, upper, depth: base, side: None }];
+    let mut results = Vec::new();
+
+    while let Some(frame) = frames.pop() {
+        match frame {
+            Frame::Enter { tree, lower, upper, depth, side } => {
+                path.truncate(depth.saturating_sub(1).max(base));
+                path.extend(side);
+
+                let node = match tree {
+                    Some(node) => node,
+                    None => {
+                        results.push((0, 0));
+                        continue;
+                    }
+                };
+
+                check_order(node, lower, upper, compare, path)?;
+
+                let value = Some(&node.value);
+                frames.push(Frame::Exit { node, depth });
+                frames.push(Frame::Enter { tree: &node.right, lower: value, upper, depth: depth + 1, side: Some(Side::Right) });
+                frames.push(Frame::Enter { tree: &node.left, lower, upper: value, depth: depth + 1, side: Some(Side::Left) });
+            }
+            Frame::Exit { node, depth } => {
+                path.truncate(depth);
+
+                let right = results.pop().unwrap();
+                let left = results.pop().unwrap();
+                results.push(check_node(node, left, right, path)?);
+            }
+        }
+    }
+
+    path.truncate(base);
+    Ok(results.pop().unwrap())
+}
+
+/// Like `check_tree`, splitting the work across threads with `rayon::join`
+/// down to subtrees of `PARALLEL_CUTOFF` nodes. Prefers the left subtree's
+/// violation when both halves have one.
+#[cfg(feature = "rayon")]
+fn par_check_tree<'a, T: Sync, C: Compare<T> + Sync>(
+    tree: &'a AvlTree<T>,
+    lower: Option<&'a T>,
+    upper: Option<&'a T>,
+    compare: &C,
+    mut path: Vec<Side>,
+) -> Result<(usize, usize), InvariantViolation<'a, T>>
+where
+    crate::Link<T>: Sync,
+{
+    let node = match tree {
+        Some(node) if node.size >= PARALLEL_CUTOFF => node,
+        _ => { return check_tree(tree, lower, upper, compare, &mut path); }
+    };
+
+    check_order(node, lower, upper, compare, &path)?;
+
+    let value = Some(&node.value);
+    let (left_path, right_path) = (child_path(&path, Side::Left), child_path(&path, Side::Right));
+    let (left_tree, right_tree) = (&node.left, &node.right);
+    let (left, right) = rayon::join(
+        || par_check_tree(left_tree, lower, value, compare, left_path),
+        || par_check_tree(right_tree, value, upper, compare, right_path),
+    );
+
+    check_node(node, left?, right?, &path)
+}
+
+#[cfg(feature = "rayon")]
+fn child_path(path: &[Side], side: Side) -> Vec<Side> {
+    let mut child = path.to_vec();
+    child.push(side);
+    child
+}
+
+fn check_order<'a, T, C: Compare<T>>(
+    node: &'a AvlNode<T>,
+    lower: Option<&'a T>,
+    upper: Option<&'a T>,
+    compare: &C,
+    path: &[Side],
+) -> Result<(), InvariantViolation<'a, T>> {
+    let value = &node.value;
+    let above_lower = lower.is_none_or(|lower| compare.compare(lower, value) == Ordering::Less);
+    let below_upper = upper.is_none_or(|upper| compare.compare(value, upper) == Ordering::Less);
+
+    if !above_lower || !below_upper {
+        return Err(InvariantViolation::Unordered { path: path.to_vec(), value });
+    }
+
+    Ok(())
+}
+
+/// Checks `node`'s stored height and size and its balance against the
+/// actual heights and sizes of its children, returning its own.
+fn check_node<'a, T>(
+    node: &'a AvlNode<T>,
+    (left_height, left_size): (usize, usize),
+    (right_height, right_size): (usize, usize),
+    path: &[Side],
+) -> Result<(usize, usize), InvariantViolation<'a, T>> {
+    let value = &node.value;
+    let height = left_height.max(right_height) + 1;
+    let size = left_size + right_size + 1;
+
+    if node.height != height {
+        return Err(InvariantViolation::WrongHeight { path: path.to_vec(), value, stored: node.height, actual: height });
+    }
+
+    if node.size != size {
+        return Err(InvariantViolation::WrongSize { path: path.to_vec(), value, stored: node.size, actual: size });
+    }
+
+    let balance_factor = left_height as isize - right_height as isize;
+
+    if balance_factor.abs() > 1 {
+        return Err(InvariantViolation::Unbalanced { path: path.to_vec(), value, balance_factor });
+    }
+
+    Ok((height, size))
+}
+
+impl<T: fmt::Debug> fmt::Display for InvariantViolation<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvariantViolation::Unordered { path, value } => {
+                write!(f, "value {:?} at {:?} is out of order", value, path)
+            }
+            InvariantViolation::WrongHeight { path, value, stored, actual } => {
+                write!(f, "node {:?} at {:?} stores height {} but has height {}", value, path, stored, actual)
+            }
+            InvariantViolation::WrongSize { path, value, stored, actual } => {
+                write!(f, "node {:?} at {:?} stores size {} but has size {}", value, path, stored, actual)
+            }
+            InvariantViolation::Unbalanced { path, value, balance_factor } => {
+                write!(f, "node {:?} at {:?} has balance factor {}", value, path, balance_factor)
+            }
+            InvariantViolation::WrongLen { stored, actual } => {
+                write!(f, "set stores len {} but holds {} values", stored, actual)
+            }
+            InvariantViolation::TooTall { height, len } => {
+                write!(f, "tree of {} values has height {}", len, height)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: fmt::Debug> std::error::Error for InvariantViolation<'_, T> {}
+
+/// Describes a violation by where it is, for values that aren't `Debug`.
+#[cfg(feature = "paranoid")]
+struct Described<'a, 'b, T>(&'b InvariantViolation<'a, T>);
+
+#[cfg(feature = "paranoid")]
+impl<T> fmt::Display for Described<'_, '_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            InvariantViolation::Unordered { path, .. } => write!(f, "value at {:?} is out of order", path),
+            InvariantViolation::WrongHeight { path, stored, actual, .. } => {
+                write!(f, "node at {:?} stores height {} but has height {}", path, stored, actual)
+            }
+            InvariantViolation::WrongSize { path, stored, actual, .. } => {
+                write!(f, "node at {:?} stores size {} but has size {}", path, stored, actual)
+            }
+            InvariantViolation::Unbalanced { path, balance_factor, .. } => {
+                write!(f, "node at {:?} has balance factor {}", path, balance_factor)
+            }
+            InvariantViolation::WrongLen { stored, actual } => {
+                write!(f, "set stores len {} but holds {} values", stored, actual)
+            }
+            InvariantViolation::TooTall { height, len } => write!(f, "tree of {} values has height {}", len, height),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{min_len_for_height, InvariantViolation};
+    use crate::{AvlNode, AvlTreeSet, Link, Side};
+
+    #[test]
+    fn valid_sets_pass() {
+        assert_eq!(AvlTreeSet::<u8>::new().check_invariants(), Ok(()));
+
+        let mut set = (0..100).rev().collect::<AvlTreeSet<_>>();
+        set.retain(|value| value % 3 != 0);
+        assert_eq!(set.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn min_len_for_height_follows_fibonacci() {
+        let lens = (0..8).map(min_len_for_height).collect::<Vec<_>>();
+
+        assert_eq!(lens, vec![0, 1, 2, 4, 7, 12, 20, 33]);
+        assert_eq!(min_len_for_height(200), usize::MAX);
+    }
+
+    #[test]
+    fn reports_offending_node() {
+        let mut set = (0..7).collect::<AvlTreeSet<_>>();
+
+        set.root.as_mut().unwrap().right.as_mut().unwrap().height = 5;
+        assert_eq!(
+            set.check_invariants(),
+            Err(InvariantViolation::WrongHeight { path: vec![Side::Right], value: &5, stored: 5, actual: 2 })
+        );
+
+        set.root.as_mut().unwrap().right.as_mut().unwrap().height = 2;
+        set.root.as_mut().unwrap().left.as_mut().unwrap().value = 4;
+        assert_eq!(
+            set.check_invariants(),
+            Err(InvariantViolation::Unordered { path: vec![Side::Left], value: &4 })
+        );
+
+        set.root.as_mut().unwrap().left.as_mut().unwrap().value = 1;
+        set.len = 6;
+        assert_eq!(set.check_invariants(), Err(InvariantViolation::WrongLen { stored: 6, actual: 7 }));
+    }
+
+    #[cfg(feature = "paranoid")]
+    #[test]
+    #[should_panic(expected = "insert left a set of 8 values with height 4 invalid: node at [Left] stores size 9 but has size 3")]
+    fn paranoid_mutations_panic_on_corruption() {
+        let mut set = (0..7).collect::<AvlTreeSet<_>>();
+
+        set.root.as_mut().unwrap().left.as_mut().unwrap().size = 9;
+        set.insert(7);
+    }
+
+    #[cfg(feature = "paranoid")]
+    #[test]
+    #[should_panic(expected = "remove left a set of 6 values with height 3 invalid: node at [Left] stores size 9")]
+    fn paranoid_remove_names_itself() {
+        let mut set = (0..7).collect::<AvlTreeSet<_>>();
+
+        set.root.as_mut().unwrap().left.as_mut().unwrap().size = 9;
+        set.remove(&6);
+    }
+
+    #[cfg(feature = "paranoid")]
+    #[test]
+    #[should_panic(expected = "insert_range left a set of 8 values with height 4 invalid: node at [Left] stores size 9")]
+    fn paranoid_insert_range_names_itself() {
+        let mut set = (0..7u32).collect::<AvlTreeSet<_>>();
+
+        set.root.as_mut().unwrap().left.as_mut().unwrap().size = 9;
+        set.insert_range(7..=7);
+    }
+
+    #[cfg(feature = "paranoid")]
+    #[test]
+    #[should_panic(
+        expected = "AvlTreeSetCursorMut::insert_before left the 9 values around a cursor invalid: set stores len 9 but holds 8"
+    )]
+    fn paranoid_cursor_mutations_are_checked() {
+        let mut set = (0..7).map(|value| value * 2).collect::<AvlTreeSet<_>>();
+        let mut cursor = set.lower_bound_mut(core::ops::Bound::Included(&6));
+
+        cursor.set.len += 1;
+        let _ = cursor.insert_before(5);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_check_invariants_matches_sequential() {
+        let mut set = (0..1 << 16).collect::<AvlTreeSet<_>>();
+        assert_eq!(set.par_check_invariants(), Ok(()));
+
+        let mut node = set.root.as_mut().unwrap();
+        for _ in 0..5 {
+            node = node.right.as_mut().unwrap().left.as_mut().unwrap();
+        }
+        node.size += 1;
+
+        assert!(matches!(set.par_check_invariants(), Err(InvariantViolation::WrongSize { .. })));
+        assert_eq!(set.par_check_invariants(), set.check_invariants());
+    }
+
+    #[test]
+    fn reports_unbalanced_node() {
+        let leaf = |value| Some(Link::new(AvlNode { value, left: None, right: None, height: 1, size: 1, augment: () }));
+        let mut set = AvlTreeSet::new();
+        set.root = Some(Link::new(AvlNode {
+            value: 0,
+            left: None,
+            right: Some(Link::new(AvlNode { value: 1, left: None, right: leaf(2), height: 2, size: 2, augment: () })),
+            height: 3,
+            size: 3,
+            augment: (),
+        }));
+        set.len = 3;
+
+        assert_eq!(
+            set.check_invariants(),
+            Err(InvariantViolation::Unbalanced { path: vec![], value: &0, balance_factor: -2 })
+        );
+        assert_eq!(set.check_invariants().unwrap_err().to_string(), "node 0 at [] has balance factor -2");
+    }
+}
@@ -0,0 +1,128 @@
+use crate::{AvlTreeSet, AvlTreeSetIter};
+use core::cmp::Ordering;
+use core::iter::{FusedIterator, Peekable};
+
+/// One step of a merge join: a value found on only one side, or a matching
+/// pair found on both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EitherOrBoth<L, R> {
+    Left(L),
+    Right(R),
+    Both(L, R),
+}
+
+impl<L, R> EitherOrBoth<L, R> {
+    pub fn left(self) -> Option<L> {
+        match self {
+            EitherOrBoth::Left(left) | EitherOrBoth::Both(left, _) => Some(left),
+            EitherOrBoth::Right(_) => None,
+        }
+    }
+
+    pub fn right(self) -> Option<R> {
+        match self {
+            EitherOrBoth::Right(right) | EitherOrBoth::Both(_, right) => Some(right),
+            EitherOrBoth::Left(_) => None,
+        }
+    }
+}
+
+/// Walks a set and another sorted source side by side. Created by
+/// `AvlTreeSet::merge_join_by`.
+pub struct AvlTreeSetMergeJoinBy<'a, T, I: Iterator, F> {
+    left: Peekable<AvlTreeSetIter<'a, T>>,
+    right: Peekable<I>,
+    cmp: F,
+}
+
+impl<T, C> AvlTreeSet<T, C> {
+    /// Joins the set against `other`, which must be sorted consistently
+    /// with `cmp`, in one pass over both and without collecting either.
+    /// Each value comes out as `Left` if only the set has it, `Right` if
+    /// only `other` has it, or `Both` if `cmp` finds them equal, in
+    /// ascending order. Runs of equal values in `other` are each paired
+    /// with the same set value.
+    pub fn merge_join_by<I, F>(&self, other: I, cmp: F) -> AvlTreeSetMergeJoinBy<'_, T, I::IntoIter, F>
+    where
+        I: IntoIterator,
+        F: FnMut(&T, &I::Item) -> Ordering,
+    {
+        AvlTreeSetMergeJoinBy { left: self.iter().peekable(), right: other.into_iter().peekable(), cmp }
+    }
+}
+
+impl<'a, T, I, F> Iterator for AvlTreeSetMergeJoinBy<'a, T, I, F>
+where
+    I: Iterator,
+    F: FnMut(&T, &I::Item) -> Ordering,
+{
+    type Item = EitherOrBoth<&'a T, I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ordering = match (self.left.peek(), self.right.peek()) {
+            (Some(left), Some(right)) => (self.cmp)(left, right),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => return None,
+        };
+
+        match ordering {
+            Ordering::Less => self.left.next().map(EitherOrBoth::Left),
+            Ordering::Greater => self.right.next().map(EitherOrBoth::Right),
+            Ordering::Equal => {
+                let right = self.right.next()?;
+                match self.right.peek() {
+                    Some(next) if (self.cmp)(self.left.peek()?, next) == Ordering::Equal => {
+                        self.left.peek().map(|&left| EitherOrBoth::Both(left, right))
+                    }
+                    _ => self.left.next().map(|left| EitherOrBoth::Both(left, right)),
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (left, _) = self.left.size_hint();
+        let (right_lower, right_upper) = self.right.size_hint();
+        let upper = right_upper.and_then(|right| right.checked_add(left));
+
+        (left.max(right_lower), upper)
+    }
+}
+
+impl<'a, T, I, F> FusedIterator for AvlTreeSetMergeJoinBy<'a, T, I, F>
+where
+    I: FusedIterator,
+    F: FnMut(&T, &I::Item) -> Ordering,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn merge_join_against_a_sorted_stream() {
+        let set = AvlTreeSet::from([1, 3, 5, 7]);
+        let rows = [(2, 'b'), (3, 'c'), (3, 'C'), (7, 'g'), (9, 'i')];
+
+        let joined = set.merge_join_by(rows.iter(), |value, (key, _)| value.cmp(key)).collect::<Vec<_>>();
+        assert_eq!(
+            joined,
+            vec![
+                EitherOrBoth::Left(&1),
+                EitherOrBoth::Right(&(2, 'b')),
+                EitherOrBoth::Both(&3, &(3, 'c')),
+                EitherOrBoth::Both(&3, &(3, 'C')),
+                EitherOrBoth::Left(&5),
+                EitherOrBoth::Both(&7, &(7, 'g')),
+                EitherOrBoth::Right(&(9, 'i')),
+            ]
+        );
+
+        let matched = set.merge_join_by(2..6, |value, key| value.cmp(key)).filter_map(EitherOrBoth::right);
+        assert_eq!(matched.collect::<Vec<_>>(), vec![2, 3, 4, 5]);
+        assert_eq!(AvlTreeSet::<i32>::new().merge_join_by(0..0, |value, key| value.cmp(key)).next(), None);
+    }
+}
@@ -0,0 +1,131 @@
+//! A set that keeps a bounded log of the operations applied to it, so a
+//! failing sequence, e.g. from a fuzzer, can be replayed and minimized.
+
+use crate::{AvlTreeSet, Compare, Natural};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+/// One operation applied to a `JournaledAvlTreeSet`, with what it returned
+/// and the number of rotations it took.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpRecord<T> {
+    Insert { value: T, inserted: bool, rotations: u64 },
+    Remove { value: T, removed: bool, rotations: u64 },
+}
+
+/// A set that logs the last `capacity` insertions and removals, created by
+/// `AvlTreeSet::journaled`. Older records are dropped as new ones arrive.
+#[derive(Debug, Clone)]
+pub struct JournaledAvlTreeSet<T, C = Natural> {
+    set: AvlTreeSet<T, C>,
+    log: VecDeque<OpRecord<T>>,
+    capacity: usize,
+}
+
+impl<T: Clone, C: Compare<T>> AvlTreeSet<T, C> {
+    /// Wraps the set to log its next operations, keeping at most `capacity`
+    /// records.
+    pub fn journaled(self, capacity: usize) -> JournaledAvlTreeSet<T, C> {
+        JournaledAvlTreeSet { set: self, log: VecDeque::new(), capacity }
+    }
+}
+
+impl<T: Clone, C: Compare<T>> JournaledAvlTreeSet<T, C> {
+    pub fn insert(&mut self, value: T) -> bool {
+        let rotations = self.set.rotation_count();
+        let inserted = self.set.insert(value.clone());
+        let rotations = self.set.rotation_count() - rotations;
+
+        self.record(OpRecord::Insert { value, inserted, rotations });
+        inserted
+    }
+
+    pub fn remove(&mut self, value: &T) -> bool {
+        let rotations = self.set.rotation_count();
+        let removed = self.set.remove(value);
+        let rotations = self.set.rotation_count() - rotations;
+
+        self.record(OpRecord::Remove { value: value.clone(), removed, rotations });
+        removed
+    }
+
+    fn record(&mut self, record: OpRecord<T>) {
+        if self.log.len() == self.capacity {
+            self.log.pop_front();
+        }
+        if self.capacity > 0 {
+            self.log.push_back(record);
+        }
+    }
+
+    /// The logged operations, oldest first.
+    pub fn journal(&self) -> Vec<OpRecord<T>> {
+        self.log.iter().cloned().collect()
+    }
+
+    pub fn clear_journal(&mut self) {
+        self.log.clear();
+    }
+
+    /// The set, for every read-only query it supports.
+    pub fn as_set(&self) -> &AvlTreeSet<T, C> {
+        &self.set
+    }
+
+    pub fn into_inner(self) -> AvlTreeSet<T, C> {
+        self.set
+    }
+}
+
+impl<T: Ord + Clone> OpRecord<T> {
+    /// Applies `records` in order to an empty set, returning the set and
+    /// the index of the first record whose result or rotation count the
+    /// replay didn't reproduce, if any.
+    pub fn replay(records: &[OpRecord<T>]) -> (AvlTreeSet<T>, Option<usize>) {
+        let mut set = AvlTreeSet::new();
+
+        let diverged = records.iter().position(|record| {
+            let rotations = set.rotation_count();
+            let (result, expected, expected_rotations) = match record {
+                OpRecord::Insert { value, inserted, rotations } => (set.insert(value.clone()), *inserted, *rotations),
+                OpRecord::Remove { value, removed, rotations } => (set.remove(value), *removed, *rotations),
+            };
+
+            result != expected || set.rotation_count() - rotations != expected_rotations
+        });
+
+        (set, diverged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn journal_replays_operations() {
+        let mut set = AvlTreeSet::new().journaled(4);
+
+        set.insert(1);
+        set.insert(2);
+        set.insert(3);
+        set.remove(&5);
+        set.remove(&1);
+
+        let journal = set.journal();
+        assert_eq!(journal.len(), 4);
+        assert_eq!(journal[0], OpRecord::Insert { value: 2, inserted: true, rotations: 0 });
+        assert_eq!(journal[1], OpRecord::Insert { value: 3, inserted: true, rotations: 1 });
+        assert_eq!(journal[2], OpRecord::Remove { value: 5, removed: false, rotations: 0 });
+
+        let mut full = AvlTreeSet::new().journaled(8);
+        for &value in &[1, 2, 3] {
+            full.insert(value);
+        }
+        full.remove(&1);
+
+        let (replayed, diverged) = OpRecord::replay(&full.journal());
+        assert_eq!((replayed, diverged), (full.into_inner(), None));
+        assert_eq!(OpRecord::replay(&journal).1, Some(1));
+    }
+}
@@ -1,35 +1,375 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+// Only the opt-in C and JavaScript bindings may use `unsafe`, and each has
+// to opt in itself.
+#![cfg_attr(not(any(feature = "capi", feature = "wasm")), forbid(unsafe_code))]
+#![cfg_attr(any(feature = "capi", feature = "wasm"), deny(unsafe_code))]
+
+extern crate alloc;
 #[cfg(test)]
 extern crate quickcheck;
 #[cfg(test)]
 #[macro_use(quickcheck)]
 extern crate quickcheck_macros;
 
-use std::cmp;
-use std::mem;
-use std::cmp::{Ord, Ordering};
-use std::iter::FromIterator;
+use alloc::collections::BTreeSet;
+use alloc::vec::{self, Vec};
+use core::borrow::Borrow;
+use core::cmp;
+use core::mem;
+use core::ptr;
+use core::cmp::{Ord, Ordering};
+use core::fmt::{self, Debug, Display};
+use core::hash::{Hash, Hasher};
+use core::iter::{self, FromIterator, FusedIterator, Peekable};
+use core::marker::PhantomData;
+use core::ops::{BitAnd, BitOr, BitXor, Bound, Index, RangeBounds, Sub};
+#[cfg(target_has_atomic = "ptr")]
+use core::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+pub mod arena;
+pub mod augment;
+mod balance;
+#[cfg(feature = "binary")]
+pub mod binary;
+pub mod bounded;
+mod bytes;
+mod collect;
+pub mod collections;
+mod compare;
+#[cfg(feature = "capi")]
+pub mod ffi;
+pub mod frozen;
+mod group;
+pub mod heap;
+#[cfg(feature = "heap-size")]
+mod heap_size;
+pub mod immutable;
+#[cfg(feature = "metrics")]
+mod instrument;
+pub mod interval;
+mod invariants;
+mod join;
+#[cfg(feature = "journal")]
+pub mod journal;
+mod link;
+pub mod map;
+pub mod multiset;
+mod pool;
+pub mod prefix;
+#[cfg(feature = "test-util")]
+mod proptest_impl;
+mod query;
+#[cfg(feature = "test-util")]
+mod quickcheck_impl;
+mod ranges;
+mod render;
+#[cfg(feature = "rayon")]
+mod rayon_impl;
+#[cfg(feature = "rand")]
+pub mod sample;
+#[cfg(feature = "serde")]
+mod serde_impl;
+pub mod small;
+mod stats;
+mod transaction;
+#[cfg(feature = "snapshots")]
+pub mod versioned;
+mod view;
+mod visit;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use arena::{AvlArraySet, AvlTreeSetArena};
+pub use augment::{Augment, AugmentedAvlTreeSet, MerkleHash, RangeAugment, TreeDelta, WeightSum, Weighted, WeightedValue};
+#[cfg(feature = "binary")]
+pub use binary::{Decode, DecodeError, Encode};
+pub use bounded::{BoundedAvlTreeSet, BoundedInsert, CapacityExceeded, OverflowPolicy};
+pub use bytes::AvlByteSet;
+pub use collect::{AvlTreeSetBuilder, DuplicateValue, OutOfOrder};
+pub use compare::{AvlTreeSetBy, AvlTreeSetDesc, ByKey, Compare, Descending, DynOrd, Incomparable, Natural, TotalOrder};
+pub use frozen::FrozenAvlSet;
+pub use group::AvlTreeSetGroups;
+#[cfg(feature = "heap-size")]
+pub use heap_size::HeapSizeOf;
+pub use immutable::ImmutableAvlTreeSet;
+#[cfg(feature = "metrics")]
+pub use instrument::{Counting, RotationCounts, SetStats};
+pub use interval::IntervalTreeSet;
+pub use invariants::InvariantViolation;
+pub use join::{AvlTreeSetMergeJoinBy, EitherOrBoth};
+#[cfg(feature = "journal")]
+pub use journal::{JournaledAvlTreeSet, OpRecord};
+pub use link::Link;
+pub use map::AvlTreeMap;
+pub use multiset::AvlTreeMultiSet;
+pub use prefix::PrefixBounds;
+#[cfg(feature = "test-util")]
+pub use proptest_impl::{avl_tree_set, AvlTreeSetStrategy, AvlTreeSetValueTree};
+pub use query::RangeQuery;
+pub use ranges::{AvlTreeSetRanges, Discrete};
+pub use render::{AvlTreeSetDisplay, AvlTreeSetShape};
+pub use small::SmallAvlTreeSet;
+pub use stats::BalanceStats;
+pub use transaction::AvlTreeSetTransaction;
+#[cfg(feature = "snapshots")]
+pub use versioned::{SnapshotId, VersionedAvlTreeSet};
+pub use view::AvlTreeSetView;
+
+use balance::Nodes;
+use pool::NodePool;
 
-#[derive(Debug, PartialEq)]
-pub struct AvlNode<T: Ord> {
-    pub value: T,
-    pub left: AvlTree<T>,
-    pub right: AvlTree<T>,
-    pub height: usize,
+/// A node of a tree, with the height and size of the subtree below it and
+/// its summary `A` of that subtree, which is `()` for plain sets and is
+/// recomputed along with the height whenever the subtree changes.
+#[derive(Debug, Clone)]
+pub struct AvlNode<T, A = ()> {
+    value: T,
+    left: AvlTree<T, A>,
+    right: AvlTree<T, A>,
+    height: usize,
+    size: usize,
+    augment: A,
 }
 
-pub type AvlTree<T> = Option<Box<AvlNode<T>>>;
+/// A possibly empty subtree, owned through its root's `Link`: a `Box`, or
+/// with the `cow` feature an `Arc` that clones of the set share until one
+/// of them changes it.
+///
+/// Insertion, removal and every other walk that mutates a tree recurse
+/// once per level and rebalance each node as the recursion unwinds, instead
+/// of keeping parent pointers or an explicit stack of them. An AVL tree of
+/// n nodes is at most about 1.44 log2(n) levels tall, so the recursion is
+/// at most 34 frames deep for ten million values and under 92 for any tree
+/// that fits in a 64-bit address space, and the crate needs no `unsafe`.
+pub type AvlTree<T, A = ()> = Option<Link<T, A>>;
 
-struct AvlTreeSet<T: Ord> {
+/// An ordered set of values, kept sorted by the comparator `C`, which
+/// defaults to the values' own `Ord`. The set and its iterators are `Send`
+/// and `Sync` whenever `T` and `C` are, though with the `cow` feature `T`
+/// has to be both for either.
+#[derive(Clone)]
+pub struct AvlTreeSet<T, C = Natural> {
     root: AvlTree<T>,
+    len: usize,
+    metrics: Metrics,
+    generation: Generation,
+    pool: NodePool<T>,
+    compare: Instrumented<C>,
+}
+
+/// Stamps a set's contents for the caches of `RangeQuery`: an id drawn on
+/// the set's first change, so no two sets share one, and the number of
+/// changes since. A set that was never changed has no stamp and its queries
+/// aren't cached. A clone starts out without a stamp too, since otherwise it
+/// and the original could reach the same stamp with different contents.
+///
+/// Ids are pointer-sized so that targets without 64-bit atomics, e.g.
+/// Cortex-M, can draw them. Targets without atomics at all never stamp a
+/// set, so their queries always descend afresh.
+#[derive(Debug, Default)]
+struct Generation {
+    id: usize,
+    changes: u64,
+}
+
+#[cfg(target_has_atomic = "ptr")]
+static NEXT_SET_ID: AtomicUsize = AtomicUsize::new(1);
+
+impl Generation {
+    const fn new() -> Self {
+        Generation { id: 0, changes: 0 }
+    }
+
+    fn bump(&mut self) {
+        if self.id == 0 {
+            self.id = next_set_id();
+        }
+        self.changes += 1;
+    }
+
+    fn stamp(&self) -> Option<(usize, u64)> {
+        if self.id == 0 {
+            return None;
+        }
+
+        Some((self.id, self.changes))
+    }
+}
+
+/// A fresh set id. Once every id has been drawn this returns 0, which
+/// leaves the set unstamped rather than sharing another set's id.
+#[cfg(target_has_atomic = "ptr")]
+fn next_set_id() -> usize {
+    NEXT_SET_ID.fetch_update(AtomicOrdering::Relaxed, AtomicOrdering::Relaxed, |id| id.checked_add(1)).unwrap_or(0)
+}
+
+#[cfg(not(target_has_atomic = "ptr"))]
+fn next_set_id() -> usize {
+    0
+}
+
+impl Clone for Generation {
+    fn clone(&self) -> Self {
+        Generation::new()
+    }
+}
+
+/// The comparator a set holds: `C` itself, or under the `metrics` feature
+/// `C` wrapped to count the comparisons made through it.
+#[cfg(not(feature = "metrics"))]
+type Instrumented<C> = C;
+
+#[cfg(not(feature = "metrics"))]
+const fn instrumented<C>(compare: C) -> Instrumented<C> {
+    compare
+}
+
+#[cfg(not(feature = "metrics"))]
+fn uninstrumented<C>(compare: &Instrumented<C>) -> &C {
+    compare
+}
+
+#[cfg(feature = "metrics")]
+use instrument::{instrumented, uninstrumented, Instrumented};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Metrics {
+    #[cfg(feature = "metrics")]
+    rotations: u64,
+    #[cfg(feature = "metrics")]
+    rebalances: RotationCounts,
+    #[cfg(feature = "metrics")]
+    max_height: usize,
 }
 
+/// Iterates over a set's values in ascending order. Like every borrowing
+/// iterator and cursor here, it holds a reference to the set, so code that
+/// mutates the set while the iterator is still in use doesn't compile.
+/// Iterators therefore carry no generation check and have no `try_next`:
+/// there is no stale state for either to detect at run time.
 #[derive(Debug)]
-struct AvlTreeSetIter<'a, T: Ord> {
-    prev_nodes: Vec<&'a AvlNode<T>>,
-    current_tree: &'a AvlTree<T>,
+pub struct AvlTreeSetIter<'a, T, A = ()> {
+    traverser: Traverser<&'a AvlNode<T, A>>,
+    remaining: usize,
+}
+
+/// Iterates over a set's values in ascending order along with their
+/// ranks, counted as it goes. Created by `AvlTreeSet::iter_with_rank`.
+#[derive(Debug)]
+pub struct AvlTreeSetRankedIter<'a, T> {
+    iter: AvlTreeSetIter<'a, T>,
+    rank: usize,
+}
+
+#[derive(Debug)]
+pub struct AvlTreeSetRange<'a, T> {
+    traverser: Traverser<&'a AvlNode<T>>,
+}
+
+#[derive(Debug)]
+pub struct AvlTreeSetUnion<'a, T, C = Natural> {
+    left: Peekable<AvlTreeSetIter<'a, T>>,
+    right: Peekable<AvlTreeSetIter<'a, T>>,
+    compare: &'a Instrumented<C>,
+}
+
+#[derive(Debug)]
+pub struct AvlTreeSetIntersection<'a, T, C = Natural> {
+    left: Peekable<AvlTreeSetIter<'a, T>>,
+    right: Peekable<AvlTreeSetIter<'a, T>>,
+    compare: &'a Instrumented<C>,
+}
+
+#[derive(Debug)]
+pub struct AvlTreeSetDifference<'a, T, C = Natural> {
+    left: Peekable<AvlTreeSetIter<'a, T>>,
+    right: Peekable<AvlTreeSetIter<'a, T>>,
+    compare: &'a Instrumented<C>,
+}
+
+#[derive(Debug)]
+pub struct AvlTreeSetSymmetricDifference<'a, T, C = Natural> {
+    left: Peekable<AvlTreeSetIter<'a, T>>,
+    right: Peekable<AvlTreeSetIter<'a, T>>,
+    compare: &'a Instrumented<C>,
+}
+
+/// The values that differ between two sets, each list in ascending order.
+/// Returned by `AvlTreeSet::symmetric_diff_report`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvlTreeSetDiff<'a, T> {
+    pub only_in_self: Vec<&'a T>,
+    pub only_in_other: Vec<&'a T>,
+}
+
+#[derive(Debug)]
+pub struct AvlTreeSetIntoIter<T> {
+    traverser: Traverser<Link<T>>,
+    remaining: usize,
+}
+
+pub struct AvlTreeSetCursor<'a, T, C = Natural> {
+    traverser: Traverser<&'a AvlNode<T>>,
+    compare: PhantomData<&'a C>,
+}
+
+pub struct AvlTreeSetCursorMut<'a, T, C: Compare<T> = Natural> {
+    set: &'a mut AvlTreeSet<T, C>,
+    fingers: Traverser<Link<T>>,
+}
+
+pub struct AvlTreeSetDrainFilter<'a, T, F, C: Compare<T> = Natural> {
+    set: &'a mut AvlTreeSet<T, C>,
+    remaining: AvlTreeSetIntoIter<T>,
+    kept: Vec<T>,
+    pred: F,
+}
+
+pub struct AvlTreeSetDrainSorted<'a, T, C = Natural> {
+    set: &'a mut AvlTreeSet<T, C>,
+}
+
+pub struct AvlTreeSetDrainSortedRev<'a, T, C = Natural> {
+    set: &'a mut AvlTreeSet<T, C>,
 }
 
-impl<'a, T: 'a + Ord> AvlNode<T> {
+impl<T, A> AvlNode<T, A> {
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    pub fn left(&self) -> &AvlTree<T, A> {
+        &self.left
+    }
+
+    pub fn right(&self) -> &AvlTree<T, A> {
+        &self.right
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The number of values in the subtree rooted at this node.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The summary of the subtree rooted at this node.
+    pub fn augment(&self) -> &A {
+        &self.augment
+    }
+
+    /// The smallest value in the subtree rooted at this node.
+    pub fn min(&self) -> &T {
+        first_node(&self.left).map_or(&self.value, |node| &node.value)
+    }
+
+    /// The largest value in the subtree rooted at this node.
+    pub fn max(&self) -> &T {
+        last_node(&self.right).map_or(&self.value, |node| &node.value)
+    }
+
     pub fn left_height(&self) -> usize {
         self.left.as_ref().map_or(0, |left| left.height)
     }
@@ -37,206 +377,4707 @@ impl<'a, T: 'a + Ord> AvlNode<T> {
     pub fn right_height(&self) -> usize {
         self.right.as_ref().map_or(0, |right| right.height)
     }
-    
-    pub fn update_height(&mut self) {
-        self.height = cmp::max(self.left_height(), self.right_height()) + 1;
+
+    fn child(&self, side: Side) -> &AvlTree<T, A> {
+        match side {
+            Side::Left => &self.left,
+            Side::Right => &self.right,
+        }
     }
 
+    fn child_mut(&mut self, side: Side) -> &mut AvlTree<T, A> {
+        match side {
+            Side::Left => &mut self.left,
+            Side::Right => &mut self.right,
+        }
+    }
+
+    /// The left subtree's height minus the right's. Nodes are only built
+    /// by the set, which keeps this within one, or within two while it
+    /// rebalances, so a larger difference trips a debug assertion. Release
+    /// builds clamp it to `i8`'s range rather than let it wrap.
     pub fn balance_factor(&self) -> i8 {
         let left_height = self.left_height();
         let right_height = self.right_height();
+        let difference = cmp::max(left_height, right_height) - cmp::min(left_height, right_height);
+
+        debug_assert!(difference <= 2, "subtree heights {} and {} are out of balance", left_height, right_height);
+        let difference = cmp::min(difference, i8::MAX as usize) as i8;
 
         if left_height >= right_height {
-            (left_height - right_height) as i8
+            difference
         } else {
-            -((right_height - left_height) as i8)
+            -difference
         }
     }
 
-    pub fn rotate_left(&mut self) -> bool {
-        if self.right.is_none() { 
-            return false;
-        }
+}
+
+impl<T> AvlNode<T> {
+    /// Iterates over the subtree rooted at this node in ascending order.
+    pub fn iter(&self) -> AvlTreeSetIter<'_, T> {
+        let mut traverser = Traverser { front: alloc::vec![self], back: alloc::vec![self] };
+
+        push_left_edge(&mut traverser.front, self.left.as_deref());
+        push_right_edge(&mut traverser.back, self.right.as_deref());
+        AvlTreeSetIter { traverser, remaining: self.size }
+    }
+}
+
+impl<T, A: Augment<T>> AvlNode<T, A> {
+    fn leaf(value: T) -> Self {
+        let augment = A::augment(&value, None, None);
+        AvlNode { value, left: None, right: None, height: 1, size: 1, augment }
+    }
 
-        let right_node = self.right.as_mut().unwrap();
-        let right_left_tree = right_node.left.take();
-        let right_right_tree = right_node.right.take();
+    /// Recomputes this node's height, subtree size and summary from its
+    /// children.
+    fn update_height(&mut self) {
+        self.height = cmp::max(self.left_height(), self.right_height()) + 1;
+        self.size = tree_size(&self.left) + tree_size(&self.right) + 1;
+        self.augment = A::augment(
+            &self.value,
+            self.left.as_ref().map(|left| &left.augment),
+            self.right.as_ref().map(|right| &right.augment),
+        );
+    }
+}
+
+/// Which child of a node a step along a path goes to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
 
-        let mut new_left_tree = mem::replace(&mut self.right, right_right_tree);
-        mem::swap(&mut self.value, &mut new_left_tree.as_mut().unwrap().value);
+/// The rotations `rebalance` applied to a node. The double rotations first
+/// rotate the child on the heavy side, then the node itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rotation {
+    Left,
+    Right,
+    RightLeft,
+    LeftRight,
+}
 
-        let left_tree = self.left.take();
-        let new_left_node = new_left_tree.as_mut().unwrap();
+impl Side {
+    fn opposite(self) -> Self {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+}
 
-        new_left_node.right = right_left_tree;
-        new_left_node.left = left_tree;
-        self.left = new_left_tree;
+impl Rotation {
+    /// Updates `path`, the steps from the rotated node down to one of its
+    /// descendants (stored bottom-up), so that it still leads to the same
+    /// value after the rotation.
+    fn update_path(self, path: &mut Vec<Side>) {
+        match self {
+            Rotation::Left => rotate_path(path, Side::Left),
+            Rotation::Right => rotate_path(path, Side::Right),
+            Rotation::RightLeft => {
+                rotate_child_path(path, Side::Right, Side::Right);
+                rotate_path(path, Side::Left);
+            }
+            Rotation::LeftRight => {
+                rotate_child_path(path, Side::Left, Side::Left);
+                rotate_path(path, Side::Right);
+            }
+        }
+    }
+}
 
-        if let Some(node) = self.left.as_mut() {
-            node.update_height();
+/// Rewrites a bottom-up `path` for a rotation towards `side` at its root:
+/// the root's value moves down towards `side` and the child on the opposite
+/// side takes its place.
+fn rotate_path(path: &mut Vec<Side>, side: Side) {
+    match path.pop() {
+        None => path.push(side),
+        Some(step) if step == side => {
+            path.push(side);
+            path.push(side);
+        }
+        Some(_) => {
+            if path.last() == Some(&side) {
+                path.pop();
+                path.push(side.opposite());
+                path.push(side);
+            }
         }
+    }
+}
+
+fn rotate_child_path(path: &mut Vec<Side>, child: Side, side: Side) {
+    if path.last() == Some(&child) {
+        path.pop();
+        rotate_path(path, side);
+        path.push(child);
+    }
+}
+
+/// Boxed trees own their nodes through their links, so the set's
+/// `Metrics` stands in for where they live and counts the rotations.
+impl<T, A: Augment<T>> Nodes<Link<T, A>> for Metrics {
+    fn child_height(&self, node: &Link<T, A>, side: Side) -> usize {
+        tree_height(node.child(side))
+    }
+
+    fn take_child(&mut self, node: &mut Link<T, A>, side: Side) -> Option<Link<T, A>> {
+        node.child_mut(side).take()
+    }
 
-        self.update_height();
+    fn set_child(&mut self, node: &mut Link<T, A>, side: Side, child: Option<Link<T, A>>) {
+        *node.child_mut(side) = child;
+    }
 
-        true
+    fn update(&mut self, node: &mut Link<T, A>) {
+        node.update_height();
     }
 
-    pub fn rotate_right(&mut self) -> bool {
-        if self.left.is_none() {
-            return false;
+    fn rebalanced(&mut self, rotation: Rotation) {
+        if let Rotation::RightLeft | Rotation::LeftRight = rotation {
+            self.record_rotation();
         }
 
-        let left_node = self.left.as_mut().unwrap();
-        let left_left_tree = left_node.left.take();
-        let left_right_tree = left_node.right.take();
+        self.record_rotation();
+        self.record_rebalance(rotation);
+    }
+}
+
+/// Refreshes the root of `tree` after a change below it and rotates it back
+/// into balance.
+fn rebalance<T, A: Augment<T>>(tree: &mut AvlTree<T, A>, metrics: &mut Metrics) -> Option<Rotation> {
+    let (root, rotation) = balance::rebalance(metrics, tree.take()?);
+    *tree = Some(root);
+
+    rotation
+}
+
+impl Metrics {
+    /// The counters a new set starts with, equal to `Metrics::default()`
+    /// but usable in constants.
+    const fn new() -> Self {
+        Self {
+            #[cfg(feature = "metrics")]
+            rotations: 0,
+            #[cfg(feature = "metrics")]
+            rebalances: RotationCounts { left_left: 0, right_right: 0, left_right: 0, right_left: 0 },
+            #[cfg(feature = "metrics")]
+            max_height: 0,
+        }
+    }
 
-        let mut new_right_tree = mem::replace(&mut self.left, left_left_tree);
-        mem::swap(&mut self.value, &mut new_right_tree.as_mut().unwrap().value);
+    #[inline]
+    fn record_rotation(&mut self) {
+        #[cfg(feature = "metrics")]
+        {
+            self.rotations += 1;
+        }
+    }
 
-        let right_tree = self.right.take();
-        let new_right_node = new_right_tree.as_mut().unwrap();
+    #[inline]
+    fn record_rebalance(&mut self, _rotation: Rotation) {
+        #[cfg(feature = "metrics")]
+        {
+            let counts = &mut self.rebalances;
+            let count = match _rotation {
+                Rotation::Right => &mut counts.left_left,
+                Rotation::Left => &mut counts.right_right,
+                Rotation::LeftRight => &mut counts.left_right,
+                Rotation::RightLeft => &mut counts.right_left,
+            };
+            *count += 1;
+        }
+    }
 
-        new_right_node.left = left_right_tree;
-        new_right_node.right = right_tree;
-        self.right = new_right_tree;
+    #[inline]
+    fn record_height<T>(&mut self, _tree: &AvlTree<T>) {
+        #[cfg(feature = "metrics")]
+        {
+            self.max_height = cmp::max(self.max_height, tree_height(_tree));
+        }
+    }
 
-        if let Some(node) = self.right.as_mut() {
-            node.update_height();
+    /// Adds the counts gathered separately by another thread.
+    #[cfg(feature = "rayon")]
+    fn absorb(&mut self, _other: Metrics) {
+        #[cfg(feature = "metrics")]
+        {
+            self.rotations += _other.rotations;
+            self.rebalances.add(&_other.rebalances);
+            self.max_height = cmp::max(self.max_height, _other.max_height);
         }
+    }
+}
+
+fn above_start<Q: ?Sized, C: Compare<Q>>(value: &Q, start: Bound<&Q>, compare: &C) -> bool {
+    match start {
+        Bound::Included(start) => compare.compare(value, start) != Ordering::Less,
+        Bound::Excluded(start) => compare.compare(value, start) == Ordering::Greater,
+        Bound::Unbounded => true,
+    }
+}
 
-        self.update_height();
+fn below_end<Q: ?Sized, C: Compare<Q>>(value: &Q, end: Bound<&Q>, compare: &C) -> bool {
+    match end {
+        Bound::Included(end) => compare.compare(value, end) != Ordering::Greater,
+        Bound::Excluded(end) => compare.compare(value, end) == Ordering::Less,
+        Bound::Unbounded => true,
+    }
+}
 
-        true
+/// Drops `nodes` and all of their descendants without recursing, so that
+/// even a degenerate chain of nodes can't overflow the stack.
+fn drop_iteratively<T, A>(mut nodes: Vec<Link<T, A>>) {
+    while let Some(mut node) = nodes.pop() {
+        if let Some(node) = link::get_unique(&mut node) {
+            nodes.extend(node.left.take());
+            nodes.extend(node.right.take());
+        }
     }
+}
 
-    pub fn rebalance(&mut self) -> bool {
-        match self.balance_factor() {
-            -2 => {
-                let right_node = self.right.as_mut().unwrap();
+fn tree_height<T, A>(tree: &AvlTree<T, A>) -> usize {
+    tree.as_ref().map_or(0, |node| node.height)
+}
 
-                if right_node.balance_factor() == 1 {
-                    right_node.rotate_right();
-                }
+/// Calls `f` on every node of `tree` in order.
+fn for_each_node_mut<T, F: FnMut(&mut AvlNode<T>)>(tree: &mut AvlTree<T>, f: &mut F) {
+    if let Some(node) = tree {
+        for_each_node_mut(&mut node.left, f);
+        f(node);
+        for_each_node_mut(&mut node.right, f);
+    }
+}
 
-                self.rotate_left();
+fn is_strictly_increasing<T, C: Compare<T>>(values: &[T], compare: &C) -> bool {
+    values.windows(2).all(|pair| compare.compare(&pair[0], &pair[1]) == Ordering::Less)
+}
 
-                true
-            },
-            2 => {
-                let left_node = self.left.as_mut().unwrap();
+/// Takes the `k` nearest values from `below`, which walks down from the
+/// query, and `above`, which walks up from it, both paired with their
+/// distances, and returns them in sorted order.
+fn merge_nearest<'a, T, D: Ord>(
+    below: impl Iterator<Item=(D, &'a T)>,
+    above: impl Iterator<Item=(D, &'a T)>,
+    k: usize,
+) -> Vec<&'a T> {
+    let (mut below, mut above) = (below.peekable(), above.peekable());
+    let (mut before, mut after) = (Vec::new(), Vec::new());
 
-                if left_node.balance_factor() == -1 {
-                    left_node.rotate_left();
-                }
+    while before.len() + after.len() < k {
+        let take_below = match (below.peek(), above.peek()) {
+            (Some((below_distance, _)), Some((above_distance, _))) => below_distance <= above_distance,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
 
-                self.rotate_right();
-                
-                true
-            },
-            _ => false,
+        if take_below {
+            before.extend(below.next().map(|(_, value)| value));
+        } else {
+            after.extend(above.next().map(|(_, value)| value));
         }
     }
+
+    before.reverse();
+    before.extend(after);
+    before
 }
 
-impl<T: Ord> AvlTreeSet<T> {
-    fn new() -> Self {
-        Self { root: None }
+fn tree_size<T, A>(tree: &AvlTree<T, A>) -> usize {
+    tree.as_ref().map_or(0, |node| node.size)
+}
+
+/// Builds a perfectly balanced tree from the next `len` values of `values`,
+/// which must be strictly increasing, in O(len) and without rotations.
+fn build_sorted<T, I: Iterator<Item = T>>(values: &mut I, len: usize) -> AvlTree<T> {
+    if len == 0 {
+        return None;
     }
 
-    fn insert(&mut self, value: T) -> bool {
-        let mut current_tree = &mut self.root;
+    let left = build_sorted(values, len / 2);
+    let value = values.next()?;
+    let right = build_sorted(values, len - len / 2 - 1);
 
-        while let Some(current_node) = current_tree {
-            match current_node.value.cmp(&value) {
-                Ordering::Less => current_tree = &mut current_node.right,
-                Ordering::Equal => { return false; }
-                Ordering::Greater => current_tree = &mut current_node.left,
-            }
+    let mut node = AvlNode { value, left, right, height: 0, size: 0, augment: () };
+    node.update_height();
+
+    Some(Link::new(node))
+}
+
+/// Like `build_sorted`, but relinks detached nodes rather than boxing new
+/// ones.
+fn link_sorted<T, I: Iterator<Item = Link<T>>>(nodes: &mut I, len: usize) -> AvlTree<T> {
+    if len == 0 {
+        return None;
+    }
+
+    let left = link_sorted(nodes, len / 2);
+    let mut node = nodes.next()?;
+    node.left = left;
+    node.right = link_sorted(nodes, len - len / 2 - 1);
+    node.update_height();
+
+    Some(node)
+}
+
+fn pop_min<T>(tree: &mut AvlTree<T>, metrics: &mut Metrics) -> Option<T> {
+    pop_min_node(tree, metrics).map(|node| link::into_node(node).value)
+}
+
+/// Like `pop_min`, but hands back the unlinked node rather than its value.
+fn pop_min_node<T, A: Augment<T>>(tree: &mut AvlTree<T, A>, metrics: &mut Metrics) -> Option<Link<T, A>> {
+    match tree {
+        None => None,
+        Some(node) if node.left.is_some() => {
+            let min = pop_min_node(&mut node.left, metrics);
+
+            rebalance(tree, metrics);
+
+            min
         }
+        Some(_) => {
+            let mut node = tree.take().unwrap();
+            *tree = node.right.take();
 
-        *current_tree = Some(Box::new(AvlNode {
-            value,
-            left: None,
-            right: None,
-            height: 0,
-        }));
+            Some(node)
+        }
+    }
+}
+
+fn pop_max<T>(tree: &mut AvlTree<T>, metrics: &mut Metrics) -> Option<T> {
+    match tree {
+        None => None,
+        Some(node) if node.right.is_some() => {
+            let value = pop_max(&mut node.right, metrics);
 
-        true
+            rebalance(tree, metrics);
+
+            value
+        }
+        Some(_) => {
+            let node = link::into_node(tree.take().unwrap());
+            *tree = node.left;
+
+            Some(node.value)
+        }
     }
 }
 
-impl<'a, T: 'a + Ord> Iterator for AvlTreeSetIter<'a, T> {
-    type Item = &'a T;
+/// Inserts `value` below `tree`, updating heights and rebalancing every node
+/// along the insertion path.
+fn insert_into<T, A: Augment<T>, C: Compare<T>>(
+    tree: &mut AvlTree<T, A>,
+    value: T,
+    compare: &C,
+    metrics: &mut Metrics,
+) -> bool {
+    let current_node = match tree {
+        Some(current_node) => current_node,
+        None => {
+            *tree = Some(Link::new(AvlNode::leaf(value)));
 
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            match *self.current_tree {
-                Some(ref current_node) => {
-                    if current_node.left.is_some() {
-                        self.prev_nodes.push(&current_node);
-                        self.current_tree = &current_node.left;
-                        continue;
-                    }
+            return true;
+        }
+    };
 
-                    if current_node.right.is_some() {
-                        self.current_tree = &current_node.right;
-                        return Some(&current_node.value);
-                    }
+    let inserted = match compare.compare(&current_node.value, &value) {
+        Ordering::Less => insert_into(&mut current_node.right, value, compare, metrics),
+        Ordering::Equal => false,
+        Ordering::Greater => insert_into(&mut current_node.left, value, compare, metrics),
+    };
 
-                    self.current_tree = &None;
-                    return Some(&current_node.value);
-                },
-                None => match self.prev_nodes.pop() {
-                    Some(ref prev_node) => {
-                        self.current_tree = &prev_node.right;
-                        return Some(&prev_node.value);
-                    },
-                    None => { return None; },
-                }
-            }
+    if inserted {
+        rebalance(tree, metrics);
+    }
+
+    inserted
+}
+
+/// Like `insert_into`, but links in `node`, a leaf, instead of allocating
+/// one. Hands `node` back if `tree` already holds its value.
+fn insert_node<T, C: Compare<T>>(
+    tree: &mut AvlTree<T>,
+    node: Link<T>,
+    compare: &C,
+    metrics: &mut Metrics,
+) -> Result<(), Link<T>> {
+    let current_node = match tree {
+        Some(current_node) => current_node,
+        None => {
+            *tree = Some(node);
+            return Ok(());
         }
+    };
+
+    let inserted = match compare.compare(&current_node.value, &node.value) {
+        Ordering::Less => insert_node(&mut current_node.right, node, compare, metrics),
+        Ordering::Equal => Err(node),
+        Ordering::Greater => insert_node(&mut current_node.left, node, compare, metrics),
+    };
+
+    if inserted.is_ok() {
+        rebalance(tree, metrics);
     }
+
+    inserted
 }
 
-impl<'a, T: 'a + Ord> AvlTreeSet<T> {
-    fn iter(&'a self) -> AvlTreeSetIter<'a, T> {
-        AvlTreeSetIter {
-            prev_nodes: Vec::new(),
-            current_tree: &self.root,
+/// Like `insert_into`, but returns the steps from the root of `tree` down to
+/// the stored value after rebalancing, stored bottom-up, along with whether
+/// the value was inserted rather than already present.
+fn insert_tracked<T, C: Compare<T>>(
+    tree: &mut AvlTree<T>,
+    value: T,
+    compare: &C,
+    metrics: &mut Metrics,
+) -> (Vec<Side>, bool) {
+    let cmp = |current: &T, value: &T| compare.compare(current, value);
+    insert_tracked_by(tree, value, &cmp, |value| value, metrics)
+}
+
+/// Searches `tree` for `target` using `cmp`, which orders a stored value
+/// against it, and only calls `make` to build the value to insert once the
+/// search has fallen off a leaf. Returns the same as `insert_tracked`.
+fn insert_tracked_by<T, Q, C, F>(
+    tree: &mut AvlTree<T>,
+    target: Q,
+    cmp: &C,
+    make: F,
+    metrics: &mut Metrics,
+) -> (Vec<Side>, bool)
+where
+    C: Fn(&T, &Q) -> Ordering,
+    F: FnOnce(Q) -> T,
+{
+    let current_node = match tree {
+        Some(current_node) => current_node,
+        None => {
+            *tree = Some(Link::new(AvlNode::leaf(make(target))));
+
+            return (Vec::new(), true);
+        }
+    };
+
+    let (side, child) = match cmp(&current_node.value, &target) {
+        Ordering::Less => (Side::Right, &mut current_node.right),
+        Ordering::Equal => { return (Vec::new(), false); }
+        Ordering::Greater => (Side::Left, &mut current_node.left),
+    };
+
+    let (mut path, inserted) = insert_tracked_by(child, target, cmp, make, metrics);
+    path.push(side);
+
+    if inserted {
+        if let Some(rotation) = rebalance(tree, metrics) {
+            rotation.update_path(&mut path);
         }
     }
+
+    (path, inserted)
+}
+
+fn node_at_path_mut<'a, T>(mut current_tree: &'a mut AvlTree<T>, path: &[Side]) -> &'a mut AvlNode<T> {
+    for side in path.iter().rev() {
+        let current_node = current_tree.as_mut().unwrap();
+
+        current_tree = match side {
+            Side::Left => &mut current_node.left,
+            Side::Right => &mut current_node.right,
+        };
+    }
+
+    current_tree.as_mut().unwrap()
 }
 
-impl<T: Ord> FromIterator<T> for AvlTreeSet<T> {
-    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> Self {
-        let mut set = Self::new();
+/// Removes the value for which `cmp` returns `Equal` from `tree`, replacing a
+/// node with two children by its in-order successor, and rebalances every
+/// node on the way back up. `cmp` orders a stored value against the key being
+/// searched for.
+fn remove_by<T, A, F>(tree: &mut AvlTree<T, A>, cmp: &F, metrics: &mut Metrics) -> Option<T>
+where
+    A: Augment<T>,
+    F: Fn(&T) -> Ordering,
+{
+    remove_node_by(tree, cmp, metrics).map(|node| link::into_node(node).value)
+}
 
-        for i in iter {
-            set.insert(i);
+/// Like `remove_by`, but hands back an unlinked node holding the removed
+/// value: the in-order successor's, when that takes the removed value's
+/// place.
+fn remove_node_by<T, A, F>(tree: &mut AvlTree<T, A>, cmp: &F, metrics: &mut Metrics) -> Option<Link<T, A>>
+where
+    A: Augment<T>,
+    F: Fn(&T) -> Ordering,
+{
+    let current_node = tree.as_mut()?;
+
+    let removed = match cmp(&current_node.value) {
+        Ordering::Less => remove_node_by(&mut current_node.right, cmp, metrics),
+        Ordering::Greater => remove_node_by(&mut current_node.left, cmp, metrics),
+        Ordering::Equal if current_node.left.is_some() && current_node.right.is_some() => {
+            let mut successor = pop_min_node(&mut current_node.right, metrics).unwrap();
+            mem::swap(&mut current_node.value, &mut successor.value);
+            Some(successor)
         }
+        Ordering::Equal => {
+            let mut node = tree.take().unwrap();
+            *tree = node.left.take().or_else(|| node.right.take());
 
-        set
+            return Some(node);
+        }
+    };
+
+    if removed.is_some() {
+        rebalance(tree, metrics);
     }
+
+    removed
 }
 
-#[cfg(test)]
-mod properties {
-    use super::*;
-    use itertools::equal;
-    use std::collections::BTreeSet;
+fn find_node<T, A, F>(mut current_tree: &AvlTree<T, A>, cmp: F) -> Option<&AvlNode<T, A>>
+where
+    F: Fn(&T) -> Ordering,
+{
+    while let Some(current_node) = current_tree {
+        match cmp(&current_node.value) {
+            Ordering::Less => current_tree = &current_node.right,
+            Ordering::Equal => { return Some(current_node); }
+            Ordering::Greater => current_tree = &current_node.left,
+        }
+    }
 
-    #[quickcheck]
-    fn iterator_parity(input: Vec<usize>) -> bool {
-        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
-        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+    None
+}
 
-        equal(avl_set.iter(), btree_set.iter())
+fn find_node_mut<T, F>(mut current_tree: &mut AvlTree<T>, cmp: F) -> Option<&mut AvlNode<T>>
+where
+    F: Fn(&T) -> Ordering,
+{
+    while let Some(current_node) = current_tree {
+        match cmp(&current_node.value) {
+            Ordering::Less => current_tree = &mut current_node.right,
+            Ordering::Equal => { return Some(current_node); }
+            Ordering::Greater => current_tree = &mut current_node.left,
+        }
     }
 
-    #[quickcheck]
-    fn insert_parity(mut bt: BTreeSet<u8>, x: u8) -> bool {
-        let mut avl_set = bt.iter().cloned().collect::<AvlTreeSet<_>>();
+    None
+}
 
-        avl_set.insert(x) == bt.insert(x)
+/// Joins `left`, `mid` and `right` into a single balanced tree, where every
+/// value in `left` is less than `mid` and every value in `right` is greater.
+/// Runs in O(|height(left) - height(right)|).
+fn join<T>(left: AvlTree<T>, mid: T, right: AvlTree<T>, metrics: &mut Metrics) -> AvlTree<T> {
+    let mid = Link::new(AvlNode::leaf(mid));
+
+    join_node(left, mid, right, metrics)
+}
+
+/// Like `join`, but links in `mid`, a node whose children are replaced,
+/// instead of allocating one.
+fn join_node<T>(mut left: AvlTree<T>, mid: Link<T>, mut right: AvlTree<T>, metrics: &mut Metrics) -> AvlTree<T> {
+    if tree_height(&left) <= tree_height(&right) {
+        join_front(&mut right, left, mid, metrics);
+        right
+    } else {
+        join_back(&mut left, mid, right, metrics);
+        left
+    }
+}
+
+/// Splits `tree` around `key` into the values less than it, the value equal
+/// to it (if any), and the values greater than it, in O(log n) joins.
+fn split<T, Q, C>(tree: AvlTree<T>, key: &Q, compare: &C, metrics: &mut Metrics) -> (AvlTree<T>, Option<T>, AvlTree<T>)
+where
+    T: Borrow<Q>,
+    Q: ?Sized,
+    C: Compare<Q>,
+{
+    let node = match tree {
+        Some(node) => link::into_node(node),
+        None => { return (None, None, None); }
+    };
+
+    match compare.compare(node.value.borrow(), key) {
+        Ordering::Less => {
+            let (left, mid, right) = split(node.right, key, compare, metrics);
+            (join(node.left, node.value, left, metrics), mid, right)
+        }
+        Ordering::Equal => (node.left, Some(node.value), node.right),
+        Ordering::Greater => {
+            let (left, mid, right) = split(node.left, key, compare, metrics);
+            (left, mid, join(right, node.value, node.right, metrics))
+        }
+    }
+}
+
+/// Concatenates two trees, where every value in `left` is less than every
+/// value in `right`.
+fn concat<T>(mut left: AvlTree<T>, right: AvlTree<T>, metrics: &mut Metrics) -> AvlTree<T> {
+    match pop_max(&mut left, metrics) {
+        Some(mid) => join(left, mid, right, metrics),
+        None => right,
+    }
+}
+
+fn prepend_value<T>(value: Option<T>, tree: AvlTree<T>, metrics: &mut Metrics) -> AvlTree<T> {
+    match value {
+        Some(value) => join(None, value, tree, metrics),
+        None => tree,
+    }
+}
+
+fn append_value<T>(tree: AvlTree<T>, value: Option<T>, metrics: &mut Metrics) -> AvlTree<T> {
+    match value {
+        Some(value) => join(tree, value, None, metrics),
+        None => tree,
+    }
+}
+
+fn first_node<T, A>(mut current_tree: &AvlTree<T, A>) -> Option<&AvlNode<T, A>> {
+    let mut first = None;
+
+    while let Some(current_node) = current_tree {
+        first = Some(current_node.as_ref());
+        current_tree = &current_node.left;
+    }
+
+    first
+}
+
+fn last_node<T, A>(mut current_tree: &AvlTree<T, A>) -> Option<&AvlNode<T, A>> {
+    let mut last = None;
+
+    while let Some(current_node) = current_tree {
+        last = Some(current_node.as_ref());
+        current_tree = &current_node.right;
+    }
+
+    last
+}
+
+fn join_front<T>(tree: &mut AvlTree<T>, left: AvlTree<T>, mut mid: Link<T>, metrics: &mut Metrics) {
+    match tree {
+        Some(node) if node.height > tree_height(&left) + 1 => {
+            join_front(&mut node.left, left, mid, metrics);
+
+            rebalance(tree, metrics);
+        }
+        _ => {
+            mid.left = left;
+            mid.right = tree.take();
+            mid.update_height();
+
+            *tree = Some(mid);
+        }
+    }
+}
+
+fn join_back<T>(tree: &mut AvlTree<T>, mut mid: Link<T>, right: AvlTree<T>, metrics: &mut Metrics) {
+    match tree {
+        Some(node) if node.height > tree_height(&right) + 1 => {
+            join_back(&mut node.right, mid, right, metrics);
+
+            rebalance(tree, metrics);
+        }
+        _ => {
+            mid.left = tree.take();
+            mid.right = right;
+            mid.update_height();
+
+            *tree = Some(mid);
+        }
+    }
+}
+
+impl<T: Ord> AvlTreeSet<T> {
+    /// An empty set, for initializing statics and constants.
+    // Every use is a fresh set, whose comparison counter under the
+    // `metrics` feature is its own.
+    #[allow(clippy::declare_interior_mutable_const)]
+    pub const EMPTY: Self = Self::new();
+
+    /// Creates an empty set. This doesn't allocate, and works in constant
+    /// contexts such as `static SET: Mutex<AvlTreeSet<u32>> =
+    /// Mutex::new(AvlTreeSet::new());`.
+    pub const fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+
+    /// Builds a perfectly balanced set from strictly increasing values in
+    /// O(n), without any rotations.
+    pub fn from_sorted_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from_sorted_iter_with(iter, Natural)
+    }
+
+    /// Builds a perfectly balanced set from a strictly increasing vector,
+    /// such as one returned by `into_sorted_vec`, in O(n).
+    pub fn from_sorted_vec(values: Vec<T>) -> Self {
+        Self::from_sorted_iter(values)
+    }
+
+    /// Collects fallible values into a set, stopping at the first error.
+    /// Equivalent to collecting into `Result<AvlTreeSet<T>, E>`.
+    pub fn try_from_iter<E, I: IntoIterator<Item = Result<T, E>>>(iter: I) -> Result<Self, E> {
+        iter.into_iter().collect()
+    }
+}
+
+impl<T, C: Compare<T>> AvlTreeSet<T, C> {
+    /// Creates an empty set ordered by `compare` instead of `T`'s `Ord`.
+    pub const fn with_comparator(compare: C) -> Self {
+        Self { root: None, len: 0, metrics: Metrics::new(), generation: Generation::new(), pool: NodePool::new(), compare: instrumented(compare) }
+    }
+
+    /// Like `from_sorted_iter`, for values that are strictly increasing
+    /// according to `compare`.
+    pub fn from_sorted_iter_with<I: IntoIterator<Item = T>>(iter: I, compare: C) -> Self {
+        let values = iter.into_iter().collect::<Vec<_>>();
+        debug_assert!(
+            is_strictly_increasing(&values, &compare),
+            "from_sorted_iter input is not strictly increasing"
+        );
+
+        let mut set = Self::with_comparator(compare);
+        set.rebuild_sorted(values);
+        set
+    }
+
+    /// The comparator that orders the set.
+    pub fn comparator(&self) -> &C {
+        uninstrumented(&self.compare)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of rotations performed while rebalancing since the set was
+    /// created or `reset_metrics` was last called.
+    #[cfg(feature = "metrics")]
+    pub fn rotation_count(&self) -> u64 {
+        self.metrics.rotations
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn reset_metrics(&mut self) {
+        self.metrics = Metrics::default();
+        self.compare.reset();
+    }
+
+    pub fn insert(&mut self, value: T) -> bool {
+        let inserted = self.insert_value(value);
+
+        self.finish("insert", inserted);
+        inserted
+    }
+
+    /// Records that `operation` has changed the set; see `finish`.
+    fn changed(&mut self, operation: &str) {
+        self.finish(operation, true);
+    }
+
+    /// The one exit point of every public mutation. If `operation` changed
+    /// the set, cached range queries are invalidated; either way, with the
+    /// `paranoid` feature, the invariants are checked. Calls that leave the
+    /// set as it was, e.g. inserting a value it already holds, pass `false`
+    /// so that polling queries keep their caches.
+    fn finish(&mut self, operation: &str, changed: bool) {
+        if changed {
+            self.generation.bump();
+            self.metrics.record_height(&self.root);
+        }
+        self.verify(operation);
+    }
+
+    /// `insert` without the check after it, for operations that insert
+    /// value by value and check once when done.
+    fn insert_value(&mut self, value: T) -> bool {
+        let inserted = match self.pool.take() {
+            Some(mut node) => {
+                node.value = value;
+
+                match insert_node(&mut self.root, node, &self.compare, &mut self.metrics) {
+                    Ok(()) => true,
+                    Err(node) => {
+                        self.pool.recycle(node);
+                        false
+                    }
+                }
+            }
+            None => insert_into(&mut self.root, value, &self.compare, &mut self.metrics),
+        };
+
+        if inserted {
+            self.len += 1;
+            self.metrics.record_height(&self.root);
+        }
+
+        inserted
+    }
+
+    pub fn insert_with<Q, F>(&mut self, key: &Q, make: F) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        F: FnOnce() -> T,
+    {
+        let (_, inserted) = self.get_or_insert_path(key, |_| make());
+
+        self.finish("insert_with", inserted);
+        inserted
+    }
+
+    /// Inserts `value` if no equal value is present, returning a reference to
+    /// the stored value either way.
+    pub fn get_or_insert(&mut self, value: T) -> &T {
+        let (path, inserted) = insert_tracked(&mut self.root, value, &self.compare, &mut self.metrics);
+
+        if inserted {
+            self.len += 1;
+        }
+
+        self.finish("get_or_insert", inserted);
+        &node_at_path_mut(&mut self.root, &path).value
+    }
+
+    /// Like `insert`, but also returns a reference to the stored value:
+    /// `value` itself if it was inserted, or the equal value already
+    /// present if it wasn't.
+    pub fn insert_and_get(&mut self, value: T) -> (bool, &T) {
+        let (path, inserted) = insert_tracked(&mut self.root, value, &self.compare, &mut self.metrics);
+
+        if inserted {
+            self.len += 1;
+        }
+
+        self.finish("insert_and_get", inserted);
+        (inserted, &node_at_path_mut(&mut self.root, &path).value)
+    }
+
+    /// Appends `value` down the right edge of the tree without comparing it
+    /// against anything, for append-mostly workloads. The caller must
+    /// guarantee that `value` is greater than every value in the set;
+    /// otherwise the set is left out of order and later lookups may miss
+    /// values. This is only checked in debug builds.
+    pub fn insert_unique_unchecked(&mut self, value: T) -> &T {
+        debug_assert!(
+            last_node(&self.root).iter().all(|last| self.compare.compare(&last.value, &value) == Ordering::Less),
+            "insert_unique_unchecked called with a value that is not greater than the set's last value"
+        );
+
+        let (path, _) = insert_tracked_by(&mut self.root, value, &|_: &T, _: &T| Ordering::Less, |value| value, &mut self.metrics);
+        self.len += 1;
+
+        self.changed("insert_unique_unchecked");
+        &node_at_path_mut(&mut self.root, &path).value
+    }
+
+    /// Returns the value equal to `key`, first inserting `make(key)` if there
+    /// is none. `make` is only called when the value is missing, and the
+    /// lookup and insertion share a single descent.
+    pub fn get_or_insert_with<Q, F>(&mut self, key: &Q, make: F) -> &T
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        F: FnOnce(&Q) -> T,
+    {
+        let (path, inserted) = self.get_or_insert_path(key, make);
+
+        self.finish("get_or_insert_with", inserted);
+        &node_at_path_mut(&mut self.root, &path).value
+    }
+
+    fn get_or_insert_path<Q, F>(&mut self, key: &Q, make: F) -> (Vec<Side>, bool)
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        F: FnOnce(&Q) -> T,
+    {
+        let compare = &self.compare;
+        let cmp = |current: &T, key: &&Q| compare.compare(current.borrow(), *key);
+        let make = |key: &Q| {
+            let value = make(key);
+            debug_assert!(compare.compare(value.borrow(), key) == Ordering::Equal);
+            value
+        };
+        let (path, inserted) = insert_tracked_by(&mut self.root, key, &cmp, make, &mut self.metrics);
+
+        if inserted {
+            self.len += 1;
+        }
+
+        (path, inserted)
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.get(value).is_some()
+    }
+
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        find_node(&self.root, |current| self.compare.compare(current.borrow(), value)).map(|node| &node.value)
+    }
+
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let compare = &self.compare;
+        let cmp = |current: &T| compare.compare(current.borrow(), value);
+        let removed = match remove_node_by(&mut self.root, &cmp, &mut self.metrics) {
+            Some(node) => {
+                self.pool.recycle(node);
+                self.len -= 1;
+                true
+            }
+            None => false,
+        };
+
+        self.finish("remove", removed);
+        removed
+    }
+
+    /// Removes and returns the value equal to `value`, if any.
+    pub fn take<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let removed = self.take_value(value);
+
+        self.finish("take", removed.is_some());
+        removed
+    }
+
+    fn take_value<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let compare = &self.compare;
+        let cmp = |current: &T| compare.compare(current.borrow(), value);
+        let removed = remove_by(&mut self.root, &cmp, &mut self.metrics)?;
+        self.len -= 1;
+
+        Some(removed)
+    }
+
+    /// Inserts `value`, replacing and returning an equal value that was
+    /// already present.
+    pub fn replace(&mut self, value: T) -> Option<T> {
+        let compare = &self.compare;
+        let replaced = match find_node_mut(&mut self.root, |current| compare.compare(current, &value)) {
+            Some(node) => Some(mem::replace(&mut node.value, value)),
+            None => {
+                self.insert_value(value);
+                None
+            }
+        };
+
+        self.changed("replace");
+        replaced
+    }
+
+    /// Passes the value equal to `value`, if any, to `f` for mutation in
+    /// place, returning what `f` returns. `f` may change anything that
+    /// doesn't affect the ordering; that the value still compares equal to
+    /// `value` afterwards is only checked in debug builds.
+    pub fn update_with<Q, F, R>(&mut self, value: &Q, f: F) -> Option<R>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        F: FnOnce(&mut T) -> R,
+    {
+        let compare = &self.compare;
+        let node = find_node_mut(&mut self.root, |current| compare.compare(current.borrow(), value))?;
+        let result = f(&mut node.value);
+
+        debug_assert!(
+            compare.compare(node.value.borrow(), value) == Ordering::Equal,
+            "update_with changed the ordering of a value"
+        );
+
+        self.changed("update_with");
+        Some(result)
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        first_node(&self.root).map(|node| &node.value)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        last_node(&self.root).map(|node| &node.value)
+    }
+
+    pub fn pop_first(&mut self) -> Option<T> {
+        let first = pop_min(&mut self.root, &mut self.metrics);
+
+        if first.is_some() {
+            self.len -= 1;
+        }
+
+        self.finish("pop_first", first.is_some());
+        first
+    }
+
+    pub fn pop_last(&mut self) -> Option<T> {
+        let last = pop_max(&mut self.root, &mut self.metrics);
+
+        if last.is_some() {
+            self.len -= 1;
+        }
+
+        self.finish("pop_last", last.is_some());
+        last
+    }
+
+    /// Inserts `values`, which must be sorted. When every value is smaller
+    /// than the set's minimum, they are built into a balanced subtree and
+    /// joined in front of the existing tree in O(log n + k).
+    pub fn prepend_sorted(&mut self, values: &[T])
+    where
+        T: Clone,
+    {
+        let is_prefix = is_strictly_increasing(values, &self.compare)
+            && match (values.last(), self.first()) {
+                (Some(last), Some(first)) => self.compare.compare(last, first) == Ordering::Less,
+                _ => true,
+            };
+
+        if !is_prefix {
+            for value in values {
+                self.insert_value(value.clone());
+            }
+        } else {
+            let mut left = build_sorted(&mut values.iter().cloned(), values.len());
+            self.len += values.len();
+
+            if let Some(mid) = pop_max(&mut left, &mut self.metrics) {
+                self.root = join(left, mid, self.root.take(), &mut self.metrics);
+            }
+        }
+
+        self.changed("prepend_sorted");
+    }
+
+    /// Moves every value of `other` into `self`, leaving `other` empty. Both
+    /// sets are merged as sorted streams and rebuilt into a balanced tree in
+    /// O(n + m). Values of `other` replace equal values already in `self`.
+    pub fn append(&mut self, other: &mut Self) {
+        if other.is_empty() {
+            return;
+        }
+
+        if self.is_empty() {
+            mem::swap(self, other);
+            self.changed("append");
+            other.changed("append");
+            return;
+        }
+
+        let (left, right) = (self.drain(), other.drain());
+        let merged = self.merge_runs(left, right, |_, theirs| theirs);
+        self.rebuild_sorted(merged);
+        self.changed("append");
+    }
+
+    /// Consumes both sets and merges them as sorted streams into a balanced
+    /// set in O(n + m), moving every value rather than cloning it. Each pair
+    /// of equal values is passed to `resolve` as `(ours, theirs)`, and the
+    /// value it returns is the one kept.
+    pub fn merge_sorted<F: FnMut(T, T) -> T>(mut self, mut other: Self, resolve: F) -> Self {
+        let (left, right) = (self.drain(), other.drain());
+        let merged = self.merge_runs(left, right, resolve);
+        self.rebuild_sorted(merged);
+        self.changed("merge_sorted");
+        self
+    }
+
+    /// Inserts every value from `values`, returning how many were new. A
+    /// batch that is large next to the set is sorted, merged with the set's
+    /// own values in one pass and rebuilt balanced in O(n + k log k) without
+    /// rotations; a small one falls back to inserting each value. Either
+    /// way values already in the set win over equal new ones, and of equal
+    /// new values the first is kept.
+    pub fn insert_batch<I: IntoIterator<Item = T>>(&mut self, values: I) -> usize {
+        let inserted = self.insert_batch_values(values);
+
+        self.changed("insert_batch");
+        inserted
+    }
+
+    /// `insert_batch` without the check after it, for operations built on
+    /// it that check once under their own name.
+    fn insert_batch_values<I: IntoIterator<Item = T>>(&mut self, values: I) -> usize {
+        let mut values = values.into_iter().collect::<Vec<_>>();
+        let len = self.len;
+
+        if values.len() * self.height() < len {
+            for value in values {
+                self.insert_value(value);
+            }
+
+            return self.len - len;
+        }
+
+        let compare = &self.compare;
+        values.sort_by(|a, b| compare.compare(a, b));
+        values.dedup_by(|a, b| compare.compare(a, b) == Ordering::Equal);
+
+        let existing = self.drain();
+        let merged = self.merge_runs(existing, values.into_iter(), |ours, _| ours);
+        self.rebuild_sorted(merged);
+
+        self.len - len
+    }
+
+    /// Merges two sorted, deduplicated runs into one, calling `resolve` on
+    /// each pair of equal values.
+    fn merge_runs<L, R, F>(&self, left: L, right: R, mut resolve: F) -> Vec<T>
+    where
+        L: ExactSizeIterator<Item = T>,
+        R: ExactSizeIterator<Item = T>,
+        F: FnMut(T, T) -> T,
+    {
+        let mut merged = Vec::with_capacity(left.len() + right.len());
+        let mut left = left.peekable();
+        let mut right = right.peekable();
+
+        loop {
+            let ordering = match (left.peek(), right.peek()) {
+                (Some(l), Some(r)) => self.compare.compare(l, r),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+
+            match ordering {
+                Ordering::Less => merged.extend(left.next()),
+                Ordering::Equal => merged.push(resolve(left.next().unwrap(), right.next().unwrap())),
+                Ordering::Greater => merged.extend(right.next()),
+            }
+        }
+
+        merged
+    }
+
+    /// Removes every value that is also in `other`, walking both sets in
+    /// order at once in O(n + m) rather than removing values one by one.
+    pub fn remove_all(&mut self, other: &Self) {
+        self.retain_by_membership(other, false);
+        self.changed("remove_all");
+    }
+
+    /// Keeps only the values that are also in `other`, walking both sets in
+    /// order at once in O(n + m).
+    pub fn retain_all(&mut self, other: &Self) {
+        self.retain_by_membership(other, true);
+        self.changed("retain_all");
+    }
+
+    fn retain_by_membership(&mut self, other: &Self, keep_members: bool) {
+        let values = self.drain();
+        let compare = &self.compare;
+        let mut others = other.iter().peekable();
+
+        let retained = values.filter(|value| {
+            while others.next_if(|other| compare.compare(other, value) == Ordering::Less).is_some() {}
+            let is_member = others.peek().is_some_and(|other| compare.compare(other, value) == Ordering::Equal);
+
+            is_member == keep_members
+        }).collect();
+
+        self.rebuild_sorted(retained);
+    }
+
+    /// Keeps only the values for which `f` returns `true`, visiting each
+    /// value once in ascending order and rebuilding a balanced tree from the
+    /// survivors.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        let retained = self.drain().filter(|value| f(value)).collect();
+        self.rebuild_sorted(retained);
+        self.changed("retain");
+    }
+
+    /// Consumes the set, splitting it into the values for which `f` returns
+    /// `true` and those for which it returns `false`. Each value is visited
+    /// once in ascending order and both sets are rebuilt balanced from the
+    /// sorted runs.
+    pub fn partition<F: FnMut(&T) -> bool>(mut self, mut f: F) -> (Self, Self)
+    where
+        C: Clone,
+    {
+        let (matching, rest): (Vec<_>, Vec<_>) = self.drain().partition(|value| f(value));
+
+        let mut other = Self::with_comparator(self.comparator().clone());
+        self.rebuild_sorted(matching);
+        other.rebuild_sorted(rest);
+        self.changed("partition");
+        other.changed("partition");
+        (self, other)
+    }
+
+    /// Passes every value to `f` in ascending order for mutation in place.
+    /// If the mutations broke the ordering, the values are re-sorted and the
+    /// tree is rebuilt, keeping the first of any values that became equal.
+    pub fn for_each_mut<F: FnMut(&mut T)>(&mut self, mut f: F) {
+        for_each_node_mut(&mut self.root, &mut |node| f(&mut node.value));
+
+        let ordered = self.iter().zip(self.iter().skip(1)).all(|(a, b)| self.compare.compare(a, b) == Ordering::Less);
+
+        if !ordered {
+            let values = self.drain().collect();
+            self.rebuild_unsorted(values);
+        }
+
+        self.changed("for_each_mut");
+    }
+
+    /// Replaces every value with `f(value)`, visiting them in ascending
+    /// order, and rebuilds the tree from the results. When `f` preserves the
+    /// ordering this is O(n); otherwise the results are sorted and the first
+    /// of any equal values is kept.
+    pub fn transform<F: FnMut(T) -> T>(&mut self, f: F) {
+        let values = self.drain().map(f).collect();
+        self.rebuild_unsorted(values);
+        self.changed("transform");
+    }
+
+    fn rebuild_unsorted(&mut self, mut values: Vec<T>) {
+        if !is_strictly_increasing(&values, &self.compare) {
+            let compare = &self.compare;
+            values.sort_by(|a, b| compare.compare(a, b));
+            values.dedup_by(|a, b| compare.compare(a, b) == Ordering::Equal);
+        }
+
+        self.rebuild_sorted(values);
+    }
+
+    /// Moves every value greater than or equal to `value` into a new set,
+    /// splitting the tree with O(log n) joins instead of repeated removal.
+    pub fn split_off<Q>(&mut self, value: &Q) -> Self
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q> + Clone,
+    {
+        let (left, mid, right) = split(self.root.take(), value, &self.compare, &mut self.metrics);
+
+        let right = prepend_value(mid, right, &mut self.metrics);
+
+        let mut other = Self::with_comparator(self.comparator().clone());
+        other.len = tree_size(&right);
+        other.root = right;
+
+        self.len -= other.len;
+        self.root = left;
+
+        self.changed("split_off");
+        other.changed("split_off");
+        other
+    }
+
+    /// Joins two sets and a value between them into one set, keeping
+    /// `left`'s comparator. Every value of `left` has to be less than `mid`
+    /// and every value of `right` greater, which is only checked in debug
+    /// builds. Runs in O(|height(left) - height(right)|), i.e. O(log n).
+    pub fn join(mut left: Self, mid: T, mut right: Self) -> Self {
+        debug_assert!(
+            left.last().is_none_or(|last| left.compare.compare(last, &mid) == Ordering::Less)
+                && right.first().is_none_or(|first| left.compare.compare(&mid, first) == Ordering::Less),
+            "join requires left < mid < right"
+        );
+
+        left.root = join(left.root.take(), mid, right.root.take(), &mut left.metrics);
+        left.len += mem::take(&mut right.len) + 1;
+        left.changed("join");
+        left
+    }
+
+    /// Splits the set around `value` into the values less than it, the value
+    /// equal to it if there is one, and the values greater than it, in
+    /// O(log n). The inverse of `join`.
+    pub fn split<Q>(mut self, value: &Q) -> (Self, Option<T>, Self)
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q> + Clone,
+    {
+        let (left, mid, right) = split(self.root.take(), value, &self.compare, &mut self.metrics);
+
+        let mut greater = Self::with_comparator(self.comparator().clone());
+        greater.len = tree_size(&right);
+        greater.root = right;
+
+        self.len = tree_size(&left);
+        self.root = left;
+
+        self.changed("split");
+        greater.changed("split");
+        (self, mid, greater)
+    }
+
+    /// Returns `true` as soon as a value within `range` is found, descending
+    /// towards the bounds without visiting the rest of the range.
+    pub fn any_in_range<Q, R>(&self, range: R) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        R: RangeBounds<Q>,
+    {
+        let mut current_tree = &self.root;
+
+        while let Some(current_node) = current_tree {
+            let value = current_node.value.borrow();
+
+            if !above_start(value, range.start_bound(), &self.compare) {
+                current_tree = &current_node.right;
+                continue;
+            }
+
+            if !below_end(value, range.end_bound(), &self.compare) {
+                current_tree = &current_node.left;
+                continue;
+            }
+
+            return true;
+        }
+
+        false
+    }
+
+    /// Returns the number of values within `range` in O(log n), without
+    /// visiting them. A range whose start lies beyond its end is empty.
+    pub fn count_range<Q, R>(&self, range: R) -> usize
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        R: RangeBounds<Q>,
+    {
+        let before_start = self.count_prefix(|value| !above_start(value.borrow(), range.start_bound(), &self.compare));
+        let up_to_end = self.count_prefix(|value| below_end(value.borrow(), range.end_bound(), &self.compare));
+
+        up_to_end.saturating_sub(before_start)
+    }
+
+    /// Counts the values satisfying `predicate`, which has to hold for a
+    /// prefix of the set in sorted order and for nothing after it.
+    fn count_prefix<F: Fn(&T) -> bool>(&self, predicate: F) -> usize {
+        let mut count = 0;
+        let mut current_tree = &self.root;
+
+        while let Some(current_node) = current_tree {
+            if predicate(&current_node.value) {
+                count += tree_size(&current_node.left) + 1;
+                current_tree = &current_node.right;
+            } else {
+                current_tree = &current_node.left;
+            }
+        }
+
+        count
+    }
+
+    /// Returns the `k` values nearest to `value` in sorted order, or all of
+    /// them if the set holds fewer. Distance is by rank: `value` itself is
+    /// nearest, then the values one position to either side of it, and so
+    /// on, with ties going to the smaller value.
+    pub fn nearest<Q>(&self, value: &Q, k: usize) -> Vec<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let mut above = self.range::<Q, _>((Bound::Included(value), Bound::Unbounded)).peekable();
+        let offset = match above.peek() {
+            Some(next) if self.compare.compare((*next).borrow(), value) == Ordering::Equal => 0,
+            _ => 1,
+        };
+        let below = self.range::<Q, _>((Bound::Unbounded, Bound::Excluded(value))).rev();
+
+        merge_nearest(
+            below.enumerate().map(|(rank, value)| (rank + 1, value)),
+            above.enumerate().map(|(rank, value)| (rank + offset, value)),
+            k,
+        )
+    }
+
+    /// Returns the `k` values nearest to `value` by `distance` in sorted
+    /// order, with ties going to the smaller value. `distance` must not
+    /// decrease going away from `value` in either direction, as with the
+    /// absolute difference of numbers.
+    pub fn nearest_by<Q, D, F>(&self, value: &Q, k: usize, distance: F) -> Vec<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        D: Ord,
+        F: Fn(&T) -> D,
+    {
+        let below = self.range::<Q, _>((Bound::Unbounded, Bound::Excluded(value))).rev();
+        let above = self.range::<Q, _>((Bound::Included(value), Bound::Unbounded));
+
+        merge_nearest(below.map(|value| (distance(value), value)), above.map(|value| (distance(value), value)), k)
+    }
+
+    /// Returns the number of values in the set that are less than `value`,
+    /// in O(log n).
+    pub fn rank<Q>(&self, value: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.search_rank(value).0
+    }
+
+    /// Returns the position of `value` in sorted order, if it is present.
+    pub fn get_index_of<Q>(&self, value: &Q) -> Option<usize>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        match self.search_rank(value) {
+            (rank, true) => Some(rank),
+            (_, false) => None,
+        }
+    }
+
+    /// Returns the value at position `index` in sorted order. Equivalent to
+    /// `select`.
+    pub fn get_index(&self, index: usize) -> Option<&T> {
+        self.select(index)
+    }
+
+    fn search_rank<Q>(&self, value: &Q) -> (usize, bool)
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let mut rank = 0;
+        let mut current_tree = &self.root;
+
+        while let Some(current_node) = current_tree {
+            match self.compare.compare(current_node.value.borrow(), value) {
+                Ordering::Less => {
+                    rank += tree_size(&current_node.left) + 1;
+                    current_tree = &current_node.right;
+                }
+                Ordering::Equal => { return (rank + tree_size(&current_node.left), true); }
+                Ordering::Greater => current_tree = &current_node.left,
+            }
+        }
+
+        (rank, false)
+    }
+
+    /// Returns the `k`-th smallest value in the set, counting from zero, in
+    /// O(log n).
+    pub fn select(&self, mut k: usize) -> Option<&T> {
+        let mut current_tree = &self.root;
+
+        while let Some(current_node) = current_tree {
+            let left_size = tree_size(&current_node.left);
+
+            match k.cmp(&left_size) {
+                Ordering::Less => current_tree = &current_node.left,
+                Ordering::Equal => { return Some(&current_node.value); }
+                Ordering::Greater => {
+                    k -= left_size + 1;
+                    current_tree = &current_node.right;
+                }
+            }
+        }
+
+        None
+    }
+
+    pub fn neighbors<Q>(&self, value: &Q) -> (Option<&T>, Option<&T>)
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let mut predecessor = None;
+        let mut successor = None;
+        let mut current_tree = &self.root;
+
+        while let Some(current_node) = current_tree {
+            match self.compare.compare(current_node.value.borrow(), value) {
+                Ordering::Less => {
+                    predecessor = Some(&current_node.value);
+                    current_tree = &current_node.right;
+                }
+                Ordering::Equal => {
+                    let mut left_tree = &current_node.left;
+                    while let Some(left_node) = left_tree {
+                        predecessor = Some(&left_node.value);
+                        left_tree = &left_node.right;
+                    }
+
+                    let mut right_tree = &current_node.right;
+                    while let Some(right_node) = right_tree {
+                        successor = Some(&right_node.value);
+                        right_tree = &right_node.left;
+                    }
+
+                    break;
+                }
+                Ordering::Greater => {
+                    successor = Some(&current_node.value);
+                    current_tree = &current_node.left;
+                }
+            }
+        }
+
+        (predecessor, successor)
+    }
+
+    /// Returns the greatest value in the set that is less than or equal to
+    /// `value`.
+    pub fn floor<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.closest(value, Ordering::Less, true)
+    }
+
+    /// Returns the smallest value in the set that is greater than or equal
+    /// to `value`.
+    pub fn ceiling<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.closest(value, Ordering::Greater, true)
+    }
+
+    /// Returns the greatest value in the set that is strictly less than
+    /// `value`.
+    pub fn floor_strict<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.closest(value, Ordering::Less, false)
+    }
+
+    /// Returns the smallest value in the set that is strictly greater than
+    /// `value`.
+    pub fn ceiling_strict<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.closest(value, Ordering::Greater, false)
+    }
+
+    /// Returns the smallest value in the set greater than `value`, which
+    /// need not be a member. Equivalent to `ceiling_strict`.
+    pub fn next_after<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.ceiling_strict(value)
+    }
+
+    /// Returns the greatest value in the set less than `value`, which need
+    /// not be a member. Equivalent to `floor_strict`.
+    pub fn next_before<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.floor_strict(value)
+    }
+
+    /// Finds the value nearest to `value` among those on `side` of it (and
+    /// `value` itself if `inclusive`) in one descent, remembering the last
+    /// candidate passed on the way down.
+    fn closest<Q>(&self, value: &Q, side: Ordering, inclusive: bool) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let mut closest = None;
+        let mut current_tree = &self.root;
+
+        while let Some(current_node) = current_tree {
+            let order = self.compare.compare(current_node.value.borrow(), value);
+
+            if order == Ordering::Equal && inclusive {
+                return Some(&current_node.value);
+            }
+
+            let toward_value = if order == side {
+                closest = Some(&current_node.value);
+                side.reverse()
+            } else {
+                side
+            };
+
+            current_tree = match toward_value {
+                Ordering::Less => &current_node.left,
+                _ => &current_node.right,
+            };
+        }
+
+        closest
+    }
+}
+
+impl<T: Ord> Default for AvlTreeSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: PartialEq, C> PartialEq for AvlTreeSet<T, C> {
+    /// Compares the values in sorted order, however the trees are shaped.
+    /// Use `structural_eq` to compare the shapes too.
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, C> Eq for AvlTreeSet<T, C> {}
+
+impl<T: PartialEq, C> PartialEq<BTreeSet<T>> for AvlTreeSet<T, C> {
+    fn eq(&self, other: &BTreeSet<T>) -> bool {
+        self.len == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+/// A set equals a vector holding the same values in ascending order.
+impl<T: PartialEq, C> PartialEq<Vec<T>> for AvlTreeSet<T, C> {
+    fn eq(&self, other: &Vec<T>) -> bool {
+        self.len == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Ord> From<BTreeSet<T>> for AvlTreeSet<T> {
+    /// Builds the tree directly from the already sorted values in O(n).
+    fn from(set: BTreeSet<T>) -> Self {
+        Self::from_sorted_iter(set)
+    }
+}
+
+impl<T: Ord> From<Vec<T>> for AvlTreeSet<T> {
+    /// Sorts and deduplicates the values in place, keeping the first of
+    /// several equal values, and builds the tree without rotations.
+    fn from(values: Vec<T>) -> Self {
+        let mut set = Self::new();
+        set.rebuild_unsorted(values);
+        set
+    }
+}
+
+impl<T: Ord, const N: usize> From<[T; N]> for AvlTreeSet<T> {
+    fn from(values: [T; N]) -> Self {
+        Self::from(Vec::from(values))
+    }
+}
+
+impl<T: Ord, C> From<AvlTreeSet<T, C>> for BTreeSet<T> {
+    fn from(set: AvlTreeSet<T, C>) -> Self {
+        set.into_iter().collect()
+    }
+}
+
+impl<T: PartialOrd, C> PartialOrd for AvlTreeSet<T, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord, C> Ord for AvlTreeSet<T, C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<T: Hash, C> Hash for AvlTreeSet<T, C> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len);
+
+        for value in self {
+            value.hash(state);
+        }
+    }
+}
+
+impl<T: Debug, C> Debug for AvlTreeSet<T, C> {
+    /// Formats the values like `{1, 2, 3}`. The alternate form `{:#?}` draws
+    /// the tree's shape instead, with each node's height and balance.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            Display::fmt(&self.shape(), f)
+        } else {
+            f.debug_set().entries(self.iter()).finish()
+        }
+    }
+}
+
+impl<T, C: Compare<T>> Index<usize> for AvlTreeSet<T, C> {
+    type Output = T;
+
+    /// Returns the value at position `index` in sorted order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    fn index(&self, index: usize) -> &T {
+        match self.select(index) {
+            Some(value) => value,
+            None => panic!("index out of bounds: the len is {} but the index is {}", self.len, index),
+        }
+    }
+}
+
+impl<T, C> Drop for AvlTreeSet<T, C> {
+    fn drop(&mut self) {
+        drop_iteratively(self.root.take().into_iter().collect());
+    }
+}
+
+/// The state shared by the in-order iterators: a stack of the nodes still
+/// to visit from the front, topped by the next one, and a stack for the
+/// back, topped by the last one. The borrowing iterators walk from both
+/// ends, which meet at the last node left between them, after which both
+/// stacks are cleared. Seeding the stacks with partial edges bounds the
+/// traversal to a range. `drain` owns its nodes, which can't sit on both
+/// stacks at once, so it only walks from the front.
+///
+/// A cursor seeds the stacks differently, with the ancestors of its gap:
+/// `front` the nodes after it, `back` those before it, each topped by the
+/// node nearest the gap. `advance` and `retreat` move the gap by one node,
+/// so the stacks never meet.
+#[derive(Debug, Clone)]
+struct Traverser<N> {
+    front: Vec<N>,
+    back: Vec<N>,
+}
+
+/// How a traversal holds a node: borrowed from the tree, or owned once a
+/// drain has unlinked it. Taking a child of a borrowed node leaves it in
+/// place.
+trait Frame: Sized {
+    fn take_left(&mut self) -> Option<Self>;
+    fn take_right(&mut self) -> Option<Self>;
+}
+
+impl<'a, T, A> Frame for &'a AvlNode<T, A> {
+    fn take_left(&mut self) -> Option<Self> {
+        let node: &'a AvlNode<T, A> = self;
+        node.left.as_deref()
+    }
+
+    fn take_right(&mut self) -> Option<Self> {
+        let node: &'a AvlNode<T, A> = self;
+        node.right.as_deref()
+    }
+}
+
+impl<T> Frame for Link<T> {
+    fn take_left(&mut self) -> Option<Self> {
+        self.left.take()
+    }
+
+    fn take_right(&mut self) -> Option<Self> {
+        self.right.take()
+    }
+}
+
+impl<N: Frame> Traverser<N> {
+    /// Walks every node of `tree`, from the front only.
+    fn front_only(tree: Option<N>) -> Self {
+        let mut traverser = Traverser { front: Vec::new(), back: Vec::new() };
+
+        push_left_edge(&mut traverser.front, tree);
+        traverser
+    }
+
+    /// Pops the next node from the front, stacking the left edge of its
+    /// right subtree in its place.
+    fn step_front(&mut self) -> Option<N> {
+        let mut node = self.front.pop()?;
+
+        push_left_edge(&mut self.front, node.take_right());
+        Some(node)
+    }
+
+    fn step_back(&mut self) -> Option<N> {
+        let mut node = self.back.pop()?;
+
+        push_right_edge(&mut self.back, node.take_left());
+        Some(node)
+    }
+
+    fn finish(&mut self) {
+        self.front.clear();
+        self.back.clear();
+    }
+}
+
+impl<'a, T, A> Traverser<&'a AvlNode<T, A>> {
+    /// Traverses every node of `tree`.
+    fn new(tree: &'a AvlTree<T, A>) -> Self {
+        let mut traverser = Traverser { front: Vec::new(), back: Vec::new() };
+
+        push_left_edge(&mut traverser.front, tree.as_deref());
+        push_right_edge(&mut traverser.back, tree.as_deref());
+        traverser
+    }
+
+    fn next_front(&mut self) -> Option<&'a AvlNode<T, A>> {
+        let node = *self.front.last()?;
+        let back = *self.back.last()?;
+
+        if ptr::eq(node, back) {
+            self.finish();
+            Some(node)
+        } else {
+            self.step_front()
+        }
+    }
+
+    fn next_back(&mut self) -> Option<&'a AvlNode<T, A>> {
+        let node = *self.back.last()?;
+        let front = *self.front.last()?;
+
+        if ptr::eq(node, front) {
+            self.finish();
+            Some(node)
+        } else {
+            self.step_back()
+        }
+    }
+
+    /// Stacks the ancestors of the gap in `tree` that lies after the values
+    /// for which `before_gap` holds and before the rest, for a cursor.
+    fn around<F: Fn(&T) -> bool>(tree: &'a AvlTree<T, A>, before_gap: F) -> Self {
+        let mut traverser = Traverser { front: Vec::new(), back: Vec::new() };
+        let mut current_tree = tree;
+
+        while let Some(current_node) = current_tree {
+            if before_gap(&current_node.value) {
+                traverser.back.push(current_node.as_ref());
+                current_tree = &current_node.right;
+            } else {
+                traverser.front.push(current_node.as_ref());
+                current_tree = &current_node.left;
+            }
+        }
+
+        traverser
+    }
+
+    /// Moves a cursor's gap past the next node. The node becomes the
+    /// nearest ancestor before the gap, in place of the right edge of its
+    /// left subtree, which topped `back` and is now below it. Amortized
+    /// O(1), since a walk over the whole tree pushes and pops each node
+    /// once per stack.
+    fn advance(&mut self) -> Option<&'a AvlNode<T, A>> {
+        let node = self.step_front()?;
+        let mut covered = node.left.as_deref();
+
+        while let Some(covered_node) = covered {
+            self.back.pop();
+            covered = covered_node.right.as_deref();
+        }
+
+        self.back.push(node);
+        Some(node)
+    }
+
+    /// Moves a cursor's gap back past the previous node.
+    fn retreat(&mut self) -> Option<&'a AvlNode<T, A>> {
+        let node = self.step_back()?;
+        let mut covered = node.right.as_deref();
+
+        while let Some(covered_node) = covered {
+            self.front.pop();
+            covered = covered_node.left.as_deref();
+        }
+
+        self.front.push(node);
+        Some(node)
+    }
+}
+
+impl<T> Traverser<Link<T>> {
+    /// Stacks the edges of `before` and `after` that face the gap between
+    /// them, for a mutable cursor: `back` the right edge of `before` and
+    /// `front` the left edge of `after`, each topped by the node nearest the
+    /// gap. Stacked nodes keep only their child away from the gap, so
+    /// `step_front` and `step_back` unlink the values next to it in
+    /// amortized O(1).
+    fn around_gap(before: AvlTree<T>, after: AvlTree<T>) -> Self {
+        let mut fingers = Traverser { front: Vec::new(), back: Vec::new() };
+
+        push_right_edge(&mut fingers.back, before);
+        push_left_edge(&mut fingers.front, after);
+        fingers
+    }
+
+    /// Stacks `node`, a leaf whose value lies between the gap and every
+    /// value after it, as the next value. Like a carry in a binary counter,
+    /// the subtree below the top is joined into it while it's no taller
+    /// than the top's own, keeping the stack O(log n) deep at an amortized
+    /// O(1) cost while values keep coming in at the gap.
+    fn push_front_leaf(&mut self, node: Link<T>, metrics: &mut Metrics) {
+        self.front.push(node);
+
+        while let [.., below, nearest] = self.front.as_slice() {
+            if tree_height(&below.right) > tree_height(&nearest.right) {
+                break;
+            }
+
+            let mut nearest = self.front.pop().unwrap();
+            let mut below = self.front.pop().unwrap();
+            let (near, far) = (nearest.right.take(), below.right.take());
+
+            nearest.right = join_node(near, below, far, metrics);
+            self.front.push(nearest);
+        }
+    }
+
+    /// Stacks `node`, a leaf whose value lies between every value before
+    /// the gap and the gap, as the previous value.
+    fn push_back_leaf(&mut self, node: Link<T>, metrics: &mut Metrics) {
+        self.back.push(node);
+
+        while let [.., below, nearest] = self.back.as_slice() {
+            if tree_height(&below.left) > tree_height(&nearest.left) {
+                break;
+            }
+
+            let mut nearest = self.back.pop().unwrap();
+            let mut below = self.back.pop().unwrap();
+            let (far, near) = (below.left.take(), nearest.left.take());
+
+            nearest.left = join_node(far, below, near, metrics);
+            self.back.push(nearest);
+        }
+    }
+
+    /// Joins the nodes on `back` into a tree of the values before the gap,
+    /// in O(log n).
+    fn take_before(&mut self, metrics: &mut Metrics) -> AvlTree<T> {
+        let mut tree = None;
+
+        while let Some(mut node) = self.back.pop() {
+            let far = node.left.take();
+            tree = join_node(far, node, tree, metrics);
+        }
+
+        tree
+    }
+
+    /// Joins the nodes on `front` into a tree of the values after the gap.
+    fn take_after(&mut self, metrics: &mut Metrics) -> AvlTree<T> {
+        let mut tree = None;
+
+        while let Some(mut node) = self.front.pop() {
+            let far = node.right.take();
+            tree = join_node(tree, node, far, metrics);
+        }
+
+        tree
+    }
+}
+
+fn push_left_edge<N: Frame>(nodes: &mut Vec<N>, mut current: Option<N>) {
+    while let Some(mut node) = current {
+        current = node.take_left();
+        nodes.push(node);
+    }
+}
+
+fn push_right_edge<N: Frame>(nodes: &mut Vec<N>, mut current: Option<N>) {
+    while let Some(mut node) = current {
+        current = node.take_right();
+        nodes.push(node);
+    }
+}
+
+impl<'a, T: 'a, A> Iterator for AvlTreeSetIter<'a, T, A> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current_node = self.traverser.next_front()?;
+        self.remaining -= 1;
+
+        Some(&current_node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T: 'a, A> DoubleEndedIterator for AvlTreeSetIter<'a, T, A> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let current_node = self.traverser.next_back()?;
+        self.remaining -= 1;
+
+        Some(&current_node.value)
+    }
+}
+
+impl<'a, T: 'a, A> ExactSizeIterator for AvlTreeSetIter<'a, T, A> {}
+
+impl<'a, T: 'a, A> FusedIterator for AvlTreeSetIter<'a, T, A> {}
+
+impl<'a, T: 'a> Iterator for AvlTreeSetRankedIter<'a, T> {
+    type Item = (usize, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = self.iter.next()?;
+        self.rank += 1;
+
+        Some((self.rank - 1, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, T: 'a> DoubleEndedIterator for AvlTreeSetRankedIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let value = self.iter.next_back()?;
+
+        Some((self.rank + self.iter.remaining, value))
+    }
+}
+
+impl<'a, T: 'a> ExactSizeIterator for AvlTreeSetRankedIter<'a, T> {}
+
+impl<'a, T: 'a> FusedIterator for AvlTreeSetRankedIter<'a, T> {}
+
+impl<T, C> AvlTreeSet<T, C> {
+    pub fn iter(&self) -> AvlTreeSetIter<'_, T> {
+        AvlTreeSetIter::new(&self.root, self.len)
+    }
+
+    /// Like `iter`, also yielding each value's rank, its zero-based position
+    /// in the set, from either end.
+    pub fn iter_with_rank(&self) -> AvlTreeSetRankedIter<'_, T> {
+        AvlTreeSetRankedIter { iter: self.iter(), rank: 0 }
+    }
+
+    /// The root of the tree, for walking it node by node.
+    pub fn root_node(&self) -> Option<&AvlNode<T>> {
+        self.root.as_deref()
+    }
+
+    /// Returns an iterator over the `k` smallest values in ascending order,
+    /// which only visits O(k + log n) nodes.
+    pub fn smallest(&self, k: usize) -> iter::Take<AvlTreeSetIter<'_, T>> {
+        self.iter().take(k)
+    }
+
+    /// Returns an iterator over the `k` largest values in descending order,
+    /// largest first, which only visits O(k + log n) nodes.
+    pub fn largest(&self, k: usize) -> iter::Take<iter::Rev<AvlTreeSetIter<'_, T>>> {
+        self.iter().rev().take(k)
+    }
+
+    /// Consumes the set into its values in ascending order, moving each
+    /// value out of its node once and allocating the vector once.
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        self.into_iter().collect()
+    }
+
+    /// Clones the values in ascending order into an owned iterator, e.g. so
+    /// a lock guarding the set can be released before they are processed.
+    /// The traversal is iterative and allocates exactly once.
+    pub fn iter_snapshot(&self) -> vec::IntoIter<T>
+    where
+        T: Clone,
+    {
+        self.iter().cloned().collect::<Vec<_>>().into_iter()
+    }
+
+    /// Relinks the tree into a shape of minimum height in O(n), e.g. after
+    /// a wave of removals has left it taller than its size needs. No values
+    /// are moved and no nodes are allocated or freed, only the links between
+    /// them change; if the set has a node pool, follow with `shrink_to_fit`
+    /// to free the nodes the removals left in it. Under the `cow` feature,
+    /// nodes still shared with a clone are copied as they're relinked, and
+    /// the clone keeps the originals.
+    pub fn optimize(&mut self)
+    where
+        C: Compare<T>,
+    {
+        let mut nodes = Vec::with_capacity(self.len);
+        let mut stack = Vec::new();
+        let mut tree = self.root.take();
+
+        loop {
+            while let Some(mut node) = tree {
+                tree = node.left.take();
+                stack.push(node);
+            }
+
+            match stack.pop() {
+                Some(mut node) => {
+                    tree = node.right.take();
+                    nodes.push(node);
+                }
+                None => break,
+            }
+        }
+
+        self.root = link_sorted(&mut nodes.into_iter(), self.len);
+        self.metrics.record_height(&self.root);
+        self.changed("optimize");
+    }
+
+    fn rebuild_sorted(&mut self, values: Vec<T>) {
+        drop_iteratively(self.root.take().into_iter().collect());
+        self.generation.bump();
+
+        self.len = values.len();
+        self.root = build_sorted(&mut values.into_iter(), self.len);
+        self.metrics.record_height(&self.root);
+    }
+}
+
+impl<'a, T: 'a, A> AvlTreeSetIter<'a, T, A> {
+    fn new(root: &'a AvlTree<T, A>, len: usize) -> Self {
+        AvlTreeSetIter { traverser: Traverser::new(root), remaining: len }
+    }
+}
+
+impl<T, C: Compare<T>> AvlTreeSet<T, C> {
+    /// Returns an iterator over the values greater than or equal to `value`
+    /// in ascending order. A single descent seeds the traversal and counts
+    /// the values skipped, so the iterator is exact-size and double-ended
+    /// like `iter`.
+    pub fn iter_from<Q>(&self, value: &Q) -> AvlTreeSetIter<'_, T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let mut front_nodes = Vec::new();
+        let mut skipped = 0;
+        let mut current_tree = &self.root;
+
+        while let Some(current_node) = current_tree {
+            if self.compare.compare(current_node.value.borrow(), value) == Ordering::Less {
+                skipped += tree_size(&current_node.left) + 1;
+                current_tree = &current_node.right;
+            } else {
+                front_nodes.push(current_node.as_ref());
+                current_tree = &current_node.left;
+            }
+        }
+
+        let mut back_nodes = Vec::new();
+        push_right_edge(&mut back_nodes, self.root.as_deref());
+
+        let traverser = Traverser { front: front_nodes, back: back_nodes };
+        AvlTreeSetIter { traverser, remaining: self.len - skipped }
+    }
+
+    /// Returns an iterator over the values within `range`, in ascending
+    /// order. Subtrees outside the bounds are never visited.
+    pub fn range<Q, R>(&self, range: R) -> AvlTreeSetRange<'_, T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        R: RangeBounds<Q>,
+    {
+        let mut front_nodes = Vec::new();
+        let mut current_tree = &self.root;
+
+        while let Some(current_node) = current_tree {
+            if above_start(current_node.value.borrow(), range.start_bound(), &self.compare) {
+                front_nodes.push(current_node.as_ref());
+                current_tree = &current_node.left;
+            } else {
+                current_tree = &current_node.right;
+            }
+        }
+
+        let mut back_nodes = Vec::new();
+        let mut current_tree = &self.root;
+
+        while let Some(current_node) = current_tree {
+            if below_end(current_node.value.borrow(), range.end_bound(), &self.compare) {
+                back_nodes.push(current_node.as_ref());
+                current_tree = &current_node.right;
+            } else {
+                current_tree = &current_node.left;
+            }
+        }
+
+        // The walks meet in the middle, so the iterator only needs to detect
+        // ranges that are empty from the start.
+        let mut traverser = Traverser { front: front_nodes, back: back_nodes };
+
+        if let (Some(front), Some(back)) = (traverser.front.last(), traverser.back.last()) {
+            if self.compare.compare(&front.value, &back.value) == Ordering::Greater {
+                traverser.finish();
+            }
+        }
+
+        AvlTreeSetRange { traverser }
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T>> AvlTreeSet<T, C> {
+    /// Values in `self` or `other`, in ascending order.
+    pub fn union(&'a self, other: &'a Self) -> AvlTreeSetUnion<'a, T, C> {
+        AvlTreeSetUnion {
+            left: self.iter().peekable(),
+            right: other.iter().peekable(),
+            compare: &self.compare,
+        }
+    }
+
+    /// Values in both `self` and `other`, in ascending order.
+    pub fn intersection(&'a self, other: &'a Self) -> AvlTreeSetIntersection<'a, T, C> {
+        AvlTreeSetIntersection {
+            left: self.iter().peekable(),
+            right: other.iter().peekable(),
+            compare: &self.compare,
+        }
+    }
+
+    /// Values in `self` but not in `other`, in ascending order.
+    pub fn difference(&'a self, other: &'a Self) -> AvlTreeSetDifference<'a, T, C> {
+        AvlTreeSetDifference {
+            left: self.iter().peekable(),
+            right: other.iter().peekable(),
+            compare: &self.compare,
+        }
+    }
+
+    /// Values in exactly one of `self` and `other`, in ascending order.
+    pub fn symmetric_difference(&'a self, other: &'a Self) -> AvlTreeSetSymmetricDifference<'a, T, C> {
+        AvlTreeSetSymmetricDifference {
+            left: self.iter().peekable(),
+            right: other.iter().peekable(),
+            compare: &self.compare,
+        }
+    }
+
+    /// Splits the symmetric difference by which set each value came from,
+    /// in a single in-order walk over both sets in O(n + m).
+    pub fn symmetric_diff_report(&'a self, other: &'a Self) -> AvlTreeSetDiff<'a, T> {
+        let mut diff = AvlTreeSetDiff { only_in_self: Vec::new(), only_in_other: Vec::new() };
+        let mut left = self.iter().peekable();
+        let mut right = other.iter().peekable();
+
+        loop {
+            let ordering = match (left.peek(), right.peek()) {
+                (Some(l), Some(r)) => self.compare.compare(l, r),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+
+            match ordering {
+                Ordering::Less => diff.only_in_self.extend(left.next()),
+                Ordering::Equal => {
+                    left.next();
+                    right.next();
+                }
+                Ordering::Greater => diff.only_in_other.extend(right.next()),
+            }
+        }
+
+        diff
+    }
+
+    /// Whether `self` and `other` share no values. Walks both sets in order
+    /// and stops at the first common value.
+    pub fn is_disjoint(&'a self, other: &'a Self) -> bool {
+        self.intersection(other).next().is_none()
+    }
+
+    /// Whether every value in `self` is also in `other`. Walks both sets in
+    /// order and stops at the first value missing from `other`.
+    pub fn is_subset(&'a self, other: &'a Self) -> bool {
+        self.len <= other.len && self.difference(other).next().is_none()
+    }
+
+    /// Whether every value in `other` is also in `self`.
+    pub fn is_superset(&'a self, other: &'a Self) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Whether the set holds exactly the values of `sorted`, which is in
+    /// strictly increasing order, without allocating.
+    pub fn matches_sorted_slice(&self, sorted: &[T]) -> bool {
+        self.len == sorted.len() && self.starts_with_sorted(sorted)
+    }
+
+    /// Whether the smallest `sorted.len()` values of the set are exactly
+    /// those of `sorted`, which is in strictly increasing order.
+    pub fn starts_with_sorted(&self, sorted: &[T]) -> bool {
+        sorted.len() <= self.len
+            && self.iter().zip(sorted).all(|(value, expected)| self.compare.compare(value, expected) == Ordering::Equal)
+    }
+
+    /// Whether every value of `sorted`, which is in ascending order, is in
+    /// the set. Walks the set and the slice together once, in O(n + m).
+    pub fn contains_all_sorted(&self, sorted: &[T]) -> bool {
+        let mut values = self.iter().peekable();
+
+        sorted.iter().all(|expected| {
+            while values.next_if(|value| self.compare.compare(value, expected) == Ordering::Less).is_some() {}
+            values.peek().is_some_and(|value| self.compare.compare(value, expected) == Ordering::Equal)
+        })
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T>> Iterator for AvlTreeSetUnion<'a, T, C> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match (self.left.peek(), self.right.peek()) {
+            (Some(left), Some(right)) => match self.compare.compare(left, right) {
+                Ordering::Less => self.left.next(),
+                Ordering::Equal => {
+                    self.right.next();
+                    self.left.next()
+                }
+                Ordering::Greater => self.right.next(),
+            },
+            (Some(_), None) => self.left.next(),
+            (None, _) => self.right.next(),
+        }
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T>> Iterator for AvlTreeSetIntersection<'a, T, C> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.compare.compare(self.left.peek()?, self.right.peek()?) {
+                Ordering::Less => { self.left.next(); }
+                Ordering::Equal => {
+                    self.right.next();
+                    return self.left.next();
+                }
+                Ordering::Greater => { self.right.next(); }
+            }
+        }
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T>> Iterator for AvlTreeSetDifference<'a, T, C> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let compare = self.compare;
+            let left = self.left.peek()?;
+
+            match self.right.peek().map(|right| compare.compare(left, right)) {
+                Some(Ordering::Less) | None => return self.left.next(),
+                Some(Ordering::Equal) => {
+                    self.left.next();
+                    self.right.next();
+                }
+                Some(Ordering::Greater) => { self.right.next(); }
+            }
+        }
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T>> Iterator for AvlTreeSetSymmetricDifference<'a, T, C> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match (self.left.peek(), self.right.peek()) {
+                (Some(left), Some(right)) => match self.compare.compare(left, right) {
+                    Ordering::Less => return self.left.next(),
+                    Ordering::Equal => {
+                        self.left.next();
+                        self.right.next();
+                    }
+                    Ordering::Greater => return self.right.next(),
+                },
+                (Some(_), None) => return self.left.next(),
+                (None, _) => return self.right.next(),
+            }
+        }
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T>> FusedIterator for AvlTreeSetUnion<'a, T, C> {}
+
+impl<'a, T: 'a, C: Compare<T>> FusedIterator for AvlTreeSetIntersection<'a, T, C> {}
+
+impl<'a, T: 'a, C: Compare<T>> FusedIterator for AvlTreeSetDifference<'a, T, C> {}
+
+impl<'a, T: 'a, C: Compare<T>> FusedIterator for AvlTreeSetSymmetricDifference<'a, T, C> {}
+
+impl<T: Clone, C: Compare<T> + Clone> BitOr<&AvlTreeSet<T, C>> for &AvlTreeSet<T, C> {
+    type Output = AvlTreeSet<T, C>;
+
+    fn bitor(self, other: &AvlTreeSet<T, C>) -> AvlTreeSet<T, C> {
+        AvlTreeSet::from_sorted_iter_with(self.union(other).cloned(), self.comparator().clone())
+    }
+}
+
+impl<T: Clone, C: Compare<T> + Clone> BitAnd<&AvlTreeSet<T, C>> for &AvlTreeSet<T, C> {
+    type Output = AvlTreeSet<T, C>;
+
+    fn bitand(self, other: &AvlTreeSet<T, C>) -> AvlTreeSet<T, C> {
+        AvlTreeSet::from_sorted_iter_with(self.intersection(other).cloned(), self.comparator().clone())
+    }
+}
+
+impl<T: Clone, C: Compare<T> + Clone> Sub<&AvlTreeSet<T, C>> for &AvlTreeSet<T, C> {
+    type Output = AvlTreeSet<T, C>;
+
+    fn sub(self, other: &AvlTreeSet<T, C>) -> AvlTreeSet<T, C> {
+        AvlTreeSet::from_sorted_iter_with(self.difference(other).cloned(), self.comparator().clone())
+    }
+}
+
+impl<T: Clone, C: Compare<T> + Clone> BitXor<&AvlTreeSet<T, C>> for &AvlTreeSet<T, C> {
+    type Output = AvlTreeSet<T, C>;
+
+    fn bitxor(self, other: &AvlTreeSet<T, C>) -> AvlTreeSet<T, C> {
+        AvlTreeSet::from_sorted_iter_with(self.symmetric_difference(other).cloned(), self.comparator().clone())
+    }
+}
+
+impl<'a, T: 'a> Iterator for AvlTreeSetRange<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.traverser.next_front().map(|node| &node.value)
+    }
+}
+
+impl<'a, T: 'a> DoubleEndedIterator for AvlTreeSetRange<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.traverser.next_back().map(|node| &node.value)
+    }
+}
+
+impl<'a, T: 'a> FusedIterator for AvlTreeSetRange<'a, T> {}
+
+impl<T, C: Compare<T>> AvlTreeSet<T, C> {
+    /// Returns a cursor positioned in the gap before the first value above
+    /// `bound`.
+    pub fn lower_bound<Q>(&self, bound: Bound<&Q>) -> AvlTreeSetCursor<'_, T, C>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let compare = &self.compare;
+        let traverser = Traverser::around(&self.root, |value| !above_start(value.borrow(), bound, compare));
+
+        AvlTreeSetCursor { traverser, compare: PhantomData }
+    }
+
+    /// Returns a cursor positioned in the gap after the last value below
+    /// `bound`.
+    pub fn upper_bound<Q>(&self, bound: Bound<&Q>) -> AvlTreeSetCursor<'_, T, C>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let compare = &self.compare;
+        let traverser = Traverser::around(&self.root, |value| below_end(value.borrow(), bound, compare));
+
+        AvlTreeSetCursor { traverser, compare: PhantomData }
+    }
+
+    /// Like `lower_bound`, but the returned cursor can insert and remove
+    /// values around its position. The set is split at the cursor and joined
+    /// back together when the cursor is dropped.
+    pub fn lower_bound_mut<Q>(&mut self, bound: Bound<&Q>) -> AvlTreeSetCursorMut<'_, T, C>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let root = self.root.take();
+        let compare = &self.compare;
+        let metrics = &mut self.metrics;
+
+        let (before, after) = match bound {
+            Bound::Included(key) => {
+                let (left, mid, right) = split(root, key, compare, metrics);
+                (left, prepend_value(mid, right, metrics))
+            }
+            Bound::Excluded(key) => {
+                let (left, mid, right) = split(root, key, compare, metrics);
+                (append_value(left, mid, metrics), right)
+            }
+            Bound::Unbounded => (None, root),
+        };
+
+        self.generation.bump();
+        AvlTreeSetCursorMut { set: self, fingers: Traverser::around_gap(before, after) }
+    }
+
+    /// Like `upper_bound`, but the returned cursor can insert and remove
+    /// values around its position.
+    pub fn upper_bound_mut<Q>(&mut self, bound: Bound<&Q>) -> AvlTreeSetCursorMut<'_, T, C>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let root = self.root.take();
+        let compare = &self.compare;
+        let metrics = &mut self.metrics;
+
+        let (before, after) = match bound {
+            Bound::Included(key) => {
+                let (left, mid, right) = split(root, key, compare, metrics);
+                (append_value(left, mid, metrics), right)
+            }
+            Bound::Excluded(key) => {
+                let (left, mid, right) = split(root, key, compare, metrics);
+                (left, prepend_value(mid, right, metrics))
+            }
+            Bound::Unbounded => (root, None),
+        };
+
+        self.generation.bump();
+        AvlTreeSetCursorMut { set: self, fingers: Traverser::around_gap(before, after) }
+    }
+
+    /// Inserts the values of `iter` into `set`, keeping those already there,
+    /// and returns it: a stable stand-in for `Iterator::collect_into`, as
+    /// `AvlTreeSet::collect_into(batch, &mut set)`. With a node pool, a set
+    /// that is filled this way and cleared batch after batch gives each new
+    /// value a node an earlier batch left behind.
+    pub fn collect_into<I: IntoIterator<Item = T>>(iter: I, set: &mut Self) -> &mut Self {
+        set.extend(iter);
+        set
+    }
+
+    /// Replaces the set's values with those of `iter`, giving each new value
+    /// a node the set already allocated while any are left. A loop that
+    /// fills the same set batch after batch this way allocates only when a
+    /// batch holds more values than the one before it.
+    pub fn refill<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut spare = Vec::with_capacity(self.len);
+        let mut trees = alloc::vec![self.root.take()];
+
+        while let Some(tree) = trees.pop() {
+            if let Some(mut node) = tree {
+                trees.push(node.left.take());
+                trees.push(node.right.take());
+                spare.push(node);
+            }
+        }
+        self.len = 0;
+
+        for value in iter {
+            let mut node = match spare.pop() {
+                Some(node) => node,
+                None => {
+                    self.insert_value(value);
+                    continue;
+                }
+            };
+
+            node.value = value;
+            node.height = 1;
+            node.size = 1;
+
+            match insert_node(&mut self.root, node, &self.compare, &mut self.metrics) {
+                Ok(()) => self.len += 1,
+                Err(node) => spare.push(node),
+            }
+        }
+
+        for node in spare {
+            self.pool.recycle(node);
+        }
+
+        self.metrics.record_height(&self.root);
+        self.changed("refill");
+    }
+
+    /// Removes every value, keeping the nodes for later inserts if the set
+    /// has a node pool.
+    pub fn clear(&mut self) {
+        self.pool.recycle_tree(self.root.take());
+        self.len = 0;
+        self.changed("clear");
+    }
+
+    /// Empties the set, yielding its values in ascending order.
+    pub fn drain(&mut self) -> AvlTreeSetIntoIter<T> {
+        let drained = AvlTreeSetIntoIter::new(self.root.take(), mem::take(&mut self.len));
+
+        self.changed("drain");
+        drained
+    }
+
+    /// Lazily removes and yields the values for which `pred` returns `true`,
+    /// in ascending order. Values not yet visited when the iterator is
+    /// dropped stay in the set, which is rebuilt balanced at that point.
+    pub fn drain_filter<F>(&mut self, pred: F) -> AvlTreeSetDrainFilter<'_, T, F, C>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let remaining = self.drain();
+
+        AvlTreeSetDrainFilter {
+            set: self,
+            remaining,
+            kept: Vec::new(),
+            pred,
+        }
+    }
+
+    /// Removes and yields the values smallest first, one `pop_first` per
+    /// step. Stopping early leaves the values not yet yielded in the set,
+    /// which stays balanced throughout.
+    pub fn drain_sorted(&mut self) -> AvlTreeSetDrainSorted<'_, T, C> {
+        AvlTreeSetDrainSorted { set: self }
+    }
+
+    /// Like `drain_sorted`, yielding the largest values first.
+    pub fn drain_sorted_rev(&mut self) -> AvlTreeSetDrainSortedRev<'_, T, C> {
+        AvlTreeSetDrainSortedRev { set: self }
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T>> AvlTreeSetCursor<'a, T, C> {
+    /// Moves past the next value and returns it. The cursor keeps the
+    /// ancestors of its gap, so a step takes amortized O(1) rather than a
+    /// descent from the root.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<&'a T> {
+        self.traverser.advance().map(|node| &node.value)
+    }
+
+    /// Moves back past the previous value and returns it.
+    pub fn prev(&mut self) -> Option<&'a T> {
+        self.traverser.retreat().map(|node| &node.value)
+    }
+
+    pub fn peek_next(&self) -> Option<&'a T> {
+        self.traverser.front.last().map(|node| &node.value)
+    }
+
+    pub fn peek_prev(&self) -> Option<&'a T> {
+        self.traverser.back.last().map(|node| &node.value)
+    }
+}
+
+impl<'a, T: 'a, C> Clone for AvlTreeSetCursor<'a, T, C> {
+    fn clone(&self) -> Self {
+        AvlTreeSetCursor { traverser: self.traverser.clone(), compare: PhantomData }
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T>> AvlTreeSetCursorMut<'a, T, C> {
+    /// Moves past the next value and returns it. The cursor keeps the edges
+    /// of the set that face its gap, so this moves one node from one to the
+    /// other, in amortized O(1).
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<&T> {
+        let next = self.fingers.step_front()?;
+        self.fingers.push_back_leaf(next, &mut self.set.metrics);
+
+        self.verify("AvlTreeSetCursorMut::next");
+        self.peek_prev()
+    }
+
+    /// Moves back past the previous value and returns it.
+    pub fn prev(&mut self) -> Option<&T> {
+        let prev = self.fingers.step_back()?;
+        self.fingers.push_front_leaf(prev, &mut self.set.metrics);
+
+        self.verify("AvlTreeSetCursorMut::prev");
+        self.peek_next()
+    }
+
+    pub fn peek_next(&self) -> Option<&T> {
+        self.fingers.front.last().map(|node| &node.value)
+    }
+
+    pub fn peek_prev(&self) -> Option<&T> {
+        self.fingers.back.last().map(|node| &node.value)
+    }
+
+    /// Inserts `value` into the gap so that it becomes the next value. The
+    /// value is handed back if it doesn't belong between the neighboring
+    /// values.
+    pub fn insert_after(&mut self, value: T) -> Result<(), T> {
+        if !self.fits(&value) {
+            return Err(value);
+        }
+
+        let node = self.set.pool.leaf(value);
+        self.fingers.push_front_leaf(node, &mut self.set.metrics);
+        self.set.len += 1;
+
+        self.verify("AvlTreeSetCursorMut::insert_after");
+        Ok(())
+    }
+
+    /// Inserts `value` into the gap so that it becomes the previous value.
+    pub fn insert_before(&mut self, value: T) -> Result<(), T> {
+        if !self.fits(&value) {
+            return Err(value);
+        }
+
+        let node = self.set.pool.leaf(value);
+        self.fingers.push_back_leaf(node, &mut self.set.metrics);
+        self.set.len += 1;
+
+        self.verify("AvlTreeSetCursorMut::insert_before");
+        Ok(())
+    }
+
+    /// Inserts `value` wherever it belongs and leaves the cursor just past
+    /// it, returning whether it was new. With the cursor as the hint, a
+    /// value that fits the gap is compared only with its two neighbors and
+    /// stacked next to the gap, so a mostly ordered stream, e.g. of
+    /// timestamps fed through a cursor from `upper_bound_mut(Bound::Unbounded)`,
+    /// inserts in amortized O(1). A value that belongs a few places away
+    /// walks the cursor there one value at a time. One that is farther off
+    /// than the tree is tall costs O(log n), as the set is joined back
+    /// together and split at the value.
+    pub fn insert_near(&mut self, value: T) -> bool {
+        let mut steps = (usize::BITS - self.set.len.leading_zeros()) as usize;
+
+        let inserted = loop {
+            let compare = &self.set.compare;
+            let prev = self.fingers.back.last().map(|prev| compare.compare(&prev.value, &value));
+            let next = self.fingers.front.last().map(|next| compare.compare(&value, &next.value));
+
+            match (prev, next) {
+                (Some(Ordering::Equal), _) => break false,
+                (_, Some(Ordering::Equal)) => {
+                    let next = self.fingers.step_front().unwrap();
+                    self.fingers.push_back_leaf(next, &mut self.set.metrics);
+                    break false;
+                }
+                (Some(Ordering::Greater), _) | (_, Some(Ordering::Greater)) if steps == 0 => {
+                    break self.split_at(value);
+                }
+                (Some(Ordering::Greater), _) => {
+                    let prev = self.fingers.step_back().unwrap();
+                    self.fingers.push_front_leaf(prev, &mut self.set.metrics);
+                    steps -= 1;
+                }
+                (_, Some(Ordering::Greater)) => {
+                    let next = self.fingers.step_front().unwrap();
+                    self.fingers.push_back_leaf(next, &mut self.set.metrics);
+                    steps -= 1;
+                }
+                _ => {
+                    let node = self.set.pool.leaf(value);
+                    self.fingers.push_back_leaf(node, &mut self.set.metrics);
+                    self.set.len += 1;
+                    break true;
+                }
+            }
+        };
+
+        self.verify("AvlTreeSetCursorMut::insert_near");
+        inserted
+    }
+
+    /// Moves the gap to just past where `value` belongs by joining the set
+    /// back together and splitting it there, inserting `value` unless an
+    /// equal value is present.
+    fn split_at(&mut self, value: T) -> bool {
+        let set = &mut *self.set;
+        let before = self.fingers.take_before(&mut set.metrics);
+        let after = self.fingers.take_after(&mut set.metrics);
+        let root = concat(before, after, &mut set.metrics);
+        let (left, mid, right) = split(root, &value, &set.compare, &mut set.metrics);
+        let inserted = mid.is_none();
+
+        self.fingers = Traverser::around_gap(append_value(left, mid, &mut set.metrics), right);
+        if inserted {
+            self.fingers.push_back_leaf(set.pool.leaf(value), &mut set.metrics);
+            set.len += 1;
+        }
+
+        inserted
+    }
+
+    pub fn remove_next(&mut self) -> Option<T> {
+        let next = self.fingers.step_front()?;
+        self.set.len -= 1;
+
+        self.verify("AvlTreeSetCursorMut::remove_next");
+        Some(link::into_node(next).value)
+    }
+
+    pub fn remove_prev(&mut self) -> Option<T> {
+        let prev = self.fingers.step_back()?;
+        self.set.len -= 1;
+
+        self.verify("AvlTreeSetCursorMut::remove_prev");
+        Some(link::into_node(prev).value)
+    }
+
+    fn fits(&self, value: &T) -> bool {
+        let compare = &self.set.compare;
+
+        self.peek_prev().is_none_or(|prev| compare.compare(prev, value) == Ordering::Less)
+            && self.peek_next().is_none_or(|next| compare.compare(value, next) == Ordering::Less)
+    }
+
+    /// With the `paranoid` feature, checks the trees on either side of the
+    /// gap after `operation`, joining them for the check and stacking their
+    /// edges again afterwards.
+    #[cfg(feature = "paranoid")]
+    fn verify(&mut self, operation: &str) {
+        let mut metrics = Metrics::default();
+        let before = self.fingers.take_before(&mut metrics);
+        let after = self.fingers.take_after(&mut metrics);
+
+        self.set.verify_around(&before, &after, operation);
+        self.fingers = Traverser::around_gap(before, after);
+    }
+
+    #[cfg(not(feature = "paranoid"))]
+    #[inline(always)]
+    fn verify(&mut self, _operation: &str) {}
+}
+
+impl<'a, T, C: Compare<T>> Drop for AvlTreeSetCursorMut<'a, T, C> {
+    fn drop(&mut self) {
+        let metrics = &mut self.set.metrics;
+        let before = self.fingers.take_before(metrics);
+        let after = self.fingers.take_after(metrics);
+
+        self.set.root = concat(before, after, metrics);
+        self.set.changed("dropping a cursor");
+    }
+}
+
+impl<'a, T, F: FnMut(&T) -> bool, C: Compare<T>> Iterator for AvlTreeSetDrainFilter<'a, T, F, C> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for value in &mut self.remaining {
+            if (self.pred)(&value) {
+                return Some(value);
+            }
+
+            self.kept.push(value);
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining.len()))
+    }
+}
+
+impl<'a, T, F: FnMut(&T) -> bool, C: Compare<T>> FusedIterator for AvlTreeSetDrainFilter<'a, T, F, C> {}
+
+impl<'a, T, F, C: Compare<T>> Drop for AvlTreeSetDrainFilter<'a, T, F, C> {
+    fn drop(&mut self) {
+        let mut kept = mem::take(&mut self.kept);
+        kept.extend(&mut self.remaining);
+
+        self.set.rebuild_sorted(kept);
+        self.set.changed("drain_filter");
+    }
+}
+
+impl<'a, T, C: Compare<T>> Iterator for AvlTreeSetDrainSorted<'a, T, C> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.set.pop_first()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.set.len, Some(self.set.len))
+    }
+}
+
+impl<'a, T, C: Compare<T>> DoubleEndedIterator for AvlTreeSetDrainSorted<'a, T, C> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.set.pop_last()
+    }
+}
+
+impl<'a, T, C: Compare<T>> ExactSizeIterator for AvlTreeSetDrainSorted<'a, T, C> {}
+
+impl<'a, T, C: Compare<T>> FusedIterator for AvlTreeSetDrainSorted<'a, T, C> {}
+
+impl<'a, T, C: Compare<T>> Iterator for AvlTreeSetDrainSortedRev<'a, T, C> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.set.pop_last()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.set.len, Some(self.set.len))
+    }
+}
+
+impl<'a, T, C: Compare<T>> DoubleEndedIterator for AvlTreeSetDrainSortedRev<'a, T, C> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.set.pop_first()
+    }
+}
+
+impl<'a, T, C: Compare<T>> ExactSizeIterator for AvlTreeSetDrainSortedRev<'a, T, C> {}
+
+impl<'a, T, C: Compare<T>> FusedIterator for AvlTreeSetDrainSortedRev<'a, T, C> {}
+
+impl<T> AvlTreeSetIntoIter<T> {
+    fn new(root: AvlTree<T>, len: usize) -> Self {
+        AvlTreeSetIntoIter { traverser: Traverser::front_only(root), remaining: len }
+    }
+}
+
+impl<T> Iterator for AvlTreeSetIntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current_node = self.traverser.step_front()?;
+        self.remaining -= 1;
+
+        Some(link::into_node(current_node).value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> Drop for AvlTreeSetIntoIter<T> {
+    fn drop(&mut self) {
+        drop_iteratively(mem::take(&mut self.traverser.front));
+    }
+}
+
+impl<T> ExactSizeIterator for AvlTreeSetIntoIter<T> {}
+
+impl<T> FusedIterator for AvlTreeSetIntoIter<T> {}
+
+impl<T, C> IntoIterator for AvlTreeSet<T, C> {
+    type Item = T;
+    type IntoIter = AvlTreeSetIntoIter<T>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        AvlTreeSetIntoIter::new(self.root.take(), self.len)
+    }
+}
+
+impl<'a, T: 'a, C> IntoIterator for &'a AvlTreeSet<T, C> {
+    type Item = &'a T;
+    type IntoIter = AvlTreeSetIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Ord> FromIterator<T> for AvlTreeSet<T> {
+    /// Sorts and deduplicates the values unless they already arrive
+    /// strictly increasing, then builds the tree directly without any
+    /// rotations. The first of several equal values is the one kept.
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.rebuild_unsorted(iter.into_iter().collect());
+        set
+    }
+}
+
+impl<'a, T: 'a + Ord + Clone> FromIterator<&'a T> for AvlTreeSet<T> {
+    /// Clones the values into an owned set, as collecting `iter.cloned()`
+    /// would.
+    fn from_iter<I: IntoIterator<Item=&'a T>>(iter: I) -> Self {
+        iter.into_iter().cloned().collect()
+    }
+}
+
+impl<T, C: Compare<T>> Extend<T> for AvlTreeSet<T, C> {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, iter: I) {
+        for i in iter {
+            self.insert_value(i);
+        }
+
+        self.changed("extend");
+    }
+}
+
+impl<'a, T: 'a + Copy, C: Compare<T>> Extend<&'a T> for AvlTreeSet<T, C> {
+    fn extend<I: IntoIterator<Item=&'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().cloned());
+    }
+}
+
+/// Fails to compile if any public type stops being `Send` and `Sync` for
+/// `Send + Sync` values and comparators, e.g. by gaining a raw pointer or an
+/// `Rc`. Sets can then be shared behind an `Arc<RwLock<_>>` and iterated
+/// from several threads at once.
+#[allow(dead_code)]
+const _: () = {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    fn assert_all<T, V, C, F, A>()
+    where
+        T: Ord + Clone + Send + Sync,
+        V: Send + Sync,
+        C: Send + Sync,
+        F: Send + Sync,
+        A: Augment<T> + Send + Sync,
+    {
+        assert_send_sync::<AvlTreeSet<T, C>>();
+        assert_send_sync::<AvlTreeSetBy<T, F>>();
+        assert_send_sync::<AvlTreeSetBuilder<T, C>>();
+        assert_send_sync::<AvlTreeSetView<'_, T, C>>();
+        assert_send_sync::<RangeQuery<T>>();
+        assert_send_sync::<AvlTreeSetIter<'_, T>>();
+        assert_send_sync::<AvlTreeSetRankedIter<'_, T>>();
+        assert_send_sync::<AvlTreeSetRanges<'_, u32>>();
+        assert_send_sync::<AvlTreeSetTransaction<'_, u32, Natural>>();
+        assert_send_sync::<AvlTreeSetRange<'_, T>>();
+        assert_send_sync::<AvlTreeSetDiff<'_, T>>();
+        assert_send_sync::<prefix::AvlTreeSetPrefix<'_, T, T>>();
+        assert_send_sync::<AvlTreeSetUnion<'_, T, C>>();
+        assert_send_sync::<AvlTreeSetIntersection<'_, T, C>>();
+        assert_send_sync::<AvlTreeSetDifference<'_, T, C>>();
+        assert_send_sync::<AvlTreeSetSymmetricDifference<'_, T, C>>();
+        assert_send_sync::<AvlTreeSetIntoIter<T>>();
+        assert_send_sync::<AvlTreeSetCursor<'_, T, C>>();
+        assert_send_sync::<AvlTreeSetCursorMut<'_, T, Natural>>();
+        assert_send_sync::<AvlTreeSetDrainFilter<'_, T, F, Natural>>();
+        assert_send_sync::<AvlTreeSetDrainSorted<'_, T, C>>();
+        assert_send_sync::<AvlTreeSetDrainSortedRev<'_, T, C>>();
+        assert_send_sync::<heap::AvlTreeSetMinHeap<'_, T, C>>();
+        assert_send_sync::<heap::AvlTreeSetMaxHeap<'_, T, C>>();
+        assert_send_sync::<AvlTreeSetShape<'_, T, C>>();
+        assert_send_sync::<AvlTreeSetDisplay<'_, T, C>>();
+        assert_send_sync::<AvlTreeSetArena<T>>();
+        assert_send_sync::<BoundedAvlTreeSet<T, C>>();
+        assert_send_sync::<collections::BoundedTopSet<T>>();
+        assert_send_sync::<collections::Interner<T>>();
+        assert_send_sync::<arena::AvlTreeSetArenaIter<'_, T>>();
+        assert_send_sync::<AvlArraySet<T, 4>>();
+        assert_send_sync::<arena::AvlArraySetIter<'_, T, 4>>();
+        assert_send_sync::<AugmentedAvlTreeSet<T, A>>();
+        assert_send_sync::<augment::AugmentedAvlTreeSetIter<'_, T, A>>();
+        assert_send_sync::<ImmutableAvlTreeSet<T>>();
+        assert_send_sync::<immutable::ImmutableAvlTreeSetIter<'_, T>>();
+        assert_send_sync::<IntervalTreeSet<T>>();
+        assert_send_sync::<interval::IntervalTreeSetIter<'_, T>>();
+        assert_send_sync::<interval::IntervalTreeSetOverlapping<'_, T>>();
+        assert_send_sync::<AvlTreeMap<T, V>>();
+        assert_send_sync::<map::AvlTreeMapIter<'_, T, V>>();
+        assert_send_sync::<map::AvlTreeMapKeys<'_, T, V>>();
+        assert_send_sync::<map::AvlTreeMapValues<'_, T, V>>();
+        assert_send_sync::<map::Entry<'_, T, V>>();
+        assert_send_sync::<AvlTreeMultiSet<T>>();
+        assert_send_sync::<multiset::AvlTreeMultiSetIter<'_, T>>();
+        assert_send_sync::<multiset::AvlTreeMultiSetCounts<'_, T>>();
+        assert_send_sync::<SmallAvlTreeSet<T>>();
+        assert_send_sync::<small::SmallAvlTreeSetIter<'_, T>>();
+        assert_send_sync::<FrozenAvlSet<T>>();
+        assert_send_sync::<frozen::FrozenAvlSetIter<'_, T>>();
+        assert_send_sync::<frozen::FrozenAvlSetRange<'_, T>>();
+    }
+};
+
+#[cfg(test)]
+mod properties {
+    use super::*;
+    use crate::tests::is_balanced;
+    use itertools::equal;
+    use quickcheck::{Arbitrary, Gen};
+    use std::cmp::Reverse;
+    use std::collections::BTreeSet;
+    use std::collections::hash_map::DefaultHasher;
+    use std::ops::Bound::{self, Excluded, Unbounded};
+
+    /// One step of a random mutation sequence, applied to a set and to a
+    /// `BTreeSet` model side by side.
+    #[derive(Debug, Clone)]
+    enum Op {
+        Insert(u8),
+        Remove(u8),
+        Contains(u8),
+        Range(u8, u8),
+        PopFirst,
+        PopLast,
+        SplitOffAndAppend(u8),
+    }
+
+    impl Arbitrary for Op {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            let (a, b) = (u8::arbitrary(g), u8::arbitrary(g));
+
+            // Weighted towards inserts and removals so sets grow and shrink.
+            match u8::arbitrary(g) % 10 {
+                0..=3 => Op::Insert(a),
+                4..=5 => Op::Remove(a),
+                6 => Op::Contains(a),
+                7 => Op::Range(cmp::min(a, b), cmp::max(a, b)),
+                8 if a % 2 == 0 => Op::PopFirst,
+                8 => Op::PopLast,
+                _ => Op::SplitOffAndAppend(a),
+            }
+        }
+    }
+
+    /// Checks every result, the iteration order and the tree's invariants
+    /// after each step. Set `QUICKCHECK_TESTS` and `QUICKCHECK_GENERATOR_SIZE`
+    /// to run longer and wider sequences.
+    #[quickcheck]
+    fn mutation_sequence_parity(ops: Vec<Op>) -> bool {
+        let mut avl_set = AvlTreeSet::new();
+        let mut btree_set = BTreeSet::new();
+
+        ops.into_iter().all(|op| {
+            let agrees = match op {
+                Op::Insert(x) => avl_set.insert(x) == btree_set.insert(x),
+                Op::Remove(x) => avl_set.remove(&x) == btree_set.remove(&x),
+                Op::Contains(x) => avl_set.contains(&x) == btree_set.contains(&x),
+                Op::Range(lo, hi) => equal(avl_set.range(lo..hi), btree_set.range(lo..hi)),
+                Op::PopFirst => avl_set.pop_first() == btree_set.pop_first(),
+                Op::PopLast => avl_set.pop_last() == btree_set.pop_last(),
+                Op::SplitOffAndAppend(x) => {
+                    let mut avl_tail = avl_set.split_off(&x);
+                    let btree_tail = btree_set.split_off(&x);
+                    let tails_agree = equal(avl_tail.iter(), btree_tail.iter())
+                        && avl_tail.check_invariants().is_ok();
+
+                    avl_set.append(&mut avl_tail);
+                    btree_set.extend(btree_tail);
+                    tails_agree
+                }
+            };
+
+            agrees
+                && avl_set.len() == btree_set.len()
+                && equal(avl_set.iter(), btree_set.iter())
+                && avl_set.check_invariants().is_ok()
+        })
+    }
+
+    fn hash_of<T: Hash>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[quickcheck]
+    fn iterator_parity(input: Vec<usize>) -> bool {
+        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        equal(avl_set.iter(), btree_set.iter())
+    }
+
+    #[quickcheck]
+    fn insert_parity(mut bt: BTreeSet<u8>, x: u8) -> bool {
+        let mut avl_set = bt.iter().cloned().collect::<AvlTreeSet<_>>();
+
+        avl_set.insert(x) == bt.insert(x)
+    }
+
+    #[quickcheck]
+    fn insert_balanced(input: Vec<usize>) -> bool {
+        let mut avl_set = AvlTreeSet::new();
+
+        input.into_iter().all(|x| {
+            avl_set.insert(x);
+            is_balanced(&avl_set.root)
+        })
+    }
+
+    #[quickcheck]
+    fn remove_balanced(input: Vec<u8>, removals: Vec<u8>) -> bool {
+        let mut avl_set = input.into_iter().collect::<AvlTreeSet<_>>();
+
+        removals.iter().all(|x| {
+            avl_set.remove(x);
+            is_balanced(&avl_set.root)
+        })
+    }
+
+    #[quickcheck]
+    fn rev_iterator_parity(input: Vec<usize>) -> bool {
+        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        equal(avl_set.iter().rev(), btree_set.iter().rev())
+    }
+
+    #[quickcheck]
+    fn alternating_iterator_parity(input: Vec<u8>, steps: Vec<bool>) -> bool {
+        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        let mut avl_iter = avl_set.iter();
+        let mut btree_iter = btree_set.iter();
+
+        steps.into_iter().all(|forward| {
+            if forward {
+                avl_iter.next() == btree_iter.next()
+            } else {
+                avl_iter.next_back() == btree_iter.next_back()
+            }
+        })
+    }
+
+    #[quickcheck]
+    fn into_iter_parity(input: Vec<usize>) -> bool {
+        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        equal(avl_set.into_iter(), btree_set.into_iter())
+    }
+
+    #[quickcheck]
+    fn any_in_range_parity(input: Vec<u8>, start: u8, end: u8) -> bool {
+        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+        let (start, end) = (cmp::min(start, end), cmp::max(start, end));
+
+        avl_set.any_in_range(start..end) == btree_set.range(start..end).next().is_some()
+            && avl_set.any_in_range(start..=end) == btree_set.range(start..=end).next().is_some()
+    }
+
+    #[quickcheck]
+    fn contains_parity(input: Vec<u8>, x: u8) -> bool {
+        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        avl_set.contains(&x) == btree_set.contains(&x) && avl_set.get(&x) == btree_set.get(&x)
+    }
+
+    #[quickcheck]
+    fn remove_parity(mut bt: BTreeSet<u8>, x: u8) -> bool {
+        let mut avl_set = bt.iter().cloned().collect::<AvlTreeSet<_>>();
+
+        avl_set.remove(&x) == bt.remove(&x) && equal(avl_set.iter(), bt.iter())
+    }
+
+    #[quickcheck]
+    fn remove_all_parity(input: Vec<u8>, removals: Vec<u8>) -> bool {
+        let mut avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let mut btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        removals.iter().all(|x| avl_set.remove(x) == btree_set.remove(x))
+            && equal(avl_set.iter(), btree_set.iter())
+    }
+
+    #[quickcheck]
+    fn len_parity(input: Vec<u8>, removals: Vec<u8>) -> bool {
+        let mut avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let mut btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        for x in removals {
+            avl_set.remove(&x);
+            btree_set.remove(&x);
+        }
+
+        avl_set.len() == btree_set.len() && avl_set.is_empty() == btree_set.is_empty()
+    }
+
+    #[quickcheck]
+    fn range_parity(input: Vec<u8>, start: u8, end: u8) -> bool {
+        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+        let (start, end) = (cmp::min(start, end), cmp::max(start, end));
+
+        equal(avl_set.range(start..end), btree_set.range(start..end))
+            && equal(avl_set.range(start..=end), btree_set.range(start..=end))
+            && equal(avl_set.range((Excluded(start), Unbounded)), btree_set.range((Excluded(start), Unbounded)))
+            && equal(avl_set.range(..=end).rev(), btree_set.range(..=end).rev())
+    }
+
+    #[quickcheck]
+    fn range_alternating_parity(input: Vec<u8>, start: u8, end: u8, steps: Vec<bool>) -> bool {
+        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+        let (start, end) = (cmp::min(start, end), cmp::max(start, end));
+
+        let mut avl_range = avl_set.range(start..=end);
+        let mut btree_range = btree_set.range(start..=end);
+
+        steps.into_iter().all(|forward| {
+            if forward {
+                avl_range.next() == btree_range.next()
+            } else {
+                avl_range.next_back() == btree_range.next_back()
+            }
+        })
+    }
+
+    #[quickcheck]
+    fn set_operation_parity(left: Vec<u8>, right: Vec<u8>) -> bool {
+        let avl_left = left.iter().cloned().collect::<AvlTreeSet<_>>();
+        let avl_right = right.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_left = left.iter().cloned().collect::<BTreeSet<_>>();
+        let btree_right = right.iter().cloned().collect::<BTreeSet<_>>();
+
+        equal(avl_left.union(&avl_right), btree_left.union(&btree_right))
+            && equal(avl_left.intersection(&avl_right), btree_left.intersection(&btree_right))
+            && equal(avl_left.difference(&avl_right), btree_left.difference(&btree_right))
+            && equal(
+                avl_left.symmetric_difference(&avl_right),
+                btree_left.symmetric_difference(&btree_right),
+            )
+    }
+
+    #[quickcheck]
+    fn symmetric_diff_report_parity(left: Vec<u8>, right: Vec<u8>) -> bool {
+        let avl_left = left.iter().cloned().collect::<AvlTreeSet<_>>();
+        let avl_right = right.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_left = left.iter().cloned().collect::<BTreeSet<_>>();
+        let btree_right = right.iter().cloned().collect::<BTreeSet<_>>();
+        let diff = avl_left.symmetric_diff_report(&avl_right);
+
+        equal(diff.only_in_self, btree_left.difference(&btree_right))
+            && equal(diff.only_in_other, btree_right.difference(&btree_left))
+    }
+
+    #[quickcheck]
+    fn set_operator_parity(left: Vec<u8>, right: Vec<u8>) -> bool {
+        let avl_left = left.iter().cloned().collect::<AvlTreeSet<_>>();
+        let avl_right = right.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_left = left.iter().cloned().collect::<BTreeSet<_>>();
+        let btree_right = right.iter().cloned().collect::<BTreeSet<_>>();
+
+        equal((&avl_left | &avl_right).iter(), (&btree_left | &btree_right).iter())
+            && equal((&avl_left & &avl_right).iter(), (&btree_left & &btree_right).iter())
+            && equal((&avl_left - &avl_right).iter(), (&btree_left - &btree_right).iter())
+            && equal((&avl_left ^ &avl_right).iter(), (&btree_left ^ &btree_right).iter())
+    }
+
+    #[quickcheck]
+    fn first_last_parity(input: Vec<u8>) -> bool {
+        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        avl_set.first() == btree_set.iter().next() && avl_set.last() == btree_set.iter().next_back()
+    }
+
+    #[quickcheck]
+    fn pop_parity(input: Vec<u8>, pops: Vec<bool>) -> bool {
+        let mut avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let mut btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        pops.into_iter().all(|first| {
+            let popped = if first {
+                avl_set.pop_first() == btree_set.pop_first()
+            } else {
+                avl_set.pop_last() == btree_set.pop_last()
+            };
+
+            popped && avl_set.len() == btree_set.len() && is_balanced(&avl_set.root)
+        })
+    }
+
+    #[quickcheck]
+    fn clone_parity(input: Vec<u8>) -> bool {
+        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let cloned = avl_set.clone();
+
+        cloned == avl_set && equal(cloned.iter(), avl_set.iter()) && is_balanced(&cloned.root)
+    }
+
+    #[quickcheck]
+    fn comparison_parity(left: Vec<u8>, right: Vec<u8>) -> bool {
+        let avl_left = left.iter().cloned().collect::<AvlTreeSet<_>>();
+        let avl_right = right.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_left = left.iter().cloned().collect::<BTreeSet<_>>();
+        let btree_right = right.iter().cloned().collect::<BTreeSet<_>>();
+
+        (avl_left == avl_right) == (btree_left == btree_right)
+            && avl_left.cmp(&avl_right) == btree_left.cmp(&btree_right)
+            && avl_left.partial_cmp(&avl_right) == btree_left.partial_cmp(&btree_right)
+    }
+
+    #[quickcheck]
+    fn equality_ignores_shape(input: Vec<u8>) -> bool {
+        let forward = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let backward = input.iter().rev().cloned().collect::<AvlTreeSet<_>>();
+
+        forward == backward && hash_of(&forward) == hash_of(&backward)
+    }
+
+    #[quickcheck]
+    fn extend_parity(left: Vec<u8>, right: Vec<u8>) -> bool {
+        let mut avl_set = left.iter().cloned().collect::<AvlTreeSet<_>>();
+        let mut btree_set = left.iter().cloned().collect::<BTreeSet<_>>();
+
+        avl_set.extend(right.iter());
+        btree_set.extend(right.iter());
+        avl_set.extend(right.iter().map(|x| x.wrapping_add(1)));
+        btree_set.extend(right.iter().map(|x| x.wrapping_add(1)));
+
+        avl_set.len() == btree_set.len() && equal(avl_set.iter(), btree_set.iter())
+    }
+
+    #[quickcheck]
+    fn refill_parity(batches: Vec<Vec<u8>>) -> bool {
+        let mut avl_set = AvlTreeSet::new();
+
+        batches.iter().all(|batch| {
+            avl_set.refill(batch.iter().cloned());
+            let btree_set = batch.iter().collect::<BTreeSet<_>>();
+            avl_set.len() == btree_set.len() && equal(avl_set.iter(), btree_set) && is_balanced(&avl_set.root)
+        })
+    }
+
+    #[quickcheck]
+    fn node_pool_parity(ops: Vec<(bool, u8)>) -> bool {
+        let mut avl_set = AvlTreeSet::new().with_node_pool();
+        let mut btree_set = BTreeSet::new();
+
+        ops.iter().all(|&(insert, value)| {
+            let agrees = if insert {
+                avl_set.insert(value) == btree_set.insert(value)
+            } else {
+                avl_set.remove(&value) == btree_set.remove(&value)
+            };
+            agrees && avl_set.len() == btree_set.len() && is_balanced(&avl_set.root)
+        }) && equal(avl_set.iter(), btree_set.iter()) && avl_set.check_invariants().is_ok()
+    }
+
+    #[quickcheck]
+    fn optimize_parity(input: Vec<u16>, removals: Vec<u16>) -> bool {
+        let mut avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>().with_node_pool();
+        let mut btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+        let mut removed = 0;
+        for value in &removals {
+            avl_set.remove(value);
+            removed += usize::from(btree_set.remove(value));
+        }
+
+        avl_set.optimize();
+        let pooled = avl_set.pooled_nodes();
+        avl_set.shrink_to_fit();
+        let min_height = (usize::BITS - avl_set.len().leading_zeros()) as usize;
+        avl_set.height() == min_height && avl_set.check_invariants().is_ok() && equal(avl_set.iter(), btree_set.iter())
+            && pooled == removed && avl_set.pooled_nodes() == 0
+    }
+
+    #[quickcheck]
+    fn insert_batch_parity(input: Vec<u16>, batches: Vec<Vec<u16>>) -> bool {
+        let mut avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let mut btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        batches.into_iter().all(|batch| {
+            let len = btree_set.len();
+            btree_set.extend(batch.iter().cloned());
+
+            avl_set.insert_batch(batch) == btree_set.len() - len && avl_set.check_invariants().is_ok()
+        }) && equal(avl_set.iter(), btree_set.iter())
+    }
+
+    #[quickcheck]
+    fn append_parity(left: Vec<u8>, right: Vec<u8>) -> bool {
+        let mut avl_left = left.iter().cloned().collect::<AvlTreeSet<_>>();
+        let mut avl_right = right.iter().cloned().collect::<AvlTreeSet<_>>();
+        let mut btree_left = left.iter().cloned().collect::<BTreeSet<_>>();
+        let mut btree_right = right.iter().cloned().collect::<BTreeSet<_>>();
+
+        avl_left.append(&mut avl_right);
+        btree_left.append(&mut btree_right);
+
+        avl_right.is_empty()
+            && avl_left.len() == btree_left.len()
+            && equal(avl_left.iter(), btree_left.iter())
+            && is_balanced(&avl_left.root)
+    }
+
+    #[quickcheck]
+    fn split_off_parity(input: Vec<u8>, x: u8) -> bool {
+        let mut avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let mut btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        let avl_other = avl_set.split_off(&x);
+        let btree_other = btree_set.split_off(&x);
+
+        avl_set.len() == btree_set.len()
+            && avl_other.len() == btree_other.len()
+            && equal(avl_set.iter(), btree_set.iter())
+            && equal(avl_other.iter(), btree_other.iter())
+            && is_balanced(&avl_set.root)
+            && is_balanced(&avl_other.root)
+    }
+
+    #[quickcheck]
+    fn retain_parity(input: Vec<u8>, divisor: u8) -> bool {
+        let divisor = cmp::max(divisor, 1);
+        let mut avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let mut btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        avl_set.retain(|x| x % divisor == 0);
+        btree_set.retain(|x| x % divisor == 0);
+
+        avl_set.len() == btree_set.len()
+            && equal(avl_set.iter(), btree_set.iter())
+            && is_balanced(&avl_set.root)
+    }
+
+    #[quickcheck]
+    fn drain_parity(input: Vec<u8>) -> bool {
+        let mut avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        equal(avl_set.drain(), btree_set.into_iter()) && avl_set.is_empty()
+    }
+
+    #[quickcheck]
+    fn drain_filter_parity(input: Vec<u8>, divisor: u8) -> bool {
+        let divisor = cmp::max(divisor, 1);
+        let mut avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let mut btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        let drained = avl_set.drain_filter(|x| x % divisor == 0).collect::<Vec<_>>();
+        let expected = btree_set.iter().cloned().filter(|x| x % divisor == 0).collect::<Vec<_>>();
+        btree_set.retain(|x| x % divisor != 0);
+
+        drained == expected
+            && avl_set.len() == btree_set.len()
+            && equal(avl_set.iter(), btree_set.iter())
+            && is_balanced(&avl_set.root)
+    }
+
+    #[quickcheck]
+    fn cursor_parity(input: Vec<u8>, x: u8, steps: Vec<bool>) -> bool {
+        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let values = input.iter().cloned().collect::<BTreeSet<_>>().into_iter().collect::<Vec<_>>();
+
+        let mut cursor = avl_set.lower_bound(Bound::Included(&x));
+        let mut gap = values.iter().position(|&v| v >= x).unwrap_or(values.len());
+
+        steps.into_iter().all(|forward| {
+            let expected = if forward {
+                let next = values.get(gap);
+                gap = cmp::min(gap + 1, values.len());
+                next
+            } else if gap > 0 {
+                gap -= 1;
+                values.get(gap)
+            } else {
+                None
+            };
+
+            let actual = if forward { cursor.next() } else { cursor.prev() };
+
+            actual == expected
+                && cursor.peek_next() == values.get(gap)
+                && cursor.peek_prev() == gap.checked_sub(1).and_then(|i| values.get(i))
+        })
+    }
+
+    #[quickcheck]
+    fn cursor_mut_parity(input: Vec<u8>, x: u8, removals: u8) -> bool {
+        let mut avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let mut btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        {
+            let mut cursor = avl_set.upper_bound_mut(Bound::Excluded(&x));
+
+            for _ in 0..removals % 4 {
+                if let Some(next) = cursor.remove_next() {
+                    btree_set.remove(&next);
+                }
+            }
+
+            if cursor.insert_before(x).is_ok() {
+                btree_set.insert(x);
+            }
+        }
+
+        avl_set.len() == btree_set.len()
+            && equal(avl_set.iter(), btree_set.iter())
+            && is_balanced(&avl_set.root)
+    }
+
+    #[quickcheck]
+    fn iter_with_rank_parity(input: Vec<u8>, from_back: Vec<bool>) -> bool {
+        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let mut iter = avl_set.iter_with_rank();
+
+        from_back.iter().all(|&back| {
+            let ranked = if back { iter.next_back() } else { iter.next() };
+            ranked.is_none_or(|(rank, value)| avl_set.get_index(rank) == Some(value))
+        }) && iter.len() == avl_set.len().saturating_sub(from_back.len())
+    }
+
+    #[quickcheck]
+    fn insert_near_parity(input: Vec<u8>, inserts: Vec<u8>) -> bool {
+        let mut avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let mut btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        let consistent = {
+            let mut cursor = avl_set.upper_bound_mut(Bound::Unbounded);
+
+            inserts.iter().all(|&x| {
+                cursor.insert_near(x) == btree_set.insert(x)
+                    && cursor.peek_prev() == Some(&x)
+                    && cursor.peek_next() == btree_set.range((Excluded(x), Unbounded)).next()
+            })
+        };
+
+        consistent
+            && avl_set.len() == btree_set.len()
+            && equal(avl_set.iter(), btree_set.iter())
+            && is_balanced(&avl_set.root)
+    }
+
+    #[quickcheck]
+    fn neighbors_parity(input: Vec<u8>, x: u8) -> bool {
+        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        let predecessor = btree_set.range(..x).next_back();
+        let successor = btree_set.range((Excluded(x), Unbounded)).next();
+
+        avl_set.neighbors(&x) == (predecessor, successor)
+    }
+
+    #[quickcheck]
+    fn sorted_vec_round_trip(input: Vec<u16>) -> bool {
+        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+        let sorted = input.into_iter().collect::<AvlTreeSet<_>>().into_sorted_vec();
+        let rebuilt = AvlTreeSet::from_sorted_vec(sorted.clone());
+
+        equal(sorted.iter(), btree_set.iter())
+            && equal(rebuilt.iter(), btree_set.iter())
+            && is_balanced(&rebuilt.root)
+    }
+
+    #[quickcheck]
+    fn iter_from_parity(input: Vec<u8>, x: u8, page: u8) -> bool {
+        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+        let page = page as usize % 8;
+
+        avl_set.iter_from(&x).len() == btree_set.range(x..).count()
+            && equal(avl_set.iter_from(&x).take(page), btree_set.range(x..).take(page))
+            && equal(avl_set.iter_from(&x).rev(), btree_set.range(x..).rev())
+    }
+
+    #[quickcheck]
+    fn smallest_largest_parity(input: Vec<u8>, k: u8) -> bool {
+        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+        let k = k as usize % 16;
+
+        equal(avl_set.smallest(k), btree_set.iter().take(k))
+            && equal(avl_set.largest(k), btree_set.iter().rev().take(k))
+    }
+
+    #[quickcheck]
+    fn remove_all_retain_all_parity(a: Vec<u8>, b: Vec<u8>) -> bool {
+        let mut removed = a.iter().cloned().collect::<AvlTreeSet<_>>();
+        let mut retained = removed.clone();
+        let other = b.iter().cloned().collect::<AvlTreeSet<_>>();
+        let (a, b) = (a.into_iter().collect::<BTreeSet<_>>(), b.into_iter().collect::<BTreeSet<_>>());
+
+        removed.remove_all(&other);
+        retained.retain_all(&other);
+
+        equal(removed.iter(), a.difference(&b))
+            && equal(retained.iter(), a.intersection(&b))
+            && removed.len() + retained.len() == a.len()
+            && is_balanced(&removed.root)
+            && is_balanced(&retained.root)
+    }
+
+    #[quickcheck]
+    fn floor_ceiling_parity(input: Vec<u8>, x: u8) -> bool {
+        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        avl_set.floor(&x) == btree_set.range(..=x).next_back()
+            && avl_set.ceiling(&x) == btree_set.range(x..).next()
+            && avl_set.floor_strict(&x) == btree_set.range(..x).next_back()
+            && avl_set.ceiling_strict(&x) == btree_set.range((Excluded(x), Unbounded)).next()
+    }
+
+    #[quickcheck]
+    fn insert_with_parity(mut bt: BTreeSet<u8>, x: u8) -> bool {
+        let mut avl_set = bt.iter().cloned().collect::<AvlTreeSet<_>>();
+        let present = bt.contains(&x);
+        let mut called = false;
+
+        let inserted = avl_set.insert_with(&x, || {
+            called = true;
+            x
+        });
+
+        inserted == bt.insert(x) && called != present
+    }
+
+    #[quickcheck]
+    fn sorted_slice_parity(input: Vec<u8>, other: Vec<u8>) -> bool {
+        let avl_set = input.iter().collect::<AvlTreeSet<_>>();
+        let values = avl_set.iter().cloned().collect::<Vec<_>>();
+        let mut other = other;
+        other.sort_unstable();
+        other.dedup();
+
+        avl_set.matches_sorted_slice(&values)
+            && avl_set.starts_with_sorted(&other) == values.starts_with(&other)
+            && avl_set.contains_all_sorted(&other) == other.iter().all(|x| avl_set.contains(x))
+    }
+
+    #[quickcheck]
+    fn split_join_round_trip(input: Vec<u8>, pivot: u8) -> bool {
+        let avl_set = input.into_iter().collect::<AvlTreeSet<_>>();
+        let expected = avl_set.clone();
+        let (less, mid, greater) = avl_set.split(&pivot);
+
+        let valid = less.iter().all(|&x| x < pivot)
+            && greater.iter().all(|&x| x > pivot)
+            && mid == expected.get(&pivot).cloned()
+            && less.check_invariants().is_ok()
+            && greater.check_invariants().is_ok();
+
+        let joined = match mid {
+            Some(mid) => AvlTreeSet::join(less, mid, greater),
+            None => {
+                let mut less = less;
+                less.extend(greater);
+                less
+            }
+        };
+
+        valid && joined == expected && joined.check_invariants().is_ok()
+    }
+
+    #[quickcheck]
+    fn partition_parity(input: Vec<u8>, modulus: u8) -> bool {
+        let modulus = modulus.max(1);
+        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+        let (matching, rest) = input.into_iter().collect::<AvlTreeSet<_>>().partition(|x| x % modulus == 0);
+
+        matching.check_invariants().is_ok()
+            && rest.check_invariants().is_ok()
+            && equal(matching.iter(), btree_set.iter().filter(|&x| x % modulus == 0))
+            && equal(rest.iter(), btree_set.iter().filter(|&x| x % modulus != 0))
+    }
+
+    #[quickcheck]
+    fn nearest_parity(input: Vec<u8>, value: u8, k: u8) -> bool {
+        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let sorted = input.iter().cloned().collect::<BTreeSet<_>>().into_iter().collect::<Vec<_>>();
+        let position = sorted.iter().take_while(|&&x| x < value).count();
+        let offset = if sorted.get(position) == Some(&value) { 0 } else { 1 };
+
+        let mut indices = (0..sorted.len()).collect::<Vec<_>>();
+        indices.sort_by_key(|&i| (if i < position { position - i } else { i - position + offset }, i));
+        indices.truncate(k as usize);
+        indices.sort();
+
+        equal(avl_set.nearest(&value, k as usize), indices.iter().map(|&i| &sorted[i]))
+    }
+
+    #[quickcheck]
+    fn count_range_parity(input: Vec<u8>, start: Bound<u8>, end: Bound<u8>) -> bool {
+        let avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let expected = input
+            .iter()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter(|&&x| (start, end).contains(&x))
+            .count();
+
+        avl_set.count_range((start, end)) == expected
+    }
+
+    #[quickcheck]
+    fn rank_select_parity(bt: BTreeSet<u8>, removed: Vec<u8>, x: u8) -> bool {
+        let mut avl_set = bt.iter().cloned().collect::<AvlTreeSet<_>>();
+        let mut bt = bt;
+
+        for value in removed {
+            avl_set.remove(&value);
+            bt.remove(&value);
+        }
+
+        avl_set.rank(&x) == bt.range(..x).count()
+            && avl_set.select(x as usize) == bt.iter().nth(x as usize)
+            && bt.iter().enumerate().all(|(i, value)| avl_set.rank(value) == i && avl_set.select(i) == Some(value))
+            && is_balanced(&avl_set.root)
+    }
+
+    #[quickcheck]
+    fn index_parity(bt: BTreeSet<u8>, x: u8) -> bool {
+        let avl_set = bt.iter().cloned().collect::<AvlTreeSet<_>>();
+        let position = bt.iter().position(|&value| value == x);
+
+        avl_set.get_index_of(&x) == position
+            && avl_set.get_index(x as usize) == bt.iter().nth(x as usize)
+            && bt.iter().enumerate().all(|(i, value)| &avl_set[i] == value)
+    }
+
+    #[quickcheck]
+    fn from_sorted_iter_parity(bt: BTreeSet<u8>) -> bool {
+        let avl_set = AvlTreeSet::from_sorted_iter(bt.iter().cloned());
+
+        avl_set.len() == bt.len() && equal(avl_set.iter(), bt.iter()) && is_balanced(&avl_set.root)
+    }
+
+    #[quickcheck]
+    fn relation_parity(a: BTreeSet<u8>, b: BTreeSet<u8>) -> bool {
+        let avl_a = a.iter().cloned().collect::<AvlTreeSet<_>>();
+        let avl_b = b.iter().cloned().collect::<AvlTreeSet<_>>();
+        let avl_c = a.union(&b).cloned().collect::<AvlTreeSet<_>>();
+
+        avl_a.is_disjoint(&avl_b) == a.is_disjoint(&b)
+            && avl_a.is_subset(&avl_b) == a.is_subset(&b)
+            && avl_a.is_superset(&avl_b) == a.is_superset(&b)
+            && avl_a.is_subset(&avl_c)
+            && avl_c.is_superset(&avl_b)
+    }
+
+    #[quickcheck]
+    fn transform_parity(bt: BTreeSet<u8>, divisor: u8) -> bool {
+        let divisor = divisor.max(1);
+        let mut avl_set = bt.iter().cloned().collect::<AvlTreeSet<_>>();
+        let mut mutated = avl_set.clone();
+        let expected = bt.iter().map(|x| x / divisor).collect::<BTreeSet<_>>();
+
+        avl_set.transform(|x| x / divisor);
+        mutated.for_each_mut(|x| *x = u8::MAX - *x / divisor);
+
+        equal(avl_set.iter(), expected.iter())
+            && avl_set.len() == expected.len()
+            && is_balanced(&avl_set.root)
+            && equal(mutated.iter().rev().map(|x| u8::MAX - x), expected.iter().cloned())
+            && mutated.len() == expected.len()
+            && is_balanced(&mutated.root)
+    }
+
+    #[quickcheck]
+    fn comparator_parity(input: Vec<u8>, removals: Vec<u8>, x: u8) -> bool {
+        let mut avl_set = AvlTreeSet::with_comparator(|a: &u8, b: &u8| b.cmp(a));
+        let mut bt = BTreeSet::new();
+
+        for value in input {
+            if avl_set.insert(value) != bt.insert(Reverse(value)) {
+                return false;
+            }
+        }
+
+        for value in removals {
+            if avl_set.remove(&value) != bt.remove(&Reverse(value)) {
+                return false;
+            }
+        }
+
+        equal(avl_set.iter(), bt.iter().map(|value| &value.0))
+            && equal(avl_set.range(..=x), bt.range(..=Reverse(x)).map(|value| &value.0))
+            && avl_set.rank(&x) == bt.range(..Reverse(x)).count()
+            && avl_set.check_invariants().is_ok()
+    }
+
+    #[quickcheck]
+    fn take_parity(mut bt: BTreeSet<u8>, xs: Vec<u8>) -> bool {
+        let mut avl_set = bt.iter().cloned().collect::<AvlTreeSet<_>>();
+
+        xs.iter().all(|x| avl_set.take(x) == bt.take(x) && is_balanced(&avl_set.root))
+            && equal(avl_set.iter(), bt.iter())
+    }
+
+    #[quickcheck]
+    fn get_or_insert_parity(mut bt: BTreeSet<u8>, xs: Vec<u8>) -> bool {
+        let mut avl_set = bt.iter().cloned().collect::<AvlTreeSet<_>>();
+
+        xs.into_iter().all(|x| {
+            bt.insert(x);
+            *avl_set.get_or_insert(x) == x && avl_set.len() == bt.len() && is_balanced(&avl_set.root)
+        }) && equal(avl_set.iter(), bt.iter())
+    }
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod metrics {
+    use super::*;
+
+    #[test]
+    fn rebalance_counts_rotations() {
+        let mut metrics = Metrics::default();
+        let mut tree = Some(Link::new(AvlNode {
+            value: 1,
+            left: None,
+            right: Some(Link::new(AvlNode {
+                value: 3,
+                left: Some(Link::new(AvlNode { value: 2, left: None, right: None, height: 1, size: 1, augment: () })),
+                right: None,
+                height: 2,
+                size: 2,
+                augment: (),
+            })),
+            height: 3,
+            size: 3,
+            augment: (),
+        }));
+
+        assert_eq!(rebalance(&mut tree, &mut metrics), Some(Rotation::RightLeft));
+        assert_eq!(metrics.rotations, 2);
+        let root = tree.unwrap();
+        assert_eq!((root.value, root.size, root.height), (2, 3, 2));
+    }
+
+    #[test]
+    fn rotation_count_is_bounded() {
+        let n = 1_000;
+        let mut set = AvlTreeSet::new();
+        set.extend(0..n);
+
+        assert!(set.rotation_count() > 0);
+        assert!(set.rotation_count() <= n as u64);
+
+        set.reset_metrics();
+        assert_eq!(set.rotation_count(), 0);
+    }
+
+    #[test]
+    fn sorted_construction_does_not_rotate() {
+        assert_eq!((0..1_000).collect::<AvlTreeSet<_>>().rotation_count(), 0);
+        assert_eq!(AvlTreeSet::from_sorted_iter(0..1_000).rotation_count(), 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::equal;
+    use std::cell::Cell;
+    use std::sync::{Arc, RwLock};
+    use std::thread;
+
+    thread_local! {
+        static COMPARISONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Counted(i32);
+
+    impl PartialOrd for Counted {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Counted {
+        fn cmp(&self, other: &Self) -> Ordering {
+            COMPARISONS.with(|count| count.set(count.get() + 1));
+            self.0.cmp(&other.0)
+        }
+    }
+
+    pub(crate) fn is_balanced<T: Ord>(tree: &AvlTree<T>) -> bool {
+        match tree {
+            None => true,
+            Some(node) => {
+                let expected_height = cmp::max(node.left_height(), node.right_height()) + 1;
+
+                node.height == expected_height
+                    && node.size == tree_size(&node.left) + tree_size(&node.right) + 1
+                    && node.balance_factor().abs() <= 1
+                    && is_balanced(&node.left)
+                    && is_balanced(&node.right)
+            }
+        }
+    }
+
+    #[test]
+    fn borrowed_lookups() {
+        let mut set = ["apple", "banana", "cherry"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<AvlTreeSet<String>>();
+
+        assert!(set.contains("banana"));
+        assert_eq!(set.get("cherry").map(String::as_str), Some("cherry"));
+        assert_eq!(set.neighbors("b"), (Some(&"apple".to_string()), Some(&"banana".to_string())));
+        assert!(set.any_in_range::<str, _>((Bound::Included("b"), Bound::Excluded("c"))));
+        assert!(!set.insert_with("apple", || unreachable!()));
+        assert!(set.remove("apple"));
+        assert!(!set.contains("apple"));
+    }
+
+    #[test]
+    #[should_panic(expected = "not strictly increasing")]
+    #[cfg(debug_assertions)]
+    fn from_sorted_iter_rejects_unsorted_input() {
+        AvlTreeSet::from_sorted_iter(vec![1, 3, 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of balance")]
+    #[cfg(debug_assertions)]
+    fn balance_factor_rejects_corrupt_heights() {
+        let leaf = AvlNode { value: 1, left: None, right: None, height: 200, size: 1, augment: () };
+        let node = AvlNode { value: 0, left: None, right: Some(Link::new(leaf)), height: 201, size: 2, augment: () };
+
+        node.balance_factor();
+    }
+
+    #[test]
+    fn custom_comparator_orders_values() {
+        type CaseInsensitive = fn(&String, &String) -> Ordering;
+
+        let by_lowercase: CaseInsensitive = |a, b| a.to_lowercase().cmp(&b.to_lowercase());
+        let mut set = AvlTreeSet::with_comparator(by_lowercase);
+
+        assert!(set.insert("b".to_string()));
+        assert!(set.insert("A".to_string()));
+        assert!(set.insert("C".to_string()));
+        assert!(!set.insert("a".to_string()));
+        assert!(set.contains(&"B".to_string()));
+        assert!(equal(set.iter().map(String::as_str), vec!["A", "b", "C"]));
+
+        let upper = set.split_off(&"b".to_string());
+        assert!(equal(upper.iter().map(String::as_str), vec!["b", "C"]));
+        assert!(equal((&set | &upper).iter().map(String::as_str), vec!["A", "b", "C"]));
+    }
+
+    #[test]
+    fn index_finds_median() {
+        let set = [9, 1, 7, 3, 5].iter().cloned().collect::<AvlTreeSet<_>>();
+
+        assert_eq!(set[set.len() / 2], 5);
+        assert_eq!(set.get_index_of(&7), Some(3));
+        assert_eq!(set.get_index_of(&4), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn index_out_of_bounds_panics() {
+        let set = (0..3).collect::<AvlTreeSet<_>>();
+        let _ = set[3];
+    }
+
+    #[test]
+    fn replace_swaps_equal_values() {
+        #[derive(Debug, PartialEq)]
+        struct Keyed(u8, &'static str);
+
+        impl Ord for Keyed {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.0.cmp(&other.0)
+            }
+        }
+
+        impl PartialOrd for Keyed {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Eq for Keyed {}
+
+        let mut set = AvlTreeSet::new();
+        assert_eq!(set.replace(Keyed(1, "old")), None);
+        assert_eq!(set.replace(Keyed(2, "two")), None);
+        assert_eq!(set.replace(Keyed(1, "new")).map(|k| k.1), Some("old"));
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.get(&Keyed(1, "")).map(|k| k.1), Some("new"));
+        assert_eq!(set.take(&Keyed(1, "")).map(|k| k.1), Some("new"));
+        assert_eq!(set.take(&Keyed(1, "")), None);
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn get_or_insert_with_interns() {
+        let mut set = AvlTreeSet::<String>::new();
+
+        let first = set.get_or_insert_with("a", str::to_string) as *const String;
+        let again = set.get_or_insert_with("a", |_| unreachable!()) as *const String;
+        assert_eq!(first, again);
+
+        for &s in ["d", "b", "c", "e"].iter() {
+            assert_eq!(set.get_or_insert_with(s, str::to_string), s);
+        }
+
+        assert_eq!(set.get_or_insert("c".to_string()), "c");
+        assert_eq!(set.len(), 5);
+        assert!(is_balanced(&set.root));
+    }
+
+    #[test]
+    fn insert_and_get_reports_the_stored_value() {
+        let mut set = AvlTreeSet::new();
+
+        for value in 0..100 {
+            assert_eq!(*set.insert_unique_unchecked(value), value);
+        }
+
+        assert_eq!(set.insert_and_get(100), (true, &100));
+        assert_eq!(set.insert_and_get(42), (false, &42));
+        assert_eq!(set.len(), 101);
+        assert!(set.iter().cloned().eq(0..=100));
+        assert!(is_balanced(&set.root));
+    }
+
+    #[test]
+    fn insert_tracked_finds_value_after_rotations() {
+        let mut metrics = Metrics::default();
+        let mut tree = None;
+
+        for value in (0..200).map(|v| (v * 37) % 200) {
+            let (path, inserted) = insert_tracked(&mut tree, value, &Natural, &mut metrics);
+            assert!(inserted);
+            assert_eq!(node_at_path_mut(&mut tree, &path).value, value);
+        }
+
+        let (path, inserted) = insert_tracked(&mut tree, 5, &Natural, &mut metrics);
+        assert!(!inserted);
+        assert_eq!(node_at_path_mut(&mut tree, &path).value, 5);
+        assert!(is_balanced(&tree));
+    }
+
+    #[test]
+    fn any_in_range_short_circuits() {
+        let mut set = AvlTreeSet::new();
+        set.prepend_sorted(&(0..1024).map(|i| Counted(i * 2)).collect::<Vec<_>>());
+
+        COMPARISONS.with(|count| count.set(0));
+        assert!(set.any_in_range(Counted(701)..Counted(704)));
+        assert!(COMPARISONS.with(Cell::get) <= 2 * tree_height(&set.root));
+
+        assert!(!set.any_in_range(Counted(701)..Counted(702)));
+    }
+
+    #[test]
+    fn update_with_mutates_payload_in_place() {
+        let mut set = AvlTreeSet::by_key(|&(key, _): &(u32, u32)| key);
+        set.extend(vec![(1, 10), (2, 20), (3, 30)]);
+
+        assert_eq!(set.update_with(&(2, 0), |value| mem::replace(&mut value.1, 25)), Some(20));
+        assert_eq!(set.update_with(&(4, 0), |value| value.1 += 1), None);
+        assert_eq!(set.get_by_key(&2), Some(&(2, 25)));
+    }
+
+    #[test]
+    #[should_panic(expected = "update_with changed the ordering")]
+    #[cfg(debug_assertions)]
+    fn update_with_checks_ordering() {
+        let mut set = (0..10).collect::<AvlTreeSet<_>>();
+        set.update_with(&3, |value| *value = 30);
+    }
+
+    #[test]
+    fn conversions_sort_and_deduplicate() {
+        let values = vec![3, 1, 2, 3];
+
+        assert_eq!(AvlTreeSet::from(values.clone()), vec![1, 2, 3]);
+        assert_eq!(AvlTreeSet::from([5, 4, 4]), vec![4, 5]);
+        assert_eq!(values.iter().collect::<AvlTreeSet<i32>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn drain_sorted_stops_early() {
+        let mut set = (0..10).collect::<AvlTreeSet<_>>();
+
+        {
+            let mut drain = set.drain_sorted();
+            assert_eq!(drain.len(), 10);
+            assert_eq!((drain.next(), drain.next_back(), drain.next()), (Some(0), Some(9), Some(1)));
+        }
+
+        assert_eq!(set.drain_sorted_rev().take(2).collect::<Vec<_>>(), vec![8, 7]);
+        assert!(set.check_invariants().is_ok());
+        assert_eq!(set, vec![2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn traverser_transitions() {
+        let values = |traverser: &mut Traverser<&AvlNode<i32>>, from_back: &[bool]| {
+            from_back
+                .iter()
+                .map(|&back| if back { traverser.next_back() } else { traverser.next_front() })
+                .map(|node| node.map(|node| node.value))
+                .collect::<Vec<_>>()
+        };
+
+        let empty = AvlTreeSet::<i32>::new();
+        assert_eq!(values(&mut Traverser::new(&empty.root), &[false, true]), vec![None, None]);
+
+        let single = AvlTreeSet::from([7]);
+        assert_eq!(values(&mut Traverser::new(&single.root), &[false, false, true]), vec![Some(7), None, None]);
+        assert_eq!(values(&mut Traverser::new(&single.root), &[true, false]), vec![Some(7), None]);
+
+        let set = (1..=7).collect::<AvlTreeSet<_>>();
+        let mut traverser = Traverser::new(&set.root);
+        assert_eq!(
+            values(&mut traverser, &[true, false, true, false, false, true, false, true]),
+            vec![Some(7), Some(1), Some(6), Some(2), Some(3), Some(5), Some(4), None],
+        );
+        assert!(traverser.front.is_empty() && traverser.back.is_empty());
+
+        let mut exhausted_front = Traverser { front: Vec::new(), back: Traverser::new(&set.root).back };
+        assert_eq!(values(&mut exhausted_front, &[true, false]), vec![None, None]);
+
+        let mut owned = set.clone();
+        let mut draining = Traverser::front_only(owned.root.take());
+        assert_eq!(draining.front.iter().map(|node| node.value).collect::<Vec<_>>(), vec![4, 2, 1]);
+        assert_eq!(draining.step_front().map(|node| (node.value, node.left.is_none(), node.right.is_none())), Some((1, true, true)));
+        assert_eq!(draining.step_front().map(|node| (node.value, node.left.is_none(), node.right.is_none())), Some((2, true, true)));
+        assert_eq!(draining.front.iter().map(|node| node.value).collect::<Vec<_>>(), vec![4, 3]);
+        assert_eq!(iter::from_fn(|| draining.step_front()).map(|node| node.value).collect::<Vec<_>>(), vec![3, 4, 5, 6, 7]);
+        assert!(draining.step_front().is_none() && draining.back.is_empty());
+
+        let stacks = |traverser: &Traverser<&AvlNode<i32>>| {
+            let values = |nodes: &Vec<&AvlNode<i32>>| nodes.iter().map(|node| node.value).collect::<Vec<_>>();
+            (values(&traverser.back), values(&traverser.front))
+        };
+        let mut cursor = Traverser::around(&set.root, |&value| value < 3);
+        assert_eq!(stacks(&cursor), (vec![2], vec![4, 3]));
+        assert_eq!(cursor.advance().map(|node| node.value), Some(3));
+        assert_eq!(stacks(&cursor), (vec![2, 3], vec![4]));
+        assert_eq!(cursor.advance().map(|node| node.value), Some(4));
+        assert_eq!(stacks(&cursor), (vec![4], vec![6, 5]));
+        assert_eq!(cursor.retreat().map(|node| node.value), Some(4));
+        assert_eq!(stacks(&cursor), (vec![2, 3], vec![4]));
+
+        let mut at_end = Traverser::around(&set.root, |_| true);
+        assert_eq!(stacks(&at_end), (vec![4, 6, 7], vec![]));
+        assert!(at_end.advance().is_none());
+        assert_eq!(iter::from_fn(|| at_end.retreat()).map(|node| node.value).collect::<Vec<_>>(), vec![7, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(stacks(&at_end), (vec![], vec![4, 2, 1]));
+    }
+
+    #[test]
+    fn empty_sets_in_statics() {
+        static REGISTRY: RwLock<AvlTreeSet<u32>> = RwLock::new(AvlTreeSet::new());
+        let none: AvlTreeSet<u32> = AvlTreeSet::EMPTY;
+
+        REGISTRY.write().unwrap().extend(vec![3, 1]);
+        assert_eq!(*REGISTRY.read().unwrap(), vec![1, 3]);
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn nearest_by_distance() {
+        let set = AvlTreeSet::from([1, 2, 10, 11, 12, 40]);
+
+        assert_eq!(set.nearest(&9, 3), vec![&1, &2, &10]);
+        assert_eq!(set.nearest_by(&9, 3, |&x: &i32| (x - 9).abs()), vec![&10, &11, &12]);
+        assert_eq!(set.nearest_by(&9, 10, |&x: &i32| (x - 9).abs()).len(), 6);
+    }
+
+    #[test]
+    fn node_api_walks_subtrees() {
+        let set = (0..100).collect::<AvlTreeSet<_>>();
+        let root = set.root_node().unwrap();
+        let left = root.left().as_deref().unwrap();
+
+        assert!(equal(root.iter(), set.iter()));
+        assert_eq!((root.min(), root.max()), (&0, &99));
+        assert!(equal(left.iter(), set.range(..root.value())));
+        assert_eq!(left.iter().next_back(), Some(left.max()));
+        assert_eq!(left.iter().len(), left.size());
+        assert!(AvlTreeSet::<u8>::new().root_node().is_none());
+    }
+
+    #[test]
+    fn into_iter_reports_exact_len() {
+        let set = (0..10).rev().collect::<AvlTreeSet<_>>();
+        let len = set.len();
+        let mut into_iter = set.into_iter();
+
+        assert_eq!(into_iter.len(), len);
+
+        for remaining in (0..len).rev() {
+            assert!(into_iter.next().is_some());
+            assert_eq!(into_iter.len(), remaining);
+        }
+
+        assert_eq!(into_iter.next(), None);
+        assert_eq!(into_iter.next(), None);
+    }
+
+    #[test]
+    fn iter_reports_exact_len() {
+        let set = (0..10).collect::<AvlTreeSet<_>>();
+        let mut iter = set.iter();
+
+        assert_eq!(iter.len(), 10);
+        iter.next();
+        iter.next_back();
+        assert_eq!(iter.size_hint(), (8, Some(8)));
+        assert!(equal(iter.cloned(), 1..9));
+    }
+
+    #[test]
+    fn drain_filter_keeps_unvisited_values() {
+        let mut set = (0..10).collect::<AvlTreeSet<_>>();
+
+        {
+            let mut evens = set.drain_filter(|x| x % 2 == 0);
+            assert_eq!(evens.next(), Some(0));
+            assert_eq!(evens.next(), Some(2));
+        }
+
+        assert!(equal(set.iter().cloned(), vec![1, 3, 4, 5, 6, 7, 8, 9]));
+        assert!(is_balanced(&set.root));
+    }
+
+    #[test]
+    fn cursor_bounds() {
+        let set = (0..10).map(|x| x * 2).collect::<AvlTreeSet<_>>();
+
+        let cursor = set.lower_bound(Bound::Excluded(&4));
+        assert_eq!((cursor.peek_prev(), cursor.peek_next()), (Some(&4), Some(&6)));
+
+        let cursor = set.upper_bound(Bound::Included(&5));
+        assert_eq!((cursor.peek_prev(), cursor.peek_next()), (Some(&4), Some(&6)));
+
+        let cursor = set.lower_bound::<i32>(Bound::Unbounded);
+        assert_eq!((cursor.peek_prev(), cursor.peek_next()), (None, Some(&0)));
+    }
+
+    #[test]
+    fn cursor_mut_rejects_out_of_order_values() {
+        let mut set = (0..10).map(|x| x * 2).collect::<AvlTreeSet<_>>();
+
+        {
+            let mut cursor = set.lower_bound_mut(Bound::Included(&8));
+            assert_eq!(cursor.insert_after(9), Err(9));
+            assert_eq!(cursor.insert_after(7), Ok(()));
+            assert_eq!(cursor.next(), Some(&7));
+            assert_eq!(cursor.next(), Some(&8));
+            assert_eq!(cursor.prev(), Some(&8));
+            assert_eq!(cursor.remove_prev(), Some(7));
+        }
+
+        assert_eq!(set.len(), 10);
+        assert!(is_balanced(&set.root));
+    }
+
+    #[test]
+    fn into_iter_by_reference() {
+        let set = (0..10).rev().collect::<AvlTreeSet<_>>();
+        let mut expected = 0;
+
+        for value in &set {
+            assert_eq!(*value, expected);
+            expected += 1;
+        }
+
+        assert_eq!(expected, 10);
+    }
+
+    fn degenerate_chain(len: usize) -> AvlTree<usize> {
+        let mut tree = None;
+
+        for value in (0..len).rev() {
+            tree = Some(Link::new(AvlNode { value, left: None, right: tree, height: 1, size: 1, augment: () }));
+        }
+
+        tree
+    }
+
+    #[test]
+    fn default_is_empty() {
+        let set = AvlTreeSet::<u8>::default();
+
+        assert!(set.is_empty());
+        assert!(set == AvlTreeSet::new());
+    }
+
+    #[test]
+    fn drop_degenerate_tree_without_overflow() {
+        let set = AvlTreeSet { root: degenerate_chain(1_000_000), len: 1_000_000, metrics: Metrics::default(), generation: Generation::new(), pool: NodePool::new(), compare: instrumented(Natural) };
+        drop(set);
+    }
+
+    #[test]
+    fn drop_partially_consumed_into_iter_without_overflow() {
+        let set = AvlTreeSet { root: degenerate_chain(1_000_000), len: 1_000_000, metrics: Metrics::default(), generation: Generation::new(), pool: NodePool::new(), compare: instrumented(Natural) };
+        let mut into_iter = set.into_iter();
+
+        assert_eq!(into_iter.next(), Some(0));
+        drop(into_iter);
+    }
+
+    #[test]
+    fn prepend_sorted_joins_balanced_prefix() {
+        let mut set = AvlTreeSet::new();
+        set.prepend_sorted(&(0..100).collect::<Vec<_>>());
+        set.prepend_sorted(&(-100..0).collect::<Vec<_>>());
+
+        assert!(is_balanced(&set.root));
+        assert!(equal(set.iter().cloned(), -100..100));
+        assert_eq!(set.len(), 200);
+    }
+
+    #[test]
+    fn prepend_sorted_joins_taller_prefix() {
+        let mut set = AvlTreeSet::new();
+        set.prepend_sorted(&[100, 101]);
+        set.prepend_sorted(&(0..100).collect::<Vec<_>>());
+
+        assert!(is_balanced(&set.root));
+        assert!(equal(set.iter().cloned(), 0..102));
+    }
+
+    #[test]
+    fn remove_rebalances() {
+        let mut set = AvlTreeSet::new();
+        set.prepend_sorted(&(0..100).collect::<Vec<_>>());
+
+        for value in (0..100).filter(|value| value % 3 != 0) {
+            assert!(set.remove(&value));
+            assert!(is_balanced(&set.root));
+        }
+
+        assert!(!set.remove(&1));
+        assert!(equal(set.iter().cloned(), (0..100).step_by(3)));
+    }
+
+    #[test]
+    fn prepend_sorted_falls_back_on_overlap() {
+        let mut set = AvlTreeSet::new();
+        set.prepend_sorted(&[1, 3, 5]);
+        set.prepend_sorted(&[0, 2, 4]);
+
+        assert!(equal(set.iter().cloned(), 0..6));
+    }
+
+    #[test]
+    fn try_from_iter_stops_at_the_first_error() {
+        let parsed = AvlTreeSet::try_from_iter("3 1 2 1".split(' ').map(str::parse::<u8>));
+        assert!(equal(parsed.unwrap().iter().cloned(), 1..4));
+
+        let mut consumed = 0;
+        let failed = AvlTreeSet::try_from_iter(["1", "x", "2"].iter().map(|s| {
+            consumed += 1;
+            s.parse::<u8>()
+        }));
+
+        assert!(failed.is_err());
+        assert_eq!(consumed, 2);
+    }
+
+    #[test]
+    fn collect_keeps_the_first_of_equal_values() {
+        /// Ordered by the key alone, so values with equal keys are duplicates.
+        #[derive(Debug)]
+        struct Tagged(i32, char);
+
+        impl PartialEq for Tagged {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+
+        impl Eq for Tagged {}
+
+        impl PartialOrd for Tagged {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for Tagged {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.0.cmp(&other.0)
+            }
+        }
+
+        let set = vec![Tagged(2, 'a'), Tagged(1, 'b'), Tagged(2, 'c')].into_iter().collect::<AvlTreeSet<_>>();
+
+        assert_eq!(set.iter().map(|value| value.1).collect::<Vec<_>>(), vec!['b', 'a']);
+    }
+
+    #[test]
+    fn merge_sorted_resolves_duplicates() {
+        let ours = (0..10).step_by(2).collect::<AvlTreeSet<_>>();
+        let theirs = (0..10).step_by(3).collect::<AvlTreeSet<_>>();
+        let mut duplicates = Vec::new();
+
+        let merged = ours.merge_sorted(theirs, |ours, theirs| {
+            duplicates.push(theirs);
+            ours
+        });
+
+        assert!(equal(merged.iter().cloned(), vec![0, 2, 3, 4, 6, 8, 9]));
+        assert!(is_balanced(&merged.root));
+        assert_eq!(duplicates, vec![0, 6]);
+    }
+
+    #[test]
+    fn debug_lists_values_and_alternate_draws_shape() {
+        let set = (1..4).collect::<AvlTreeSet<_>>();
+
+        assert_eq!(format!("{:?}", set), "{1, 2, 3}");
+        assert_eq!(format!("{:?}", AvlTreeSet::<u8>::new()), "{}");
+        assert_eq!(format!("{:#?}", set), set.shape().to_string());
+    }
+
+    #[test]
+    fn next_after_and_before_skip_to_members() {
+        let set = [10, 20, 30].iter().cloned().collect::<AvlTreeSet<_>>();
+
+        assert_eq!(set.next_after(&20), Some(&30));
+        assert_eq!(set.next_after(&25), Some(&30));
+        assert_eq!(set.next_after(&30), None);
+        assert_eq!(set.next_before(&20), Some(&10));
+        assert_eq!(set.next_before(&15), Some(&10));
+        assert_eq!(set.next_before(&10), None);
+    }
+
+    /// The tallest an AVL tree of `len` nodes can be, found by growing the
+    /// sparsest tree of each height until it needs more than `len` nodes.
+    fn max_height(len: usize) -> usize {
+        let (mut height, mut sparsest, mut sparsest_below) = (0, 0, 0);
+
+        while sparsest + sparsest_below < len {
+            let next = sparsest + sparsest_below + 1;
+            sparsest_below = sparsest;
+            sparsest = next;
+            height += 1;
+        }
+
+        height
+    }
+
+    fn pseudo_random(len: usize) -> impl Iterator<Item = u64> {
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+
+        (0..len).map(move |_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        })
+    }
+
+    #[test]
+    #[ignore = "builds ten-million-value trees; run with --release --ignored"]
+    fn ten_million_random_values_stay_shallow() {
+        let mut set = pseudo_random(10_000_000).collect::<AvlTreeSet<_>>();
+        assert!(tree_height(&set.root) <= max_height(set.len()));
+
+        for x in pseudo_random(10_000_000).step_by(2) {
+            assert!(set.remove(&x));
+        }
+
+        assert_eq!(set.len(), 5_000_000);
+        assert!(tree_height(&set.root) <= max_height(set.len()));
+        assert!(set.check_invariants().is_ok());
+    }
+
+    #[test]
+    #[ignore = "builds ten-million-value trees; run with --release --ignored"]
+    fn ten_million_ascending_inserts_stay_shallow() {
+        let mut set = AvlTreeSet::new();
+
+        for x in 0..10_000_000 {
+            set.insert(x);
+        }
+
+        assert!(tree_height(&set.root) <= max_height(set.len()));
+        while set.pop_first().is_some() {}
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn max_height_matches_sparsest_trees() {
+        let heights = (0..13).map(max_height).collect::<Vec<_>>();
+        assert_eq!(heights, vec![0, 1, 2, 2, 3, 3, 3, 4, 4, 4, 4, 4, 5]);
+        assert_eq!(max_height(10_000_000), 33);
+    }
+
+    #[test]
+    fn iter_snapshot_outlives_the_lock() {
+        let set = RwLock::new((0..10).collect::<AvlTreeSet<_>>());
+        let snapshot = set.read().unwrap().iter_snapshot();
+        set.write().unwrap().retain(|&x| x > 5);
+
+        assert_eq!(snapshot.len(), 10);
+        assert!(equal(snapshot, 0..10));
+    }
+
+    #[test]
+    fn converts_to_and_compares_with_std_collections() {
+        let btree_set = (0..10).rev().collect::<BTreeSet<_>>();
+        let set = AvlTreeSet::from(btree_set.clone());
+
+        assert_eq!(set, btree_set);
+        assert_eq!(set, (0..10).collect::<Vec<_>>());
+        assert_ne!(set, (0..10).rev().collect::<Vec<_>>());
+        assert_ne!(set, (0..9).collect::<BTreeSet<_>>());
+        assert_eq!(BTreeSet::from(set), btree_set);
+    }
+
+    #[test]
+    fn shared_across_threads() {
+        let set = Arc::new(RwLock::new((0..1000).collect::<AvlTreeSet<_>>()));
+
+        let readers = (0..4).map(|i| {
+            let set = Arc::clone(&set);
+            thread::spawn(move || set.read().unwrap().range(i * 100..).take(10).sum::<i32>())
+        }).collect::<Vec<_>>();
+        set.write().unwrap().insert(1000);
+
+        for (i, reader) in readers.into_iter().enumerate() {
+            assert_eq!(reader.join().unwrap(), (i as i32 * 100..).take(10).sum::<i32>());
+        }
+        assert_eq!(set.read().unwrap().len(), 1001);
     }
 }
@@ -0,0 +1,174 @@
+//! How a node is owned by its parent. By default each node sits in its own
+//! `Box`. Under the `cow` feature it sits behind an `Arc` instead, so that
+//! cloning a set shares its whole tree in O(1), and a mutation of either
+//! copy clones only the nodes on the path it walks down that the other copy
+//! still shares.
+
+use crate::augment::Augment;
+use crate::AvlNode;
+use core::mem;
+
+/// The owner of a node: a `Box`, or a shared link under the `cow` feature.
+#[cfg(not(feature = "cow"))]
+pub type Link<T, A = ()> = alloc::boxed::Box<AvlNode<T, A>>;
+
+#[cfg(feature = "cow")]
+pub use self::shared::Link;
+
+/// Takes the node out of `link`, copying it first if another set shares it.
+#[cfg(not(feature = "cow"))]
+#[allow(clippy::boxed_local)]
+pub(crate) fn into_node<T, A: Augment<T>>(link: Link<T, A>) -> AvlNode<T, A> {
+    *link
+}
+
+/// The node behind `link` if no other set shares it, for walks that take a
+/// tree apart without copying what they'd throw away.
+#[cfg(not(feature = "cow"))]
+pub(crate) fn get_unique<T, A>(link: &mut Link<T, A>) -> Option<&mut AvlNode<T, A>> {
+    Some(link)
+}
+
+/// The bytes each node's allocation takes.
+#[cfg(not(feature = "cow"))]
+pub(crate) fn allocation_size<T>() -> usize {
+    mem::size_of::<AvlNode<T>>()
+}
+
+#[cfg(feature = "cow")]
+pub(crate) use self::shared::{allocation_size, get_unique, into_node};
+
+#[cfg(feature = "cow")]
+mod shared {
+    use super::*;
+    use alloc::sync::Arc;
+    use core::fmt::{self, Debug};
+    use core::ops::{Deref, DerefMut};
+    use std::sync::OnceLock;
+
+    /// A node shared by every set cloned from the one that built it, until
+    /// one of them changes it. Mutable access copies the node if it's still
+    /// shared, so changes never show through to the other sets.
+    pub struct Link<T, A = ()>(Arc<Shared<T, A>>);
+
+    /// A node along with how to copy its value. Mutations don't require
+    /// `T: Clone`, so the function is recorded when the node is first
+    /// shared, which only `Clone` does, and passed down to its children
+    /// whenever it's copied, since that shares them in turn. A copy's
+    /// augmentation is recomputed from its children, so it needn't be
+    /// `Clone` either.
+    struct Shared<T, A> {
+        node: AvlNode<T, A>,
+        copy: OnceLock<fn(&T) -> T>,
+    }
+
+    impl<T, A> Link<T, A> {
+        pub fn new(node: AvlNode<T, A>) -> Self {
+            Link(Arc::new(Shared { node, copy: OnceLock::new() }))
+        }
+
+        #[cfg(test)]
+        pub(super) fn is_shared(link: &Self) -> bool {
+            Arc::strong_count(&link.0) > 1
+        }
+
+        fn share(&self, copy: fn(&T) -> T) -> Self {
+            let _ = self.0.copy.set(copy);
+            Link(Arc::clone(&self.0))
+        }
+    }
+
+    impl<T, A: Augment<T>> Clone for Shared<T, A> {
+        fn clone(&self) -> Self {
+            let copy = *self.copy.get().expect("a shared node knows how to copy its value");
+            let share = |child: &Option<Link<T, A>>| child.as_ref().map(|child| child.share(copy));
+            let value = copy(&self.node.value);
+            let (left, right) = (share(&self.node.left), share(&self.node.right));
+            let augment = A::augment(&value, left.as_ref().map(|left| &left.augment), right.as_ref().map(|right| &right.augment));
+            let node = AvlNode { value, left, right, height: self.node.height, size: self.node.size, augment };
+
+            Shared { node, copy: OnceLock::new() }
+        }
+    }
+
+    impl<T: Clone, A> Clone for Link<T, A> {
+        fn clone(&self) -> Self {
+            self.share(T::clone)
+        }
+    }
+
+    impl<T, A> Deref for Link<T, A> {
+        type Target = AvlNode<T, A>;
+
+        fn deref(&self) -> &AvlNode<T, A> {
+            &self.0.node
+        }
+    }
+
+    impl<T, A: Augment<T>> DerefMut for Link<T, A> {
+        fn deref_mut(&mut self) -> &mut AvlNode<T, A> {
+            &mut Arc::make_mut(&mut self.0).node
+        }
+    }
+
+    impl<T, A> AsRef<AvlNode<T, A>> for Link<T, A> {
+        fn as_ref(&self) -> &AvlNode<T, A> {
+            self
+        }
+    }
+
+    impl<T: Debug, A: Debug> Debug for Link<T, A> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.node.fmt(f)
+        }
+    }
+
+    pub(crate) fn into_node<T, A: Augment<T>>(link: Link<T, A>) -> AvlNode<T, A> {
+        Arc::try_unwrap(link.0).unwrap_or_else(|shared| (*shared).clone()).node
+    }
+
+    pub(crate) fn get_unique<T, A>(link: &mut Link<T, A>) -> Option<&mut AvlNode<T, A>> {
+        Arc::get_mut(&mut link.0).map(|shared| &mut shared.node)
+    }
+
+    /// Includes the reference counts `Arc` keeps alongside the node.
+    pub(crate) fn allocation_size<T>() -> usize {
+        mem::size_of::<Shared<T, ()>>() + 2 * mem::size_of::<usize>()
+    }
+}
+
+#[cfg(all(test, feature = "cow"))]
+mod tests {
+    use super::shared::Link;
+    use crate::{AvlTree, AvlTreeSet};
+    use alloc::vec::Vec;
+
+    /// Counts the nodes reachable from `tree` that no other set shares.
+    fn unshared(tree: &AvlTree<u32>) -> usize {
+        tree.as_ref().map_or(0, |node| {
+            if Link::is_shared(node) { 0 } else { 1 + unshared(&node.left) + unshared(&node.right) }
+        })
+    }
+
+    #[test]
+    fn clones_share_nodes_until_changed() {
+        let original = (0..10_000).collect::<AvlTreeSet<u32>>();
+        let mut copy = original.clone();
+        assert_eq!(unshared(&copy.root), 0);
+
+        copy.insert(10_000);
+        copy.remove(&0);
+        copy.remove(&5_000);
+        assert!(unshared(&copy.root) <= 3 * copy.height());
+        assert!(original.iter().copied().eq(0..10_000));
+        assert!(copy.iter().copied().eq((1..=10_000).filter(|&value| value != 5_000)));
+        assert_eq!(copy.check_invariants(), Ok(()));
+
+        let values = original.clone().into_iter().collect::<Vec<_>>();
+        drop(original);
+        copy.retain(|value| value % 2 == 0);
+        assert_eq!(unshared(&copy.root), copy.len());
+        assert_eq!(values.len(), 10_000);
+        assert_eq!(copy.check_invariants(), Ok(()));
+    }
+}
@@ -0,0 +1,444 @@
+use crate::{
+    drop_iteratively, find_node, find_node_mut, for_each_node_mut, insert_into, insert_tracked, node_at_path_mut,
+    remove_by, AvlTree, AvlTreeSetIter, Metrics, Natural,
+};
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::iter::{FromIterator, FusedIterator};
+use core::mem;
+
+/// A key and its value as stored in the tree. Ordering only looks at the key,
+/// which lets the map reuse the set's balancing machinery unchanged.
+#[derive(Debug, Clone)]
+pub(crate) struct KeyValue<K, V> {
+    pub(crate) key: K,
+    pub(crate) value: V,
+}
+
+impl<K: Ord, V> PartialEq for KeyValue<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K: Ord, V> Eq for KeyValue<K, V> {}
+
+impl<K: Ord, V> PartialOrd for KeyValue<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, V> Ord for KeyValue<K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+#[derive(Clone)]
+pub struct AvlTreeMap<K: Ord, V> {
+    root: AvlTree<KeyValue<K, V>>,
+    len: usize,
+    metrics: Metrics,
+}
+
+pub struct AvlTreeMapIter<'a, K: Ord, V> {
+    inner: AvlTreeSetIter<'a, KeyValue<K, V>>,
+}
+
+pub struct AvlTreeMapKeys<'a, K: Ord, V> {
+    inner: AvlTreeSetIter<'a, KeyValue<K, V>>,
+}
+
+pub struct AvlTreeMapValues<'a, K: Ord, V> {
+    inner: AvlTreeSetIter<'a, KeyValue<K, V>>,
+}
+
+pub enum Entry<'a, K: Ord, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+pub struct OccupiedEntry<'a, K: Ord, V> {
+    map: &'a mut AvlTreeMap<K, V>,
+    key: K,
+}
+
+pub struct VacantEntry<'a, K: Ord, V> {
+    map: &'a mut AvlTreeMap<K, V>,
+    key: K,
+}
+
+impl<K: Ord, V> AvlTreeMap<K, V> {
+    pub fn new() -> Self {
+        Self { root: None, len: 0, metrics: Metrics::default() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Inserts `value` under `key`, returning the value previously stored
+    /// under an equal key.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(current) = self.get_mut(&key) {
+            return Some(mem::replace(current, value));
+        }
+
+        insert_into(&mut self.root, KeyValue { key, value }, &Natural, &mut self.metrics);
+        self.len += 1;
+
+        None
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        find_node(&self.root, |current| current.key.borrow().cmp(key)).map(|node| &node.value.value)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        find_node_mut(&mut self.root, |current| current.key.borrow().cmp(key)).map(|node| &mut node.value.value)
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let cmp = |current: &KeyValue<K, V>| current.key.borrow().cmp(key);
+        let removed = remove_by(&mut self.root, &cmp, &mut self.metrics)?;
+        self.len -= 1;
+
+        Some(removed.value)
+    }
+
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        if self.contains_key(&key) {
+            Entry::Occupied(OccupiedEntry { map: self, key })
+        } else {
+            Entry::Vacant(VacantEntry { map: self, key })
+        }
+    }
+
+    /// Passes every key and a mutable reference to its value to `f`, in
+    /// ascending key order.
+    pub fn for_each_mut<F: FnMut(&K, &mut V)>(&mut self, mut f: F) {
+        for_each_node_mut(&mut self.root, &mut |node| f(&node.value.key, &mut node.value.value));
+    }
+
+    pub fn iter(&self) -> AvlTreeMapIter<'_, K, V> {
+        AvlTreeMapIter { inner: AvlTreeSetIter::new(&self.root, self.len) }
+    }
+
+    pub fn keys(&self) -> AvlTreeMapKeys<'_, K, V> {
+        AvlTreeMapKeys { inner: AvlTreeSetIter::new(&self.root, self.len) }
+    }
+
+    pub fn values(&self) -> AvlTreeMapValues<'_, K, V> {
+        AvlTreeMapValues { inner: AvlTreeSetIter::new(&self.root, self.len) }
+    }
+}
+
+impl<K: Ord, V> Default for AvlTreeMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> Drop for AvlTreeMap<K, V> {
+    fn drop(&mut self) {
+        drop_iteratively(self.root.take().into_iter().collect());
+    }
+}
+
+impl<'a, K: Ord, V> Entry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+impl<'a, K: Ord, V> OccupiedEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn get(&self) -> &V {
+        self.map.get(&self.key).unwrap()
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        self.map.get_mut(&self.key).unwrap()
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        self.map.get_mut(&self.key).unwrap()
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.map.remove(&self.key).unwrap()
+    }
+}
+
+impl<'a, K: Ord, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        let map = self.map;
+        let value = KeyValue { key: self.key, value };
+        let (path, inserted) = insert_tracked(&mut map.root, value, &Natural, &mut map.metrics);
+        debug_assert!(inserted);
+        map.len += 1;
+
+        &mut node_at_path_mut(&mut map.root, &path).value.value
+    }
+}
+
+impl<'a, K: 'a + Ord, V> Iterator for AvlTreeMapIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| (&entry.key, &entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K: 'a + Ord, V> DoubleEndedIterator for AvlTreeMapIter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|entry| (&entry.key, &entry.value))
+    }
+}
+
+impl<'a, K: 'a + Ord, V> ExactSizeIterator for AvlTreeMapIter<'a, K, V> {}
+
+impl<'a, K: 'a + Ord, V> FusedIterator for AvlTreeMapIter<'a, K, V> {}
+
+impl<'a, K: 'a + Ord, V> Iterator for AvlTreeMapKeys<'a, K, V> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| &entry.key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K: 'a + Ord, V> DoubleEndedIterator for AvlTreeMapKeys<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|entry| &entry.key)
+    }
+}
+
+impl<'a, K: 'a + Ord, V> ExactSizeIterator for AvlTreeMapKeys<'a, K, V> {}
+
+impl<'a, K: 'a + Ord, V> FusedIterator for AvlTreeMapKeys<'a, K, V> {}
+
+impl<'a, K: 'a + Ord, V> Iterator for AvlTreeMapValues<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| &entry.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K: 'a + Ord, V> DoubleEndedIterator for AvlTreeMapValues<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|entry| &entry.value)
+    }
+}
+
+impl<'a, K: 'a + Ord, V> ExactSizeIterator for AvlTreeMapValues<'a, K, V> {}
+
+impl<'a, K: 'a + Ord, V> FusedIterator for AvlTreeMapValues<'a, K, V> {}
+
+impl<'a, K: 'a + Ord, V> IntoIterator for &'a AvlTreeMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = AvlTreeMapIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for AvlTreeMap<K, V> {
+    fn from_iter<I: IntoIterator<Item=(K, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for AvlTreeMap<K, V> {
+    fn extend<I: IntoIterator<Item=(K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod properties {
+    use super::*;
+    use crate::tests::is_balanced;
+    use itertools::equal;
+    use std::collections::BTreeMap;
+
+    #[quickcheck]
+    fn insert_parity(input: Vec<(u8, u16)>) -> bool {
+        let mut avl_map = AvlTreeMap::new();
+        let mut btree_map = BTreeMap::new();
+
+        input.into_iter().all(|(key, value)| avl_map.insert(key, value) == btree_map.insert(key, value))
+            && avl_map.len() == btree_map.len()
+            && equal(avl_map.iter(), btree_map.iter())
+            && equal(avl_map.keys().rev(), btree_map.keys().rev())
+            && equal(avl_map.values(), btree_map.values())
+            && is_balanced(&avl_map.root)
+    }
+
+    #[quickcheck]
+    fn remove_parity(input: Vec<(u8, u16)>, removals: Vec<u8>) -> bool {
+        let mut avl_map = input.iter().cloned().collect::<AvlTreeMap<_, _>>();
+        let mut btree_map = input.iter().cloned().collect::<BTreeMap<_, _>>();
+
+        removals.iter().all(|key| avl_map.remove(key) == btree_map.remove(key))
+            && avl_map.len() == btree_map.len()
+            && equal(avl_map.iter(), btree_map.iter())
+            && is_balanced(&avl_map.root)
+    }
+
+    #[quickcheck]
+    fn entry_parity(input: Vec<u8>) -> bool {
+        let mut avl_map = AvlTreeMap::new();
+        let mut btree_map = BTreeMap::new();
+
+        for key in input {
+            *avl_map.entry(key).or_insert(0) += 1;
+            *btree_map.entry(key).or_insert(0) += 1;
+        }
+
+        equal(avl_map.iter(), btree_map.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_mut_and_borrowed_lookups() {
+        let mut map = AvlTreeMap::new();
+        map.insert("one".to_string(), 1);
+        map.insert("two".to_string(), 2);
+
+        *map.get_mut("one").unwrap() += 10;
+
+        assert_eq!(map.get("one"), Some(&11));
+        assert!(map.contains_key("two"));
+        assert_eq!(map.remove("two"), Some(2));
+        assert!(!map.contains_key("two"));
+    }
+
+    #[test]
+    fn for_each_mut_visits_in_key_order() {
+        let mut map = (0..10).map(|k| (k, 0)).collect::<AvlTreeMap<_, _>>();
+        let mut next = 0;
+
+        map.for_each_mut(|_, value| {
+            *value = next;
+            next += 1;
+        });
+
+        assert!(map.iter().all(|(key, value)| key == value));
+    }
+
+    #[test]
+    fn occupied_and_vacant_entries() {
+        let mut map = AvlTreeMap::new();
+
+        match map.entry(1) {
+            Entry::Vacant(entry) => { *entry.insert("a") = "b"; }
+            Entry::Occupied(_) => unreachable!(),
+        }
+
+        match map.entry(1) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.insert("c"), "b");
+                assert_eq!(entry.remove(), "c");
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+
+        assert!(map.is_empty());
+        assert_eq!(*map.entry(2).and_modify(|v| *v = "x").or_insert("y"), "y");
+        assert_eq!(*map.entry(2).and_modify(|v| *v = "x").or_default(), "x");
+    }
+}
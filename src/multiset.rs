@@ -0,0 +1,246 @@
+use crate::map::{AvlTreeMap, AvlTreeMapIter};
+use core::borrow::Borrow;
+use core::iter::{FromIterator, FusedIterator};
+
+/// An ordered multiset that keeps one node per distinct value along with
+/// the number of copies of it, so duplicates cost no extra nodes.
+#[derive(Clone)]
+pub struct AvlTreeMultiSet<T: Ord> {
+    counts: AvlTreeMap<T, usize>,
+    len: usize,
+}
+
+pub struct AvlTreeMultiSetIter<'a, T: Ord> {
+    counts: AvlTreeMapIter<'a, T, usize>,
+    current: Option<(&'a T, usize)>,
+    remaining: usize,
+}
+
+pub struct AvlTreeMultiSetCounts<'a, T: Ord> {
+    counts: AvlTreeMapIter<'a, T, usize>,
+}
+
+impl<T: Ord> AvlTreeMultiSet<T> {
+    pub fn new() -> Self {
+        Self { counts: AvlTreeMap::new(), len: 0 }
+    }
+
+    /// Returns the number of values in the multiset, counting duplicates.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the number of distinct values in the multiset.
+    pub fn distinct_len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.counts = AvlTreeMap::new();
+        self.len = 0;
+    }
+
+    /// Adds a copy of `value`, returning how many copies are now present.
+    pub fn insert(&mut self, value: T) -> usize {
+        let count = self.counts.entry(value).or_insert(0);
+        *count += 1;
+        self.len += 1;
+
+        *count
+    }
+
+    /// Returns the number of copies of `value` in the multiset.
+    pub fn count<Q>(&self, value: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.counts.get(value).cloned().unwrap_or(0)
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.counts.contains_key(value)
+    }
+
+    /// Removes one copy of `value`, returning whether there was one.
+    pub fn remove_one<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let count = match self.counts.get_mut(value) {
+            Some(count) => count,
+            None => { return false; }
+        };
+
+        *count -= 1;
+        if *count == 0 {
+            self.counts.remove(value);
+        }
+        self.len -= 1;
+
+        true
+    }
+
+    /// Removes every copy of `value`, returning how many there were.
+    pub fn remove_all<Q>(&mut self, value: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let count = self.counts.remove(value).unwrap_or(0);
+        self.len -= count;
+
+        count
+    }
+
+    /// Iterates over the values in sorted order, yielding each value as many
+    /// times as it was inserted.
+    pub fn iter(&self) -> AvlTreeMultiSetIter<'_, T> {
+        AvlTreeMultiSetIter { counts: self.counts.iter(), current: None, remaining: self.len }
+    }
+
+    /// Iterates over the distinct values in sorted order with their counts.
+    pub fn iter_counts(&self) -> AvlTreeMultiSetCounts<'_, T> {
+        AvlTreeMultiSetCounts { counts: self.counts.iter() }
+    }
+}
+
+impl<T: Ord> Default for AvlTreeMultiSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T: 'a + Ord> Iterator for AvlTreeMultiSetIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (value, copies) = match self.current.take() {
+            Some(current) => current,
+            None => self.counts.next().map(|(value, &count)| (value, count))?,
+        };
+
+        if copies > 1 {
+            self.current = Some((value, copies - 1));
+        }
+        self.remaining -= 1;
+
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T: 'a + Ord> ExactSizeIterator for AvlTreeMultiSetIter<'a, T> {}
+
+impl<'a, T: 'a + Ord> FusedIterator for AvlTreeMultiSetIter<'a, T> {}
+
+impl<'a, T: 'a + Ord> Iterator for AvlTreeMultiSetCounts<'a, T> {
+    type Item = (&'a T, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.counts.next().map(|(value, &count)| (value, count))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.counts.size_hint()
+    }
+}
+
+impl<'a, T: 'a + Ord> DoubleEndedIterator for AvlTreeMultiSetCounts<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.counts.next_back().map(|(value, &count)| (value, count))
+    }
+}
+
+impl<'a, T: 'a + Ord> ExactSizeIterator for AvlTreeMultiSetCounts<'a, T> {}
+
+impl<'a, T: 'a + Ord> FusedIterator for AvlTreeMultiSetCounts<'a, T> {}
+
+impl<'a, T: 'a + Ord> IntoIterator for &'a AvlTreeMultiSet<T> {
+    type Item = &'a T;
+    type IntoIter = AvlTreeMultiSetIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Ord> FromIterator<T> for AvlTreeMultiSet<T> {
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T: Ord> Extend<T> for AvlTreeMultiSet<T> {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod properties {
+    use super::*;
+    use itertools::equal;
+
+    #[quickcheck]
+    fn multiset_parity(input: Vec<u8>, removals: Vec<(bool, u8)>) -> bool {
+        let mut set = input.iter().cloned().collect::<AvlTreeMultiSet<_>>();
+        let mut sorted = input.clone();
+        sorted.sort_unstable();
+
+        let removed = removals.iter().all(|&(all, x)| {
+            let copies = sorted.iter().filter(|&&y| y == x).count();
+
+            if all {
+                sorted.retain(|&y| y != x);
+                set.remove_all(&x) == copies
+            } else {
+                if let Some(position) = sorted.iter().position(|&y| y == x) {
+                    sorted.remove(position);
+                }
+                set.remove_one(&x) == (copies > 0)
+            }
+        });
+
+        removed
+            && set.len() == sorted.len()
+            && equal(set.iter(), sorted.iter())
+            && sorted.iter().all(|x| set.count(x) == sorted.iter().filter(|&y| y == x).count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_duplicates_per_value() {
+        let mut set = vec![3, 1, 3, 2, 3].into_iter().collect::<AvlTreeMultiSet<_>>();
+
+        assert_eq!(set.len(), 5);
+        assert_eq!(set.distinct_len(), 3);
+        assert_eq!(set.count(&3), 3);
+        assert_eq!(set.insert(1), 2);
+        assert_eq!(set.iter_counts().collect::<Vec<_>>(), vec![(&1, 2), (&2, 1), (&3, 3)]);
+        assert_eq!(set.remove_all(&3), 3);
+        assert!(!set.contains(&3));
+        assert!(set.remove_one(&1));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![&1, &2]);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{drop_iteratively, link, AvlTree, AvlTreeSet, Link};
+use alloc::vec::Vec;
+use core::mem;
+
+/// The nodes a set has unlinked and kept for later inserts, once
+/// `AvlTreeSet::with_node_pool` has turned pooling on. A node can't be kept
+/// without a value in it, so each pooled node still holds the value it was
+/// removed with until an insert overwrites it.
+pub(crate) struct NodePool<T> {
+    nodes: Option<Vec<Link<T>>>,
+}
+
+impl<T> NodePool<T> {
+    pub(crate) const fn new() -> Self {
+        NodePool { nodes: None }
+    }
+
+    /// A pooled node, reset to a leaf, or `None` if the pool is empty.
+    pub(crate) fn take(&mut self) -> Option<Link<T>> {
+        self.nodes.as_mut()?.pop()
+    }
+
+    /// A leaf holding `value`, in a pooled node if there is one.
+    pub(crate) fn leaf(&mut self, value: T) -> Link<T> {
+        match self.take() {
+            Some(mut node) => {
+                node.value = value;
+                node
+            }
+            None => Link::new(crate::AvlNode::leaf(value)),
+        }
+    }
+
+    /// Keeps `node`, which must have no children, if pooling is on and no
+    /// other set shares it, and drops it otherwise.
+    pub(crate) fn recycle(&mut self, mut node: Link<T>) {
+        if let (Some(nodes), Some(leaf)) = (self.nodes.as_mut(), link::get_unique(&mut node)) {
+            leaf.height = 1;
+            leaf.size = 1;
+            nodes.push(node);
+        }
+    }
+
+    /// The values still held by pooled nodes.
+    #[cfg(feature = "heap-size")]
+    pub(crate) fn values(&self) -> impl Iterator<Item = &T> {
+        self.nodes.iter().flatten().map(|node| &node.value)
+    }
+
+    /// The bytes of the pooled nodes and of the buffer holding them.
+    pub(crate) fn heap_size(&self) -> usize {
+        self.nodes.as_ref().map_or(0, |nodes| nodes.len() * link::allocation_size::<T>() + nodes.capacity() * mem::size_of::<Link<T>>())
+    }
+
+    /// Recycles every node of `tree`.
+    pub(crate) fn recycle_tree(&mut self, tree: AvlTree<T>) {
+        if self.nodes.is_none() {
+            return drop_iteratively(tree.into_iter().collect());
+        }
+
+        let mut trees = alloc::vec![tree];
+        while let Some(tree) = trees.pop() {
+            if let Some(mut node) = tree {
+                if let Some(unique) = link::get_unique(&mut node) {
+                    trees.push(unique.left.take());
+                    trees.push(unique.right.take());
+                    self.recycle(node);
+                }
+            }
+        }
+    }
+}
+
+/// A clone starts with an empty pool, pooling if the original was, rather
+/// than copy values that are only waiting to be overwritten.
+impl<T> Clone for NodePool<T> {
+    fn clone(&self) -> Self {
+        NodePool { nodes: self.nodes.as_ref().map(|_| Vec::new()) }
+    }
+}
+
+impl<T, C> AvlTreeSet<T, C> {
+    /// Turns on a pool of the nodes freed by `remove` and `clear`, which
+    /// `insert`, `extend` and `refill` take from before allocating. A set
+    /// that keeps adding and removing values around a steady size, such as
+    /// a sliding window, then stops allocating once the pool has grown to
+    /// fit its churn.
+    ///
+    /// A pooled node keeps the value it was removed with until it's reused
+    /// or `shrink_to_fit` releases it. `take`, `pop_first` and `pop_last`
+    /// hand their values back, so the nodes they free go to the allocator.
+    pub fn with_node_pool(mut self) -> Self {
+        self.pool.nodes.get_or_insert_with(Vec::new);
+        self
+    }
+
+    /// The number of freed nodes waiting in the pool.
+    pub fn pooled_nodes(&self) -> usize {
+        self.pool.nodes.as_ref().map_or(0, Vec::len)
+    }
+
+    /// Releases the pooled nodes and the values still in them. The pool
+    /// stays on and refills as values are removed.
+    pub fn shrink_to_fit(&mut self) {
+        if let Some(nodes) = self.pool.nodes.as_mut() {
+            *nodes = Vec::new();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removed_nodes_are_reused() {
+        let mut window = (0..100).collect::<AvlTreeSet<u32>>().with_node_pool();
+
+        for value in 100..1_000 {
+            window.remove(&(value - 100));
+            assert_eq!(window.pooled_nodes(), 1);
+            window.insert(value);
+            assert_eq!(window.pooled_nodes(), 0);
+        }
+        assert!(window.iter().copied().eq(900..1_000));
+        assert_eq!(window.check_invariants(), Ok(()));
+
+        window.clear();
+        assert_eq!((window.len(), window.pooled_nodes()), (0, 100));
+        window.extend(0..150);
+        assert_eq!((window.len(), window.pooled_nodes()), (150, 0));
+        assert!(!window.insert(7) && window.remove(&7) && window.remove(&8));
+        assert_eq!((window.replace(7), window.pooled_nodes()), (None, 1));
+        assert_eq!(window.clone().pooled_nodes(), 0);
+        window.shrink_to_fit();
+        assert_eq!(window.pooled_nodes(), 0);
+        assert_eq!(window.check_invariants(), Ok(()));
+
+        let mut unpooled = AvlTreeSet::from([1, 2, 3]);
+        unpooled.remove(&2);
+        unpooled.clear();
+        assert_eq!(unpooled.pooled_nodes(), 0);
+    }
+
+    #[test]
+    fn batches_collected_into_a_pooled_set_reuse_its_nodes() {
+        let mut set = AvlTreeSet::new().with_node_pool();
+
+        for batch in 0..10u32 {
+            let total = AvlTreeSet::collect_into((0..64).map(|value| value + batch), &mut set).iter().sum::<u32>();
+            assert_eq!((total, set.pooled_nodes()), (2016 + 64 * batch, 0));
+
+            set.clear();
+            assert_eq!(set.pooled_nodes(), 64);
+        }
+
+        AvlTreeSet::collect_into([3, 1], &mut set).extend([2]);
+        assert!(AvlTreeSet::collect_into([0], &mut set).iter().copied().eq(0..4));
+        assert_eq!(set.pooled_nodes(), 60);
+    }
+}
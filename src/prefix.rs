@@ -0,0 +1,79 @@
+use crate::{AvlTreeSet, AvlTreeSetRange, Compare};
+use core::borrow::Borrow;
+use core::iter::FusedIterator;
+use core::ops::Bound;
+
+/// Keys that can start with another key of the same type, like strings and
+/// slices. The set's order must sort every key with a given prefix right
+/// after the prefix itself and contiguously, as lexicographic orders do.
+pub trait PrefixBounds {
+    fn has_prefix(&self, prefix: &Self) -> bool;
+}
+
+impl PrefixBounds for str {
+    fn has_prefix(&self, prefix: &Self) -> bool {
+        self.starts_with(prefix)
+    }
+}
+
+impl<T: PartialEq> PrefixBounds for [T] {
+    fn has_prefix(&self, prefix: &Self) -> bool {
+        self.starts_with(prefix)
+    }
+}
+
+pub struct AvlTreeSetPrefix<'a, T, Q: ?Sized> {
+    range: AvlTreeSetRange<'a, T>,
+    prefix: &'a Q,
+    done: bool,
+}
+
+impl<T, C: Compare<T>> AvlTreeSet<T, C> {
+    /// Iterates in ascending order over the values that start with
+    /// `prefix`, descending once to the first value not below `prefix` and
+    /// stopping at the first value after it that lacks the prefix.
+    pub fn iter_prefix<'a, Q>(&'a self, prefix: &'a Q) -> AvlTreeSetPrefix<'a, T, Q>
+    where
+        T: Borrow<Q>,
+        Q: PrefixBounds + ?Sized,
+        C: Compare<Q>,
+    {
+        let range = self.range::<Q, _>((Bound::Included(prefix), Bound::Unbounded));
+
+        AvlTreeSetPrefix { range, prefix, done: false }
+    }
+}
+
+impl<'a, T: Borrow<Q>, Q: PrefixBounds + ?Sized> Iterator for AvlTreeSetPrefix<'a, T, Q> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let value = self.range.next().filter(|value| (*value).borrow().has_prefix(self.prefix));
+        self.done = value.is_none();
+
+        value
+    }
+}
+
+impl<'a, T: Borrow<Q>, Q: PrefixBounds + ?Sized> FusedIterator for AvlTreeSetPrefix<'a, T, Q> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::AvlTreeSet;
+
+    #[test]
+    fn iter_prefix_finds_matching_strings() {
+        let set = ["app", "apple", "apply", "apt", "ap", "banana"].iter().map(|s| s.to_string()).collect::<AvlTreeSet<_>>();
+
+        assert_eq!(set.iter_prefix("app").collect::<Vec<_>>(), vec!["app", "apple", "apply"]);
+        assert_eq!(set.iter_prefix("").count(), 6);
+        assert_eq!(set.iter_prefix("c").next(), None);
+
+        let paths = vec![vec![1, 2], vec![1, 2, 3], vec![1, 3], vec![2]].into_iter().collect::<AvlTreeSet<_>>();
+        assert_eq!(paths.iter_prefix(&[1, 2][..]).count(), 2);
+    }
+}
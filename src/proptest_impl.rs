@@ -0,0 +1,149 @@
+use crate::AvlTreeSet;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use proptest::arbitrary::{any_with, Arbitrary};
+use proptest::collection::{self, SizeRange};
+use proptest::strategy::{NewTree, Strategy, ValueTree};
+use proptest::test_runner::TestRunner;
+
+/// Generates sets of the values `element` generates, with a number of
+/// values in `size`, built in one of several ways so the trees take varying
+/// shapes: rebuilt perfectly balanced, or grown by inserting in arbitrary,
+/// ascending or descending order. Like `proptest::collection::btree_set`,
+/// but shrinks only by removing values, one at a time.
+pub fn avl_tree_set<S>(element: S, size: impl Into<SizeRange>) -> AvlTreeSetStrategy<S>
+where
+    S: Strategy,
+    S::Value: Ord + Clone,
+{
+    AvlTreeSetStrategy { element, size: size.into() }
+}
+
+/// The strategy returned by `avl_tree_set`.
+#[derive(Debug, Clone)]
+pub struct AvlTreeSetStrategy<S> {
+    element: S,
+    size: SizeRange,
+}
+
+/// The value tree of `AvlTreeSetStrategy`, which simplifies a set by
+/// removing its values in the order they were generated.
+#[derive(Debug, Clone)]
+pub struct AvlTreeSetValueTree<T> {
+    values: Vec<T>,
+    kept: Vec<bool>,
+    shape: u8,
+    next: usize,
+    last_removed: Option<usize>,
+}
+
+impl<S> Strategy for AvlTreeSetStrategy<S>
+where
+    S: Strategy,
+    S::Value: Ord + Clone,
+{
+    type Tree = AvlTreeSetValueTree<S::Value>;
+    type Value = AvlTreeSet<S::Value>;
+
+    fn new_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+        let (mut values, shape) = (collection::vec(&self.element, self.size.clone()), 0..4u8).new_tree(runner)?.current();
+        let mut seen = AvlTreeSet::new();
+        values.retain(|value| seen.insert(value.clone()));
+
+        let kept = alloc::vec![true; values.len()];
+        Ok(AvlTreeSetValueTree { values, kept, shape, next: 0, last_removed: None })
+    }
+}
+
+impl<T: Ord + Clone + Debug> ValueTree for AvlTreeSetValueTree<T> {
+    type Value = AvlTreeSet<T>;
+
+    fn current(&self) -> AvlTreeSet<T> {
+        let mut values = self.values.iter().zip(&self.kept).filter(|(_, &kept)| kept).map(|(value, _)| value.clone()).collect::<Vec<_>>();
+
+        match self.shape {
+            0 => values.into_iter().collect(),
+            1 => insert_all(values),
+            2 => {
+                values.sort();
+                insert_all(values)
+            }
+            _ => {
+                values.sort_by(|a, b| b.cmp(a));
+                insert_all(values)
+            }
+        }
+    }
+
+    fn simplify(&mut self) -> bool {
+        while self.next < self.values.len() {
+            let index = self.next;
+            self.next += 1;
+
+            if self.kept[index] {
+                self.kept[index] = false;
+                self.last_removed = Some(index);
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn complicate(&mut self) -> bool {
+        match self.last_removed.take() {
+            Some(index) => {
+                self.kept[index] = true;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<T: Arbitrary + Ord + Clone> Arbitrary for AvlTreeSet<T> {
+    type Parameters = (SizeRange, T::Parameters);
+    type Strategy = AvlTreeSetStrategy<T::Strategy>;
+
+    fn arbitrary_with((size, args): Self::Parameters) -> Self::Strategy {
+        avl_tree_set(any_with::<T>(args), size)
+    }
+}
+
+fn insert_all<T: Ord, I: IntoIterator<Item = T>>(values: I) -> AvlTreeSet<T> {
+    let mut set = AvlTreeSet::new();
+    for value in values {
+        set.insert(value);
+    }
+    set
+}
+
+#[cfg(test)]
+mod properties {
+    use super::*;
+    use proptest::prelude::*;
+    use proptest::test_runner::TestError;
+
+    proptest! {
+        #[test]
+        fn arbitrary_sets_are_valid(set in any::<AvlTreeSet<u8>>()) {
+            prop_assert!(set.check_invariants().is_ok());
+        }
+    }
+
+    #[test]
+    fn shrinking_removes_values() {
+        let strategy = avl_tree_set(0..50u8, 10..40);
+        let result = TestRunner::default().run(&strategy, |set| {
+            prop_assert!(!set.contains(&7) || set.len() < 3);
+            Ok(())
+        });
+
+        match result {
+            Err(TestError::Fail(_, minimal)) => {
+                assert!(minimal.contains(&7) && minimal.len() == 3 && minimal.check_invariants().is_ok())
+            }
+            other => panic!("expected a shrunk failure, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{above_start, below_end, tree_size, AvlNode, AvlTree, AvlTreeSet, AvlTreeSetRange, Compare, Side, Traverser};
+use alloc::vec::Vec;
+use core::hash::{Hash, Hasher};
+use core::ops::{Bound, RangeBounds};
+
+/// A range that is queried over and over, e.g. on every tick of a polling
+/// loop. It remembers the frontier of its last descent, the paths to where
+/// the range starts and ends, along with the set's generation stamp. While
+/// the set stays unchanged, `count` answers in O(1) and `iter` retraces the
+/// recorded paths without comparing any values. Calls that leave the set as
+/// it was, such as inserting a value it already holds or removing one it
+/// doesn't, keep the frontier. After any actual change, even one outside
+/// the range, or when pointed at another set, the query descends afresh.
+#[derive(Debug, Clone)]
+pub struct RangeQuery<T> {
+    start: Bound<T>,
+    end: Bound<T>,
+    frontier: Option<Frontier>,
+}
+
+/// Where the two descents of a query went in the set stamped `stamp`: the
+/// steps towards the first value in the range, those towards the last, and
+/// how many values lie between them.
+#[derive(Debug, Clone)]
+struct Frontier {
+    stamp: Option<(usize, u64)>,
+    front: Vec<Side>,
+    back: Vec<Side>,
+    count: usize,
+}
+
+impl<T: Clone> RangeQuery<T> {
+    pub fn new<R: RangeBounds<T>>(range: R) -> Self {
+        Self { start: range.start_bound().cloned(), end: range.end_bound().cloned(), frontier: None }
+    }
+}
+
+impl<T> RangeQuery<T> {
+    pub fn start_bound(&self) -> Bound<&T> {
+        self.start.as_ref()
+    }
+
+    pub fn end_bound(&self) -> Bound<&T> {
+        self.end.as_ref()
+    }
+
+    /// Iterates over the values of `set` within the range in ascending
+    /// order.
+    pub fn iter<'a, C: Compare<T>>(&mut self, set: &'a AvlTreeSet<T, C>) -> AvlTreeSetRange<'a, T> {
+        let frontier = self.frontier(set);
+        let mut traverser = Traverser {
+            front: retrace(&set.root, &frontier.front, Side::Left),
+            back: retrace(&set.root, &frontier.back, Side::Right),
+        };
+
+        if frontier.count == 0 {
+            traverser.finish();
+        }
+
+        AvlTreeSetRange { traverser }
+    }
+
+    /// Counts the values of `set` within the range.
+    pub fn count<C: Compare<T>>(&mut self, set: &AvlTreeSet<T, C>) -> usize {
+        self.frontier(set).count
+    }
+
+    fn frontier<C: Compare<T>>(&mut self, set: &AvlTreeSet<T, C>) -> &Frontier {
+        let stamp = set.generation.stamp();
+        let frontier = match self.frontier.take() {
+            Some(frontier) if stamp.is_some() && frontier.stamp == stamp => frontier,
+            _ => {
+                let compare = &set.compare;
+                let (front, before_start) = descend(&set.root, |value| above_start(value, self.start.as_ref(), compare));
+                let (back, up_to_end) = descend(&set.root, |value| !below_end(value, self.end.as_ref(), compare));
+
+                Frontier { stamp, front, back, count: up_to_end.saturating_sub(before_start) }
+            }
+        };
+
+        self.frontier.insert(frontier)
+    }
+}
+
+/// Descends `tree` to the left wherever `go_left` holds and to the right
+/// elsewhere, returning the steps taken and how many values lie to the
+/// left of the path.
+fn descend<T, F: Fn(&T) -> bool>(tree: &AvlTree<T>, go_left: F) -> (Vec<Side>, usize) {
+    let mut path = Vec::new();
+    let mut skipped = 0;
+    let mut current_tree = tree;
+
+    while let Some(current_node) = current_tree {
+        if go_left(&current_node.value) {
+            path.push(Side::Left);
+            current_tree = &current_node.left;
+        } else {
+            path.push(Side::Right);
+            skipped += tree_size(&current_node.left) + 1;
+            current_tree = &current_node.right;
+        }
+    }
+
+    (path, skipped)
+}
+
+/// Follows `path` down `tree`, collecting the nodes left towards `side`:
+/// the stack `AvlTreeSet::range` would have built for that end.
+fn retrace<'a, T>(tree: &'a AvlTree<T>, path: &[Side], side: Side) -> Vec<&'a AvlNode<T>> {
+    let mut nodes = Vec::new();
+    let mut current_tree = tree;
+
+    for &step in path {
+        let current_node = match current_tree {
+            Some(node) => node,
+            None => break,
+        };
+
+        if step == side {
+            nodes.push(current_node.as_ref());
+        }
+        current_tree = match step {
+            Side::Left => &current_node.left,
+            Side::Right => &current_node.right,
+        };
+    }
+
+    nodes
+}
+
+impl<T: PartialEq> PartialEq for RangeQuery<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start && self.end == other.end
+    }
+}
+
+impl<T: Eq> Eq for RangeQuery<T> {}
+
+impl<T: Hash> Hash for RangeQuery<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.start.hash(state);
+        self.end.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn queries_follow_the_set() {
+        let mut set = (0..10).collect::<AvlTreeSet<_>>();
+        let mut queries = [RangeQuery::new(2..5), RangeQuery::new(8..), RangeQuery::new(..=0)];
+
+        assert_eq!(queries.iter_mut().map(|query| query.count(&set)).collect::<Vec<_>>(), vec![3, 2, 1]);
+
+        set.remove(&3);
+        set.insert(12);
+        assert_eq!(queries[0].iter(&set).collect::<Vec<_>>(), vec![&2, &4]);
+        assert_eq!(queries.iter_mut().map(|query| query.count(&set)).collect::<Vec<_>>(), vec![2, 3, 1]);
+        assert_eq!(queries[1].start_bound(), Bound::Included(&8));
+    }
+
+    #[test]
+    fn frontiers_are_reused_until_the_set_changes() {
+        let mut set = (0..100).collect::<AvlTreeSet<_>>();
+        let mut query = RangeQuery::new(20..=40);
+
+        assert_eq!(query.count(&set), 21);
+        let stamp = query.frontier.as_ref().unwrap().stamp;
+        assert!(stamp.is_some() && query.iter(&set).eq(set.range(20..=40)));
+        assert_eq!(query.frontier.as_ref().unwrap().stamp, stamp);
+
+        assert!(!set.insert(30) && !set.remove(&100) && set.take(&-1).is_none());
+        assert_eq!(set.get_or_insert(30), &30);
+        assert_eq!(query.count(&set), 21);
+        assert_eq!(query.frontier.as_ref().unwrap().stamp, stamp);
+
+        set.retain(|value| value % 2 == 0);
+        assert_eq!(query.count(&set), 11);
+        assert_ne!(query.frontier.as_ref().unwrap().stamp, stamp);
+        assert!(query.iter(&set).eq(set.range(20..=40)));
+        assert!(query.iter(&set).rev().eq(set.range(20..=40).rev()));
+
+        let mut other = set.clone();
+        assert_eq!(other.generation.stamp(), None);
+        other.insert(30);
+        assert_ne!(other.generation.stamp(), set.generation.stamp());
+        assert_eq!(query.count(&other), 11);
+        assert_eq!(RangeQuery::new((Bound::Included(50), Bound::Excluded(10))).iter(&other).next(), None);
+        assert_eq!(query.count(&AvlTreeSet::new()), 0);
+    }
+}
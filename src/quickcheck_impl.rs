@@ -0,0 +1,68 @@
+use crate::{AvlTreeSet, Link};
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use quickcheck::{Arbitrary, Gen};
+
+impl<T: Arbitrary + Ord> Arbitrary for AvlTreeSet<T>
+where
+    Link<T>: Send,
+{
+    /// Builds the set in one of several ways so the trees take varying
+    /// shapes: rebuilt perfectly balanced, grown by inserting in arbitrary,
+    /// ascending or descending order, or grown and then partly removed.
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        let mut values = Vec::<T>::arbitrary(g);
+
+        match u8::arbitrary(g) % 5 {
+            0 => values.into_iter().collect(),
+            1 => insert_all(values),
+            2 => {
+                values.sort();
+                insert_all(values)
+            }
+            3 => {
+                values.sort_by(|a, b| b.cmp(a));
+                insert_all(values)
+            }
+            _ => {
+                let mut set = insert_all(values.iter().cloned());
+                set.retain(|_| bool::arbitrary(g));
+                set
+            }
+        }
+    }
+
+    /// Shrinks only by removing values: first either half, then each value
+    /// on its own.
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let values = self.iter().cloned().collect::<Vec<_>>();
+        let len = values.len();
+        let halves = if len > 1 { vec![(0, len / 2), (len / 2, len)] } else { Vec::new() };
+        let singles = (0..len).map(|i| (i, i + 1));
+
+        Box::new(halves.into_iter().chain(singles).map(move |(start, end)| {
+            values[..start].iter().chain(&values[end..]).cloned().collect::<AvlTreeSet<_>>()
+        }))
+    }
+}
+
+fn insert_all<T: Ord, I: IntoIterator<Item = T>>(values: I) -> AvlTreeSet<T> {
+    let mut set = AvlTreeSet::new();
+    for value in values {
+        set.insert(value);
+    }
+    set
+}
+
+#[cfg(test)]
+mod properties {
+    use crate::AvlTreeSet;
+    use quickcheck::Arbitrary;
+
+    #[quickcheck]
+    fn arbitrary_sets_are_valid(set: AvlTreeSet<u8>) -> bool {
+        set.check_invariants().is_ok()
+            && set.shrink().all(|smaller| smaller.len() < set.len() && smaller.is_subset(&set))
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{AvlTreeSet, AvlTreeSetIter};
+use core::iter::{self, FusedIterator, Peekable};
+use core::ops::RangeInclusive;
+
+/// Values with an immediate successor, like the integers, so that runs of
+/// consecutive values can be told apart from gaps.
+pub trait Discrete: Ord + Copy {
+    /// The next value up, or `None` for the greatest value.
+    fn successor(&self) -> Option<Self>;
+}
+
+macro_rules! impl_discrete {
+    ($($int:ty),*) => {
+        $(
+            impl Discrete for $int {
+                fn successor(&self) -> Option<Self> {
+                    self.checked_add(1)
+                }
+            }
+        )*
+    };
+}
+
+impl_discrete!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+/// Iterates over the maximal runs of consecutive values in a set, in
+/// ascending order. Created by `AvlTreeSet::iter_ranges`.
+pub struct AvlTreeSetRanges<'a, T> {
+    iter: Peekable<AvlTreeSetIter<'a, T>>,
+}
+
+impl<T: Discrete> AvlTreeSet<T> {
+    /// Iterates over the maximal runs of consecutive values as inclusive
+    /// ranges, so `{1, 2, 3, 7, 8}` yields `1..=3` and then `7..=8`.
+    pub fn iter_ranges(&self) -> AvlTreeSetRanges<'_, T> {
+        AvlTreeSetRanges { iter: self.iter().peekable() }
+    }
+
+    /// Inserts every value in `range`, returning how many were new. Spans
+    /// that are long next to the set are merged in in one pass, as with
+    /// `insert_batch`.
+    pub fn insert_range(&mut self, range: RangeInclusive<T>) -> usize {
+        let (start, end) = range.into_inner();
+        let values = iter::successors(Some(start), |value| value.successor()).take_while(|value| *value <= end);
+
+        let inserted = self.insert_batch_values(values);
+
+        self.changed("insert_range");
+        inserted
+    }
+}
+
+impl<'a, T: Discrete> Iterator for AvlTreeSetRanges<'a, T> {
+    type Item = RangeInclusive<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = *self.iter.next()?;
+        let mut end = start;
+
+        while let Some(&&next) = self.iter.peek() {
+            if end.successor() != Some(next) {
+                break;
+            }
+
+            end = next;
+            self.iter.next();
+        }
+
+        Some(start..=end)
+    }
+}
+
+impl<'a, T: Discrete> FusedIterator for AvlTreeSetRanges<'a, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn runs_of_consecutive_values() {
+        let mut set = AvlTreeSet::from([1u8, 2, 3, 7, 8, 255]);
+        assert_eq!(set.iter_ranges().collect::<Vec<_>>(), vec![1..=3, 7..=8, 255..=255]);
+
+        assert_eq!(set.insert_range(4..=6), 3);
+        assert_eq!(set.insert_range(250..=255), 5);
+        assert_eq!(set.insert_range(RangeInclusive::new(9, 8)), 0);
+        assert_eq!(set.iter_ranges().collect::<Vec<_>>(), vec![1..=8, 250..=255]);
+        assert!(AvlTreeSet::<i32>::new().iter_ranges().next().is_none());
+    }
+}
@@ -0,0 +1,227 @@
+use crate::{concat, join, link, split, tree_size, AvlNode, AvlTree, AvlTreeSet, Compare, Link, Metrics, Natural};
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use rayon::iter::{FromParallelIterator, IntoParallelIterator, ParallelExtend, ParallelIterator};
+use rayon::slice::ParallelSliceMut;
+
+impl<T: Send, C> IntoParallelIterator for AvlTreeSet<T, C> {
+    type Item = T;
+    type Iter = rayon::vec::IntoIter<T>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.into_iter().collect::<Vec<_>>().into_par_iter()
+    }
+}
+
+impl<'a, T: Sync, C> IntoParallelIterator for &'a AvlTreeSet<T, C> {
+    type Item = &'a T;
+    type Iter = rayon::vec::IntoIter<&'a T>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.iter().collect::<Vec<_>>().into_par_iter()
+    }
+}
+
+/// Sorts `values` with a parallel merge sort and drops all but the first
+/// of each run of equal values.
+fn par_sort_dedup<T: Send, C: Compare<T> + Sync>(values: &mut Vec<T>, compare: &C) {
+    values.par_sort_by(|a, b| compare.compare(a, b));
+    values.dedup_by(|a, b| compare.compare(a, b) == Ordering::Equal);
+}
+
+impl<T: Ord + Send> FromParallelIterator<T> for AvlTreeSet<T> {
+    /// Collects and sorts the values in parallel, then builds the tree from
+    /// the sorted run in O(n) without any rotations.
+    fn from_par_iter<I: IntoParallelIterator<Item = T>>(par_iter: I) -> Self {
+        let mut values = par_iter.into_par_iter().collect::<Vec<_>>();
+        par_sort_dedup(&mut values, &Natural);
+
+        let mut set = Self::new();
+        set.rebuild_sorted(values);
+        set
+    }
+}
+
+impl<T: Send, C: Compare<T> + Sync> ParallelExtend<T> for AvlTreeSet<T, C> {
+    /// Sorts the new values in parallel and merges them with the set's own
+    /// in one pass. Values already in the set win over equal new ones, as
+    /// with `insert`.
+    fn par_extend<I: IntoParallelIterator<Item = T>>(&mut self, par_iter: I) {
+        let mut values = par_iter.into_par_iter().collect::<Vec<_>>();
+        par_sort_dedup(&mut values, &self.compare);
+
+        let existing = self.drain();
+        let merged = self.merge_runs(existing, values.into_iter(), |ours, _| ours);
+        self.rebuild_sorted(merged);
+        self.changed("par_extend");
+    }
+}
+
+/// The combined size below which the join-based set operations recurse on
+/// one thread, where forking would cost more than it saves.
+const PARALLEL_CUTOFF: usize = 1 << 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SetOp {
+    Union,
+    Intersection,
+    Difference,
+}
+
+/// The join-based operations hand subtrees to other threads, which under
+/// the `cow` feature, where they may be shared, needs `T: Sync` as well.
+impl<T: Send, C: Compare<T> + Sync> AvlTreeSet<T, C>
+where
+    Link<T>: Send,
+{
+    /// Consumes both sets into their union, keeping the values of `self`
+    /// over equal ones of `other`. Uses the divide-and-conquer join-based
+    /// algorithm with the halves on separate threads, in
+    /// O(m log(n / m + 1)) work for sets of sizes m <= n.
+    pub fn par_union(self, other: Self) -> Self {
+        let mut union = self.par_combine(other, SetOp::Union);
+        union.changed("par_union");
+        union
+    }
+
+    /// Consumes both sets into the values of `self` that are also in
+    /// `other`, like `par_union`.
+    pub fn par_intersection(self, other: Self) -> Self {
+        let mut intersection = self.par_combine(other, SetOp::Intersection);
+        intersection.changed("par_intersection");
+        intersection
+    }
+
+    /// Consumes both sets into the values of `self` that are not in
+    /// `other`, like `par_union`.
+    pub fn par_difference(self, other: Self) -> Self {
+        let mut difference = self.par_combine(other, SetOp::Difference);
+        difference.changed("par_difference");
+        difference
+    }
+
+    fn par_combine(mut self, mut other: Self, op: SetOp) -> Self {
+        other.len = 0;
+        self.root = combine(op, self.root.take(), other.root.take(), &self.compare, &mut self.metrics);
+        self.len = tree_size(&self.root);
+        self
+    }
+}
+
+/// Splits `right` around the root of `left`, combines the matching halves
+/// recursively, in parallel for large enough trees, and joins the results
+/// around the root if `op` keeps it.
+fn combine<T, C>(op: SetOp, left: AvlTree<T>, right: AvlTree<T>, compare: &C, metrics: &mut Metrics) -> AvlTree<T>
+where
+    T: Send,
+    Link<T>: Send,
+    C: Compare<T> + Sync,
+{
+    let node = match left {
+        Some(node) => node,
+        None => { return if op == SetOp::Union { right } else { None }; }
+    };
+
+    if right.is_none() {
+        return if op == SetOp::Intersection { None } else { Some(node) };
+    }
+
+    let parallel = node.size + tree_size(&right) >= PARALLEL_CUTOFF;
+    let AvlNode { value, left, right: node_right, .. } = link::into_node(node);
+    let (less, found, greater) = split(right, &value, compare, metrics);
+
+    let (combined_less, combined_greater) = if parallel {
+        let (mut left_metrics, mut right_metrics) = (Metrics::default(), Metrics::default());
+        let combined = rayon::join(
+            || combine(op, left, less, compare, &mut left_metrics),
+            || combine(op, node_right, greater, compare, &mut right_metrics),
+        );
+        metrics.absorb(left_metrics);
+        metrics.absorb(right_metrics);
+        combined
+    } else {
+        (combine(op, left, less, compare, metrics), combine(op, node_right, greater, compare, metrics))
+    };
+
+    let keep = match op {
+        SetOp::Union => true,
+        SetOp::Intersection => found.is_some(),
+        SetOp::Difference => found.is_none(),
+    };
+
+    if keep {
+        join(combined_less, value, combined_greater, metrics)
+    } else {
+        concat(combined_less, combined_greater, metrics)
+    }
+}
+
+#[cfg(test)]
+mod properties {
+    use super::*;
+    use itertools::equal;
+    use rayon::iter::IntoParallelRefIterator;
+    use std::collections::BTreeSet;
+
+    #[quickcheck]
+    fn from_par_iter_parity(input: Vec<u16>) -> bool {
+        let avl_set = input.par_iter().cloned().collect::<AvlTreeSet<_>>();
+        let btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        avl_set.len() == btree_set.len()
+            && equal(avl_set.iter(), btree_set.iter())
+            && crate::tests::is_balanced(&avl_set.root)
+    }
+
+    #[quickcheck]
+    fn par_extend_parity(input: Vec<u8>, extra: Vec<u8>) -> bool {
+        let mut avl_set = input.iter().cloned().collect::<AvlTreeSet<_>>();
+        let mut btree_set = input.iter().cloned().collect::<BTreeSet<_>>();
+
+        avl_set.par_extend(extra.par_iter().cloned());
+        btree_set.extend(extra.iter().cloned());
+
+        let sum = avl_set.par_iter().map(|&x| u64::from(x)).sum::<u64>();
+
+        equal(avl_set.iter(), btree_set.iter())
+            && avl_set.len() == btree_set.len()
+            && sum == btree_set.iter().map(|&x| u64::from(x)).sum::<u64>()
+            && equal(avl_set.into_par_iter().collect::<Vec<_>>(), btree_set)
+    }
+
+    #[quickcheck]
+    fn par_set_ops_parity(a: Vec<u8>, b: Vec<u8>) -> bool {
+        let (avl_a, avl_b) = (a.iter().collect::<AvlTreeSet<u8>>(), b.iter().collect::<AvlTreeSet<u8>>());
+        let (btree_a, btree_b) = (a.iter().collect::<BTreeSet<_>>(), b.iter().collect::<BTreeSet<_>>());
+
+        let union = avl_a.clone().par_union(avl_b.clone());
+        let intersection = avl_a.clone().par_intersection(avl_b.clone());
+        let difference = avl_a.par_difference(avl_b);
+
+        [&union, &intersection, &difference].iter().all(|set| set.check_invariants().is_ok())
+            && equal(union.iter(), btree_a.union(&btree_b).cloned())
+            && equal(intersection.iter(), btree_a.intersection(&btree_b).cloned())
+            && equal(difference.iter(), btree_a.difference(&btree_b).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn par_set_ops_split_large_sets() {
+        let evens = (0..1 << 15).map(|x| x * 2).collect::<AvlTreeSet<u32>>();
+        let threes = (0..1 << 15).map(|x| x * 3).collect::<AvlTreeSet<u32>>();
+
+        let union = evens.clone().par_union(threes.clone());
+        let intersection = evens.clone().par_intersection(threes.clone());
+        let difference = evens.par_difference(threes);
+
+        assert!(union.check_invariants().is_ok());
+        assert_eq!(union.len(), (1 << 16) - intersection.len());
+        assert!(intersection.iter().all(|x| x % 6 == 0) && intersection.check_invariants().is_ok());
+        assert!(difference.iter().all(|x| x % 2 == 0 && x % 3 != 0) && difference.check_invariants().is_ok());
+        assert_eq!(difference.len() + intersection.len(), 1 << 15);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{AvlNode, AvlTree, AvlTreeSet, Natural};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Display, Write};
+
+/// Renders the shape of a set's tree as indented ASCII, one node per line
+/// with its height and balance factor. Returned by `AvlTreeSet::shape`.
+pub struct AvlTreeSetShape<'a, T, C = Natural> {
+    set: &'a AvlTreeSet<T, C>,
+}
+
+/// Formats a set's values in ascending order with their `Display` impls,
+/// joined by a separator. Returned by `AvlTreeSet::display` and
+/// `AvlTreeSet::format_with`.
+pub struct AvlTreeSetDisplay<'a, T, C = Natural> {
+    set: &'a AvlTreeSet<T, C>,
+    separator: &'a str,
+}
+
+impl<T, C> AvlTreeSet<T, C> {
+    pub fn shape(&self) -> AvlTreeSetShape<'_, T, C> {
+        AvlTreeSetShape { set: self }
+    }
+
+    /// Formats the values as a comma-separated list like `1, 2, 3`.
+    pub fn display(&self) -> AvlTreeSetDisplay<'_, T, C> {
+        self.format_with(", ")
+    }
+
+    /// Formats the values joined by `separator`, without collecting them.
+    /// Any width or precision is applied to each value in turn.
+    pub fn format_with<'a>(&'a self, separator: &'a str) -> AvlTreeSetDisplay<'a, T, C> {
+        AvlTreeSetDisplay { set: self, separator }
+    }
+
+    /// Renders the tree as a Graphviz digraph, labelling every node with its
+    /// value, height and balance factor and every edge with its side.
+    pub fn to_dot(&self) -> String
+    where
+        T: Debug,
+    {
+        let mut dot = String::from("digraph AvlTreeSet {\n");
+        let mut next_id = 0;
+
+        if let Some(root) = &self.root {
+            write_dot_node(&mut dot, root, &mut next_id);
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn node_label<T: Debug>(node: &AvlNode<T>) -> String {
+    format!("{:?} (height {}, balance {})", node.value, node.height, node.balance_factor())
+}
+
+/// Writes `node` and its subtree, returning the id given to `node`.
+fn write_dot_node<T: Debug>(dot: &mut String, node: &AvlNode<T>, next_id: &mut usize) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+
+    writeln!(dot, "    n{} [label=\"{}\"];", id, node_label(node).escape_default()).unwrap();
+
+    for (side, child) in [("L", &node.left), ("R", &node.right)].iter() {
+        if let Some(child) = child {
+            let child_id = write_dot_node(dot, child, next_id);
+            writeln!(dot, "    n{} -> n{} [label=\"{}\"];", id, child_id, side).unwrap();
+        }
+    }
+
+    id
+}
+
+fn write_shape<T: Debug>(f: &mut fmt::Formatter, tree: &AvlTree<T>, prefix: &mut String) -> fmt::Result {
+    let node = match tree {
+        Some(node) => node,
+        None => { return Ok(()); }
+    };
+    let children = [("L", &node.left), ("R", &node.right)];
+    let children = children.iter().filter(|(_, child)| child.is_some()).collect::<Vec<_>>();
+
+    for (i, (side, child)) in children.iter().enumerate() {
+        let last = i + 1 == children.len();
+        let child_node = child.as_ref().unwrap();
+
+        writeln!(f, "{}{} {}: {}", prefix, if last { "`--" } else { "|--" }, side, node_label(child_node))?;
+
+        let len = prefix.len();
+        prefix.push_str(if last { "    " } else { "|   " });
+        write_shape(f, child, prefix)?;
+        prefix.truncate(len);
+    }
+
+    Ok(())
+}
+
+impl<T: Debug, C> Display for AvlTreeSetShape<'_, T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.set.root {
+            Some(root) => {
+                writeln!(f, "{}", node_label(root))?;
+                write_shape(f, &self.set.root, &mut String::new())
+            }
+            None => writeln!(f, "(empty)"),
+        }
+    }
+}
+
+impl<T: Display, C> Display for AvlTreeSetDisplay<'_, T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, value) in self.set.iter().enumerate() {
+            if i > 0 {
+                f.write_str(self.separator)?;
+            }
+            Display::fmt(value, f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::AvlTreeSet;
+
+    #[test]
+    fn shape_draws_every_node() {
+        let set = (0..6).collect::<AvlTreeSet<_>>();
+
+        assert_eq!(
+            set.shape().to_string(),
+            "\
+3 (height 3, balance 0)
+|-- L: 1 (height 2, balance 0)
+|   |-- L: 0 (height 1, balance 0)
+|   `-- R: 2 (height 1, balance 0)
+`-- R: 5 (height 2, balance 1)
+    `-- L: 4 (height 1, balance 0)
+"
+        );
+        assert_eq!(AvlTreeSet::<u8>::new().shape().to_string(), "(empty)\n");
+    }
+
+    #[test]
+    fn display_joins_values() {
+        let set = vec![3, 1, 2].into_iter().collect::<AvlTreeSet<_>>();
+
+        assert_eq!(set.display().to_string(), "1, 2, 3");
+        assert_eq!(format!("[{:>2}]", set.format_with("|")), "[ 1| 2| 3]");
+        assert_eq!(AvlTreeSet::<u8>::new().display().to_string(), "");
+    }
+
+    #[test]
+    fn to_dot_escapes_labels() {
+        let set = AvlTreeSet::from(["b", "a", "c"]);
+
+        assert_eq!(
+            set.to_dot(),
+            "\
+digraph AvlTreeSet {
+    n0 [label=\"\\\"b\\\" (height 2, balance 0)\"];
+    n1 [label=\"\\\"a\\\" (height 1, balance 0)\"];
+    n0 -> n1 [label=\"L\"];
+    n2 [label=\"\\\"c\\\" (height 1, balance 0)\"];
+    n0 -> n2 [label=\"R\"];
+}
+"
+        );
+    }
+}
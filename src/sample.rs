@@ -0,0 +1,105 @@
+use crate::{AvlTreeMap, AvlTreeSet, Compare};
+use core::iter::FusedIterator;
+use rand::Rng;
+
+/// An endless iterator of values drawn uniformly at random, with
+/// replacement. Returned by `AvlTreeSet::sample_iter`.
+pub struct AvlTreeSetSampleIter<'a, T, C, R: ?Sized> {
+    set: &'a AvlTreeSet<T, C>,
+    rng: &'a mut R,
+}
+
+/// An iterator over every value exactly once, in uniformly random order.
+/// Returned by `AvlTreeSet::sample_distinct`.
+pub struct AvlTreeSetSampleDistinct<'a, T, C, R: ?Sized> {
+    set: &'a AvlTreeSet<T, C>,
+    rng: &'a mut R,
+    /// The ranks moved by the lazy Fisher-Yates shuffle so far; any rank not
+    /// present is still in its original place.
+    swapped: AvlTreeMap<usize, usize>,
+    next: usize,
+}
+
+impl<T, C: Compare<T>> AvlTreeSet<T, C> {
+    /// Picks a value uniformly at random in O(log n), or `None` if the set
+    /// is empty.
+    pub fn choose<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<&T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        self.select(rng.gen_range(0, self.len))
+    }
+
+    /// Draws values uniformly at random with replacement, O(log n) each.
+    /// The iterator never ends unless the set is empty.
+    pub fn sample_iter<'a, R: Rng + ?Sized>(&'a self, rng: &'a mut R) -> AvlTreeSetSampleIter<'a, T, C, R> {
+        AvlTreeSetSampleIter { set: self, rng }
+    }
+
+    /// Draws values uniformly at random without replacement, O(log n) each,
+    /// so `.take(k)` samples `k` distinct values. Memory grows with the
+    /// number of values drawn, not with the size of the set.
+    pub fn sample_distinct<'a, R: Rng + ?Sized>(&'a self, rng: &'a mut R) -> AvlTreeSetSampleDistinct<'a, T, C, R> {
+        AvlTreeSetSampleDistinct { set: self, rng, swapped: AvlTreeMap::new(), next: 0 }
+    }
+}
+
+impl<'a, T, C: Compare<T>, R: Rng + ?Sized> Iterator for AvlTreeSetSampleIter<'a, T, C, R> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.set.choose(self.rng)
+    }
+}
+
+impl<'a, T, C: Compare<T>, R: Rng + ?Sized> Iterator for AvlTreeSetSampleDistinct<'a, T, C, R> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = self.set.len();
+        if self.next == len {
+            return None;
+        }
+
+        let chosen = self.rng.gen_range(self.next, len);
+        let rank = self.swapped.get(&chosen).cloned().unwrap_or(chosen);
+        let displaced = self.swapped.remove(&self.next).unwrap_or(self.next);
+
+        if chosen != self.next {
+            self.swapped.insert(chosen, displaced);
+        }
+        self.next += 1;
+
+        self.set.select(rank)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.set.len() - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, T, C: Compare<T>, R: Rng + ?Sized> ExactSizeIterator for AvlTreeSetSampleDistinct<'a, T, C, R> {}
+
+impl<'a, T, C: Compare<T>, R: Rng + ?Sized> FusedIterator for AvlTreeSetSampleDistinct<'a, T, C, R> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::mock::StepRng;
+
+    #[test]
+    fn sample_distinct_visits_every_value_once() {
+        let set = (0..50).collect::<AvlTreeSet<_>>();
+        let mut rng = StepRng::new(7, 0x9E37_79B9_7F4A_7C15);
+
+        let mut sampled = set.sample_distinct(&mut rng).cloned().collect::<Vec<_>>();
+        sampled.sort_unstable();
+
+        assert!(sampled.into_iter().eq(0..50));
+        assert!(set.sample_iter(&mut rng).take(100).all(|value| set.contains(value)));
+        assert_eq!(AvlTreeSet::<u8>::new().choose(&mut rng), None);
+        assert_eq!(AvlTreeSet::<u8>::new().sample_iter(&mut rng).next(), None);
+    }
+}
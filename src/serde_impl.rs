@@ -0,0 +1,78 @@
+use crate::{AvlTreeSet, Compare};
+use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+
+impl<T: Serialize, C> Serialize for AvlTreeSet<T, C> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self)
+    }
+}
+
+impl<'de, T, C> Deserialize<'de> for AvlTreeSet<T, C>
+where
+    T: Deserialize<'de>,
+    C: Compare<T> + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(AvlTreeSetVisitor(PhantomData))
+    }
+}
+
+struct AvlTreeSetVisitor<T, C>(PhantomData<(T, C)>);
+
+impl<'de, T, C> Visitor<'de> for AvlTreeSetVisitor<T, C>
+where
+    T: Deserialize<'de>,
+    C: Compare<T> + Default,
+{
+    type Value = AvlTreeSet<T, C>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+
+        // Sets are serialized in order, so `rebuild_unsorted` only has to sort
+        // input that was produced some other way.
+        let mut set = AvlTreeSet::with_comparator(C::default());
+        set.rebuild_unsorted(values);
+
+        Ok(set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::AvlTreeSet;
+    use crate::tests::is_balanced;
+    use itertools::equal;
+
+    #[test]
+    fn round_trip() {
+        let set = (0..100).collect::<AvlTreeSet<_>>();
+        let json = serde_json::to_string(&set).unwrap();
+        let deserialized = serde_json::from_str::<AvlTreeSet<i32>>(&json).unwrap();
+
+        assert_eq!(json, serde_json::to_string(&(0..100).collect::<Vec<_>>()).unwrap());
+        assert!(deserialized == set);
+        assert!(is_balanced(&deserialized.root));
+    }
+
+    #[test]
+    fn deserialize_unsorted() {
+        let set = serde_json::from_str::<AvlTreeSet<i32>>("[3, 1, 2, 3, 1]").unwrap();
+
+        assert_eq!(set.len(), 3);
+        assert!(equal(set.iter().cloned(), 1..4));
+        assert!(is_balanced(&set.root));
+    }
+}
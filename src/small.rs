@@ -0,0 +1,257 @@
+use crate::{AvlTreeSet, AvlTreeSetIter};
+use alloc::vec::Vec;
+use core::array;
+use core::borrow::Borrow;
+use core::fmt::{self, Debug};
+use core::iter::{FromIterator, FusedIterator};
+use core::slice;
+
+/// An ordered set that keeps up to `N` values in a sorted inline array and
+/// only moves them into an `AvlTreeSet` once it would grow past `N`, so
+/// tiny sets make no per-value allocations. A set that has grown into a
+/// tree stays one.
+#[derive(Clone)]
+pub struct SmallAvlTreeSet<T: Ord, const N: usize = 8> {
+    repr: Repr<T, N>,
+}
+
+#[derive(Clone)]
+enum Repr<T: Ord, const N: usize> {
+    /// The first `len` slots hold the values in ascending order and the
+    /// rest are `None`.
+    Inline { values: [Option<T>; N], len: usize },
+    Tree(AvlTreeSet<T>),
+}
+
+pub struct SmallAvlTreeSetIter<'a, T> {
+    inner: IterInner<'a, T>,
+}
+
+enum IterInner<'a, T> {
+    Inline(slice::Iter<'a, Option<T>>),
+    Tree(AvlTreeSetIter<'a, T>),
+}
+
+impl<T: Ord, const N: usize> SmallAvlTreeSet<T, N> {
+    pub fn new() -> Self {
+        Self { repr: Repr::Inline { values: array::from_fn(|_| None), len: 0 } }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.repr {
+            Repr::Inline { len, .. } => *len,
+            Repr::Tree(set) => set.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` while the values are still stored inline.
+    pub fn is_inline(&self) -> bool {
+        matches!(self.repr, Repr::Inline { .. })
+    }
+
+    pub fn insert(&mut self, value: T) -> bool {
+        let (values, len) = match &mut self.repr {
+            Repr::Inline { values, len } => (values, len),
+            Repr::Tree(set) => { return set.insert(value); }
+        };
+
+        let position = match search(&values[..*len], &value) {
+            Ok(_) => { return false; }
+            Err(position) => position,
+        };
+
+        if *len == N {
+            let mut sorted = values.iter_mut().filter_map(Option::take).collect::<Vec<_>>();
+            sorted.insert(position, value);
+            self.repr = Repr::Tree(AvlTreeSet::from_sorted_vec(sorted));
+
+            return true;
+        }
+
+        values[position..=*len].rotate_right(1);
+        values[position] = Some(value);
+        *len += 1;
+
+        true
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(value).is_some()
+    }
+
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match &self.repr {
+            Repr::Inline { values, len } => {
+                let position = search(&values[..*len], value).ok()?;
+                values[position].as_ref()
+            }
+            Repr::Tree(set) => set.get(value),
+        }
+    }
+
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.take(value).is_some()
+    }
+
+    pub fn take<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match &mut self.repr {
+            Repr::Inline { values, len } => {
+                let position = search(&values[..*len], value).ok()?;
+                let removed = values[position].take();
+                values[position..*len].rotate_left(1);
+                *len -= 1;
+
+                removed
+            }
+            Repr::Tree(set) => set.take(value),
+        }
+    }
+
+    pub fn iter(&self) -> SmallAvlTreeSetIter<'_, T> {
+        let inner = match &self.repr {
+            Repr::Inline { values, len } => IterInner::Inline(values[..*len].iter()),
+            Repr::Tree(set) => IterInner::Tree(set.iter()),
+        };
+
+        SmallAvlTreeSetIter { inner }
+    }
+}
+
+/// Binary searches the filled, sorted prefix of an inline array.
+fn search<T, Q>(values: &[Option<T>], value: &Q) -> Result<usize, usize>
+where
+    T: Borrow<Q>,
+    Q: Ord + ?Sized,
+{
+    values.binary_search_by(|probe| probe.as_ref().unwrap().borrow().cmp(value))
+}
+
+impl<T: Ord, const N: usize> Default for SmallAvlTreeSet<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord + Debug, const N: usize> Debug for SmallAvlTreeSet<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T> Iterator for SmallAvlTreeSetIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            IterInner::Inline(values) => values.next().map(|value| value.as_ref().unwrap()),
+            IterInner::Tree(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.inner {
+            IterInner::Inline(values) => values.size_hint(),
+            IterInner::Tree(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for SmallAvlTreeSetIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            IterInner::Inline(values) => values.next_back().map(|value| value.as_ref().unwrap()),
+            IterInner::Tree(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<'a, T> ExactSizeIterator for SmallAvlTreeSetIter<'a, T> {}
+
+impl<'a, T> FusedIterator for SmallAvlTreeSetIter<'a, T> {}
+
+impl<'a, T: Ord, const N: usize> IntoIterator for &'a SmallAvlTreeSet<T, N> {
+    type Item = &'a T;
+    type IntoIter = SmallAvlTreeSetIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Ord, const N: usize> FromIterator<T> for SmallAvlTreeSet<T, N> {
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T: Ord, const N: usize> Extend<T> for SmallAvlTreeSet<T, N> {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod properties {
+    use super::*;
+    use itertools::equal;
+    use std::collections::BTreeSet;
+
+    #[quickcheck]
+    fn small_set_parity(input: Vec<u8>, removals: Vec<u8>) -> bool {
+        let mut small_set = SmallAvlTreeSet::<_, 4>::new();
+        let mut btree_set = BTreeSet::new();
+
+        let inserted = input.into_iter().all(|x| small_set.insert(x) == btree_set.insert(x));
+        let removed = removals.iter().all(|x| {
+            small_set.take(x) == btree_set.take(x) && small_set.contains(x) == btree_set.contains(x)
+        });
+
+        inserted
+            && removed
+            && small_set.len() == btree_set.len()
+            && equal(small_set.iter(), btree_set.iter())
+            && equal(small_set.iter().rev(), btree_set.iter().rev())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_into_a_tree_past_capacity() {
+        let mut set = SmallAvlTreeSet::<_, 3>::new();
+        set.extend(vec![2, 0, 1, 1]);
+
+        assert!(set.is_inline());
+        assert_eq!(format!("{:?}", set), "{0, 1, 2}");
+
+        assert!(set.insert(3));
+        assert!(!set.is_inline());
+        assert_eq!(set.iter().cloned().collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{link, tree_height, tree_size, AvlNode, AvlTreeSet};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem;
+
+/// A summary of how balanced a set's tree is. Returned by
+/// `AvlTreeSet::balance_stats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BalanceStats {
+    /// The most right-heavy balance factor of any node.
+    pub min_balance: i8,
+    /// The most left-heavy balance factor of any node.
+    pub max_balance: i8,
+    /// The balance factor averaged over every node, or zero for an empty set.
+    pub mean_balance: f64,
+    /// The number of nodes at each depth, starting with the root at depth 0.
+    pub depth_histogram: Vec<usize>,
+}
+
+impl<T, C> AvlTreeSet<T, C> {
+    /// The number of levels in the tree, zero for an empty set.
+    pub fn height(&self) -> usize {
+        tree_height(&self.root)
+    }
+
+    /// The number of nodes in the tree as recorded by its root, which
+    /// always equals `len`.
+    pub fn node_count(&self) -> usize {
+        tree_size(&self.root)
+    }
+
+    /// The bytes the set has allocated for its nodes: one `AvlNode<T>` per
+    /// value, along with the reference counts under the `cow` feature, where
+    /// nodes shared with clones are counted for each. The nodes waiting in
+    /// the node pool are counted too, along with the pool's own buffer.
+    /// Memory owned by the values themselves and the allocator's own
+    /// bookkeeping are not included.
+    pub fn heap_size(&self) -> usize {
+        self.len * link::allocation_size::<T>() + self.pool.heap_size()
+    }
+
+    /// The bytes each node spends beyond its value, on child links, height
+    /// and subtree size.
+    pub fn node_overhead() -> usize {
+        link::allocation_size::<T>() - mem::size_of::<T>()
+    }
+
+    /// Returns `true` if both trees have exactly the same shape, with equal
+    /// values and heights at every position. Unlike `==`, which only
+    /// compares the values in order, two sets holding the same values can
+    /// differ structurally when they were built by different insertions.
+    pub fn structural_eq<D>(&self, other: &AvlTreeSet<T, D>) -> bool
+    where
+        T: PartialEq,
+    {
+        let mut pairs = vec![(&self.root, &other.root)];
+
+        while let Some(pair) = pairs.pop() {
+            match pair {
+                (Some(a), Some(b)) => {
+                    if a.value != b.value || a.height != b.height {
+                        return false;
+                    }
+                    pairs.push((&a.left, &b.left));
+                    pairs.push((&a.right, &b.right));
+                }
+                (None, None) => {}
+                _ => { return false; }
+            }
+        }
+
+        true
+    }
+
+    /// Walks every node once to collect its balance factor and depth.
+    pub fn balance_stats(&self) -> BalanceStats {
+        let mut stats = BalanceStats {
+            min_balance: 0,
+            max_balance: 0,
+            mean_balance: 0.0,
+            depth_histogram: vec![0; self.height()],
+        };
+        let mut balance_sum = 0isize;
+        let mut nodes: Vec<(&AvlNode<T>, usize)> = self.root.as_deref().map(|root| (root, 0)).into_iter().collect();
+
+        while let Some((node, depth)) = nodes.pop() {
+            let balance = node.balance_factor();
+            stats.min_balance = stats.min_balance.min(balance);
+            stats.max_balance = stats.max_balance.max(balance);
+            stats.depth_histogram[depth] += 1;
+            balance_sum += balance as isize;
+
+            for child in [&node.left, &node.right].iter().filter_map(|child| child.as_deref()) {
+                nodes.push((child, depth + 1));
+            }
+        }
+
+        if self.len > 0 {
+            stats.mean_balance = balance_sum as f64 / self.len as f64;
+        }
+
+        stats
+    }
+}
+
+#[cfg(test)]
+mod properties {
+    use crate::AvlTreeSet;
+
+    #[quickcheck]
+    fn stats_describe_a_balanced_tree(input: Vec<u16>) -> bool {
+        let set = input.into_iter().collect::<AvlTreeSet<_>>();
+        let stats = set.balance_stats();
+
+        set.node_count() == set.len()
+            && stats.depth_histogram.len() == set.height()
+            && stats.depth_histogram.iter().sum::<usize>() == set.len()
+            && stats.min_balance >= -1
+            && stats.max_balance <= 1
+            && stats.mean_balance.abs() <= 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_of_a_right_leaning_tree() {
+        let mut set = (0..3).collect::<AvlTreeSet<_>>();
+        set.insert(3);
+        let stats = set.balance_stats();
+
+        assert_eq!(set.height(), 3);
+        assert_eq!((stats.min_balance, stats.max_balance), (-1, 0));
+        assert_eq!(stats.mean_balance, -0.5);
+        assert_eq!(stats.depth_histogram, vec![1, 2, 1]);
+        assert_eq!(AvlTreeSet::<u8>::new().balance_stats().depth_histogram, Vec::<usize>::new());
+    }
+
+    #[test]
+    fn structural_eq_compares_shape() {
+        let mut ascending = (0..3).collect::<AvlTreeSet<_>>();
+        ascending.insert(3);
+        let mut inserted = AvlTreeSet::new();
+        for value in [3, 2, 1, 0].iter() {
+            inserted.insert(*value);
+        }
+
+        assert_eq!(ascending, inserted);
+        assert!(!ascending.structural_eq(&inserted));
+        assert!(ascending.structural_eq(&ascending.clone()));
+    }
+
+    #[test]
+    fn heap_size_counts_one_node_per_value() {
+        let set = (0..100u64).collect::<AvlTreeSet<_>>();
+
+        assert_eq!(set.heap_size(), 100 * link::allocation_size::<u64>());
+        assert!(link::allocation_size::<u64>() >= mem::size_of::<AvlNode<u64>>());
+        assert_eq!(AvlTreeSet::<u64>::node_overhead() + mem::size_of::<u64>(), link::allocation_size::<u64>());
+        assert_eq!(AvlTreeSet::<u64>::new().heap_size(), 0);
+
+        let mut pooled = set.with_node_pool();
+        (0..10).for_each(|value| assert!(pooled.remove(&value)));
+        assert_eq!(pooled.pooled_nodes(), 10);
+        assert!(pooled.heap_size() >= 100 * link::allocation_size::<u64>() + 10 * mem::size_of::<crate::Link<u64>>());
+
+        pooled.shrink_to_fit();
+        assert_eq!(pooled.heap_size(), 90 * link::allocation_size::<u64>());
+    }
+}
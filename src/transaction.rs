@@ -0,0 +1,126 @@
+use crate::{AvlTreeSet, Compare};
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+
+/// Mutations staged against a set by `AvlTreeSet::transaction`. New values
+/// are held in an overlay and removed ones are set aside, so the set only
+/// takes the new values once the transaction commits, and gets the removed
+/// ones back if it doesn't.
+pub struct AvlTreeSetTransaction<'a, T, C: Compare<T>> {
+    set: &'a mut AvlTreeSet<T, C>,
+    inserted: AvlTreeSet<T, C>,
+    removed: Vec<T>,
+}
+
+impl<T, C: Compare<T> + Clone> AvlTreeSet<T, C> {
+    /// Runs `f` against a transaction over the set. If `f` returns `Ok` its
+    /// mutations are applied all together; if it returns `Err`, or panics,
+    /// the set is left exactly as it was.
+    pub fn transaction<R, E, F>(&mut self, f: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut AvlTreeSetTransaction<'_, T, C>) -> Result<R, E>,
+    {
+        let inserted = AvlTreeSet::with_comparator(self.comparator().clone());
+        let mut transaction = AvlTreeSetTransaction { set: self, inserted, removed: Vec::new() };
+        let result = f(&mut transaction);
+
+        if result.is_ok() {
+            transaction.commit();
+        }
+        result
+    }
+}
+
+impl<'a, T, C: Compare<T>> AvlTreeSetTransaction<'a, T, C> {
+    /// The number of values the set would hold if committed now.
+    pub fn len(&self) -> usize {
+        self.set.len() + self.inserted.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.get(value).is_some()
+    }
+
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.set.get(value).or_else(|| self.inserted.get(value))
+    }
+
+    /// Stages `value`, returning `false` if an equal value is already there.
+    pub fn insert(&mut self, value: T) -> bool {
+        !self.set.contains(&value) && self.inserted.insert(value)
+    }
+
+    /// Stages the removal of the value equal to `value`, returning whether
+    /// there was one.
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        match self.set.take(value) {
+            Some(removed) => {
+                self.removed.push(removed);
+                true
+            }
+            None => self.inserted.remove(value),
+        }
+    }
+
+    fn commit(&mut self) {
+        self.removed.clear();
+        self.set.insert_batch(self.inserted.drain());
+    }
+}
+
+impl<'a, T, C: Compare<T>> Drop for AvlTreeSetTransaction<'a, T, C> {
+    fn drop(&mut self) {
+        self.set.insert_batch(self.removed.drain(..));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn transactions_commit_or_roll_back() {
+        let mut set = AvlTreeSet::from([1, 2, 3]);
+
+        let result = set.transaction(|txn| {
+            txn.remove(&1);
+            txn.insert(4);
+            if txn.contains(&4) && !txn.contains(&1) {
+                Err(txn.len())
+            } else {
+                Ok(())
+            }
+        });
+        assert_eq!(result, Err(3));
+        assert_eq!(set.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        let result = set.transaction(|txn| {
+            assert!(txn.insert(5) && !txn.insert(2) && txn.remove(&2));
+            assert!(txn.insert(2) && txn.insert(6) && txn.remove(&6));
+            Ok::<_, ()>(txn.len())
+        });
+        assert_eq!(result, Ok(4));
+        assert_eq!(set.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 5]);
+        assert!(crate::tests::is_balanced(&set.root));
+    }
+}
@@ -0,0 +1,135 @@
+//! A set with numbered snapshots, built on the path-copying nodes of
+//! `ImmutableAvlTreeSet`. Taking a snapshot is a reference count bump, and
+//! a write copies only the path to the node it changes, so old versions
+//! stay readable, from other threads too, while new writes go ahead.
+
+use crate::immutable::ImmutableAvlTreeSet;
+use alloc::collections::BTreeMap;
+use core::borrow::Borrow;
+
+/// Names a snapshot taken by `VersionedAvlTreeSet::snapshot`. Ids increase
+/// with every snapshot taken and are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SnapshotId(u64);
+
+/// A set that keeps the snapshots taken of it until they are released, so
+/// it can be read as of, or rolled back to, any of them.
+#[derive(Debug, Clone)]
+pub struct VersionedAvlTreeSet<T: Ord + Clone> {
+    current: ImmutableAvlTreeSet<T>,
+    snapshots: BTreeMap<SnapshotId, ImmutableAvlTreeSet<T>>,
+    next_id: u64,
+}
+
+impl<T: Ord + Clone> VersionedAvlTreeSet<T> {
+    pub fn new() -> Self {
+        Self { current: ImmutableAvlTreeSet::new(), snapshots: BTreeMap::new(), next_id: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.current.is_empty()
+    }
+
+    pub fn insert(&mut self, value: T) -> bool {
+        self.current.insert_in_place(value)
+    }
+
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.current.remove_in_place(value)
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.current.contains(value)
+    }
+
+    /// The set as it is now.
+    pub fn current(&self) -> &ImmutableAvlTreeSet<T> {
+        &self.current
+    }
+
+    /// Records the set as it is now, returning the id to read it back by.
+    pub fn snapshot(&mut self) -> SnapshotId {
+        let id = SnapshotId(self.next_id);
+        self.next_id += 1;
+        self.snapshots.insert(id, self.current.clone());
+        id
+    }
+
+    /// The set as it was when snapshot `id` was taken, or `None` if it has
+    /// been released. Clone the returned set to keep reading it elsewhere
+    /// while this one is written to.
+    pub fn at(&self, id: SnapshotId) -> Option<&ImmutableAvlTreeSet<T>> {
+        self.snapshots.get(&id)
+    }
+
+    /// Makes snapshot `id` the current set, returning `false` and leaving
+    /// the set alone if it has been released. Every snapshot, including
+    /// those taken after `id`, stays available.
+    pub fn rollback(&mut self, id: SnapshotId) -> bool {
+        match self.snapshots.get(&id) {
+            Some(snapshot) => {
+                self.current = snapshot.clone();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops snapshot `id`, freeing the nodes no other version shares, and
+    /// returns whether it was held.
+    pub fn release(&mut self, id: SnapshotId) -> bool {
+        self.snapshots.remove(&id).is_some()
+    }
+
+    /// The ids of the snapshots held, oldest first.
+    pub fn snapshots(&self) -> impl Iterator<Item = SnapshotId> + '_ {
+        self.snapshots.keys().cloned()
+    }
+}
+
+impl<T: Ord + Clone> Default for VersionedAvlTreeSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use std::thread;
+
+    #[test]
+    fn snapshots_survive_writes_and_rollbacks() {
+        let mut set = VersionedAvlTreeSet::new();
+        set.insert(1);
+        set.insert(2);
+        let first = set.snapshot();
+
+        let reader = set.at(first).unwrap().clone();
+        let handle = thread::spawn(move || reader.iter().cloned().collect::<Vec<_>>());
+        set.remove(&1);
+        set.insert(3);
+        let second = set.snapshot();
+        assert_eq!(handle.join().unwrap(), vec![1, 2]);
+
+        assert!(set.rollback(first));
+        assert_eq!(set.current().iter().cloned().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(set.at(second).unwrap().iter().cloned().collect::<Vec<_>>(), vec![2, 3]);
+
+        assert!(set.release(first) && !set.rollback(first));
+        assert_eq!(set.snapshots().collect::<Vec<_>>(), vec![second]);
+    }
+}
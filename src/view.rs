@@ -0,0 +1,118 @@
+use crate::{AvlTreeSet, AvlTreeSetIter, AvlTreeSetRange, Compare, Natural};
+use core::borrow::Borrow;
+use core::fmt;
+use core::ops::RangeBounds;
+
+/// A read-only, `Copy` view of a set, created by `AvlTreeSet::view`. It can
+/// be handed to any number of functions by value, which can query the set
+/// but neither mutate it nor reach the set itself.
+pub struct AvlTreeSetView<'a, T, C = Natural> {
+    set: &'a AvlTreeSet<T, C>,
+}
+
+impl<T, C> AvlTreeSet<T, C> {
+    pub fn view(&self) -> AvlTreeSetView<'_, T, C> {
+        AvlTreeSetView { set: self }
+    }
+}
+
+impl<'a, T, C> AvlTreeSetView<'a, T, C> {
+    pub fn iter(&self) -> AvlTreeSetIter<'a, T> {
+        self.set.iter()
+    }
+
+    pub fn height(&self) -> usize {
+        self.set.height()
+    }
+}
+
+impl<'a, T, C: Compare<T>> AvlTreeSetView<'a, T, C> {
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    pub fn first(&self) -> Option<&'a T> {
+        self.set.first()
+    }
+
+    pub fn last(&self) -> Option<&'a T> {
+        self.set.last()
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.set.contains(value)
+    }
+
+    pub fn get<Q>(&self, value: &Q) -> Option<&'a T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.set.get(value)
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> AvlTreeSetRange<'a, T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        R: RangeBounds<Q>,
+    {
+        self.set.range(range)
+    }
+}
+
+impl<'a, T, C> Clone for AvlTreeSetView<'a, T, C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T, C> Copy for AvlTreeSetView<'a, T, C> {}
+
+impl<'a, T: fmt::Debug, C> fmt::Debug for AvlTreeSetView<'a, T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T, C> IntoIterator for AvlTreeSetView<'a, T, C> {
+    type Item = &'a T;
+    type IntoIter = AvlTreeSetIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn evens_below(view: AvlTreeSetView<'_, u32>, end: u32) -> Vec<u32> {
+        view.range(..end).filter(|&x| x % 2 == 0).cloned().collect()
+    }
+
+    #[test]
+    fn views_are_copied_into_workers() {
+        let set = (0..10).collect::<AvlTreeSet<u32>>();
+        let view = set.view();
+
+        assert_eq!(evens_below(view, 5), vec![0, 2, 4]);
+        assert_eq!(evens_below(view, 9), vec![0, 2, 4, 6, 8]);
+        assert!(view.contains(&9) && !view.contains(&10));
+        assert_eq!((view.len(), view.first(), view.last()), (10, Some(&0), Some(&9)));
+        assert_eq!(view.into_iter().count(), 10);
+    }
+}
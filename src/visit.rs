@@ -0,0 +1,118 @@
+use crate::{AvlNode, AvlTreeSet};
+use alloc::vec::Vec;
+use core::ops::ControlFlow;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Order {
+    Pre,
+    In,
+    Post,
+}
+
+impl<T, C> AvlTreeSet<T, C> {
+    /// Calls `f` on every value in ascending order along with its node's
+    /// depth, the root being at depth 0.
+    pub fn visit_inorder<F: FnMut(&T, usize)>(&self, f: F) {
+        self.visit(Order::In, f)
+    }
+
+    /// Calls `f` on every value with its depth, each node before its
+    /// children and left subtrees before right ones.
+    pub fn visit_preorder<F: FnMut(&T, usize)>(&self, f: F) {
+        self.visit(Order::Pre, f)
+    }
+
+    /// Calls `f` on every value with its depth, each node after its
+    /// children and left subtrees before right ones.
+    pub fn visit_postorder<F: FnMut(&T, usize)>(&self, f: F) {
+        self.visit(Order::Post, f)
+    }
+
+    /// Like `visit_inorder`, but stops as soon as `f` breaks, returning the
+    /// value it broke with.
+    pub fn try_visit_inorder<B, F: FnMut(&T, usize) -> ControlFlow<B>>(&self, f: F) -> ControlFlow<B> {
+        self.try_visit(Order::In, f)
+    }
+
+    /// Like `visit_preorder`, but stops as soon as `f` breaks.
+    pub fn try_visit_preorder<B, F: FnMut(&T, usize) -> ControlFlow<B>>(&self, f: F) -> ControlFlow<B> {
+        self.try_visit(Order::Pre, f)
+    }
+
+    /// Like `visit_postorder`, but stops as soon as `f` breaks.
+    pub fn try_visit_postorder<B, F: FnMut(&T, usize) -> ControlFlow<B>>(&self, f: F) -> ControlFlow<B> {
+        self.try_visit(Order::Post, f)
+    }
+
+    fn visit<F: FnMut(&T, usize)>(&self, order: Order, mut f: F) {
+        let _ = self.try_visit::<(), _>(order, |value, depth| {
+            f(value, depth);
+            ControlFlow::Continue(())
+        });
+    }
+
+    /// Walks the tree with an explicit stack of `(node, depth, expanded)`.
+    /// A node is pushed unexpanded when first reached; when popped it is
+    /// either visited or pushed back expanded around its children, in
+    /// whichever arrangement puts the visit at the right point for `order`.
+    fn try_visit<B, F>(&self, order: Order, mut f: F) -> ControlFlow<B>
+    where
+        F: FnMut(&T, usize) -> ControlFlow<B>,
+    {
+        let mut stack: Vec<(&AvlNode<T>, usize, bool)> = self.root.as_deref().map(|root| (root, 0, false)).into_iter().collect();
+
+        while let Some((node, depth, expanded)) = stack.pop() {
+            if expanded || order == Order::Pre {
+                f(&node.value, depth)?;
+
+                if expanded {
+                    continue;
+                }
+            }
+
+            let right = node.right.as_deref().map(|right| (right, depth + 1, false));
+            let left = node.left.as_deref().map(|left| (left, depth + 1, false));
+
+            match order {
+                Order::Pre => stack.extend(right.into_iter().chain(left)),
+                Order::In => stack.extend(right.into_iter().chain(Some((node, depth, true))).chain(left)),
+                Order::Post => stack.extend(Some((node, depth, true)).into_iter().chain(right).chain(left)),
+            }
+        }
+
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visited<F: FnOnce(&AvlTreeSet<i32>, &mut dyn FnMut(&i32, usize))>(visit: F) -> Vec<(i32, usize)> {
+        let set = (1..=5).collect::<AvlTreeSet<_>>();
+        let mut values = Vec::new();
+        visit(&set, &mut |&value, depth| values.push((value, depth)));
+        values
+    }
+
+    #[test]
+    fn visits_in_each_order() {
+        assert_eq!(visited(|set, f| set.visit_inorder(f)), vec![(1, 2), (2, 1), (3, 0), (4, 2), (5, 1)]);
+        assert_eq!(visited(|set, f| set.visit_preorder(f)), vec![(3, 0), (2, 1), (1, 2), (5, 1), (4, 2)]);
+        assert_eq!(visited(|set, f| set.visit_postorder(f)), vec![(1, 2), (2, 1), (4, 2), (5, 1), (3, 0)]);
+    }
+
+    #[test]
+    fn try_visit_stops_early() {
+        let set = (0..100).collect::<AvlTreeSet<_>>();
+        let mut seen = 0;
+
+        let found = set.try_visit_inorder(|&value, _| {
+            seen += 1;
+            if value == 10 { ControlFlow::Break(value) } else { ControlFlow::Continue(()) }
+        });
+
+        assert_eq!(found, ControlFlow::Break(10));
+        assert_eq!(seen, 11);
+    }
+}
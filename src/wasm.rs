@@ -0,0 +1,74 @@
+//! A `wasm-bindgen` class for JavaScript: an `AvlSet` of numbers with the
+//! method names of JavaScript's own `Set`, iterating in ascending order.
+//! The glue the macro generates uses `unsafe`, so this module allows it.
+#![allow(unsafe_code)]
+
+use crate::{AvlTreeSet, TotalOrder};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct AvlSet(AvlTreeSet<f64, TotalOrder>);
+
+#[wasm_bindgen]
+impl AvlSet {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> AvlSet {
+        AvlSet(AvlTreeSet::partially_ordered())
+    }
+
+    /// Adds `value`, returning whether it was new. Throws for `NaN`, which
+    /// has no place in the order.
+    #[wasm_bindgen(js_name = add)]
+    pub fn insert(&mut self, value: f64) -> Result<bool, JsError> {
+        self.0.try_insert(value).map_err(|_| JsError::new("NaN can't be stored in an AvlSet"))
+    }
+
+    pub fn has(&self, value: f64) -> bool {
+        !value.is_nan() && self.0.contains(&value)
+    }
+
+    /// Removes `value`, returning whether it was present.
+    pub fn delete(&mut self, value: f64) -> bool {
+        !value.is_nan() && self.0.remove(&value)
+    }
+
+    pub fn clear(&mut self) {
+        self.0 = AvlTreeSet::partially_ordered();
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The values in ascending order, as a `Float64Array`.
+    pub fn values(&self) -> Vec<f64> {
+        self.0.iter().cloned().collect()
+    }
+}
+
+impl Default for AvlSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mirrors_js_set_methods() {
+        let mut set = AvlSet::new();
+
+        for value in [2.5, -1.0, 2.5, 10.0].iter() {
+            assert!(set.insert(*value).is_ok());
+        }
+
+        assert!(set.has(-1.0));
+        assert!(!set.has(f64::NAN));
+        assert!(set.delete(10.0));
+        assert_eq!(set.size(), 2);
+        assert_eq!(set.values(), vec![-1.0, 2.5]);
+    }
+}
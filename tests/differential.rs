@@ -0,0 +1,251 @@
+//! Differential tests running random operation sequences against
+//! `AvlTreeSet` and several reference models, requiring every model to agree
+//! on every result. To cover a new operation, add an `Op` variant, generate
+//! it in `Op::arbitrary` and handle it in each `Model`.
+
+#[macro_use]
+extern crate quickcheck;
+
+use avl_tree_set::AvlTreeSet;
+use quickcheck::{Arbitrary, Gen};
+use std::collections::BTreeSet;
+
+type Value = u16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Insert(Value),
+    Remove(Value),
+    Contains(Value),
+    Rank(Value),
+    Select(usize),
+    PopFirst,
+    PopLast,
+}
+
+impl Arbitrary for Op {
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        // A narrow value range makes repeated values and hits common.
+        let value = Value::arbitrary(g) % 256;
+
+        match u8::arbitrary(g) % 10 {
+            0..=3 => Op::Insert(value),
+            4 | 5 => Op::Remove(value),
+            6 => Op::Contains(value),
+            7 => Op::Rank(value),
+            8 => Op::Select(usize::from(value)),
+            _ => {
+                if bool::arbitrary(g) { Op::PopFirst } else { Op::PopLast }
+            }
+        }
+    }
+}
+
+/// What an operation returned, in a form every model can produce.
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    Bool(bool),
+    Count(usize),
+    Value(Option<Value>),
+}
+
+trait Model {
+    fn apply(&mut self, op: Op) -> Outcome;
+    fn values(&self) -> Vec<Value>;
+}
+
+impl Model for AvlTreeSet<Value> {
+    fn apply(&mut self, op: Op) -> Outcome {
+        match op {
+            Op::Insert(value) => Outcome::Bool(self.insert(value)),
+            Op::Remove(value) => Outcome::Bool(self.remove(&value)),
+            Op::Contains(value) => Outcome::Bool(self.contains(&value)),
+            Op::Rank(value) => Outcome::Count(self.rank(&value)),
+            Op::Select(index) => Outcome::Value(self.select(index).cloned()),
+            Op::PopFirst => Outcome::Value(self.pop_first()),
+            Op::PopLast => Outcome::Value(self.pop_last()),
+        }
+    }
+
+    fn values(&self) -> Vec<Value> {
+        self.iter().cloned().collect()
+    }
+}
+
+impl Model for BTreeSet<Value> {
+    fn apply(&mut self, op: Op) -> Outcome {
+        match op {
+            Op::Insert(value) => Outcome::Bool(self.insert(value)),
+            Op::Remove(value) => Outcome::Bool(self.remove(&value)),
+            Op::Contains(value) => Outcome::Bool(self.contains(&value)),
+            Op::Rank(value) => Outcome::Count(self.range(..value).count()),
+            Op::Select(index) => Outcome::Value(self.iter().nth(index).cloned()),
+            Op::PopFirst => Outcome::Value(self.pop_first()),
+            Op::PopLast => Outcome::Value(self.pop_last()),
+        }
+    }
+
+    fn values(&self) -> Vec<Value> {
+        self.iter().cloned().collect()
+    }
+}
+
+/// A sorted, deduplicated vector: simple enough to be obviously right.
+#[derive(Default)]
+struct SortedVec(Vec<Value>);
+
+impl Model for SortedVec {
+    fn apply(&mut self, op: Op) -> Outcome {
+        let values = &mut self.0;
+
+        match op {
+            Op::Insert(value) => Outcome::Bool(match values.binary_search(&value) {
+                Ok(_) => false,
+                Err(index) => {
+                    values.insert(index, value);
+                    true
+                }
+            }),
+            Op::Remove(value) => Outcome::Bool(match values.binary_search(&value) {
+                Ok(index) => {
+                    values.remove(index);
+                    true
+                }
+                Err(_) => false,
+            }),
+            Op::Contains(value) => Outcome::Bool(values.binary_search(&value).is_ok()),
+            Op::Rank(value) => Outcome::Count(values.iter().filter(|&&x| x < value).count()),
+            Op::Select(index) => Outcome::Value(values.get(index).cloned()),
+            Op::PopFirst => Outcome::Value(if values.is_empty() { None } else { Some(values.remove(0)) }),
+            Op::PopLast => Outcome::Value(values.pop()),
+        }
+    }
+
+    fn values(&self) -> Vec<Value> {
+        self.0.clone()
+    }
+}
+
+/// A binary search tree that never rebalances, checking that balancing
+/// doesn't change what the operations return.
+#[derive(Default)]
+struct NaiveBst(Option<Box<NaiveNode>>);
+
+struct NaiveNode {
+    value: Value,
+    left: NaiveBst,
+    right: NaiveBst,
+}
+
+impl NaiveBst {
+    fn insert(&mut self, value: Value) -> bool {
+        match &mut self.0 {
+            None => {
+                self.0 = Some(Box::new(NaiveNode { value, left: NaiveBst(None), right: NaiveBst(None) }));
+                true
+            }
+            Some(node) if value < node.value => node.left.insert(value),
+            Some(node) if value > node.value => node.right.insert(value),
+            Some(_) => false,
+        }
+    }
+
+    fn remove(&mut self, value: Value) -> bool {
+        let node = match &mut self.0 {
+            None => { return false; }
+            Some(node) if value < node.value => { return node.left.remove(value); }
+            Some(node) if value > node.value => { return node.right.remove(value); }
+            Some(node) => node,
+        };
+
+        match node.right.pop_min() {
+            Some(successor) => node.value = successor,
+            None => {
+                let left = node.left.0.take();
+                self.0 = left;
+            }
+        }
+
+        true
+    }
+
+    fn pop_min(&mut self) -> Option<Value> {
+        match &mut self.0 {
+            None => None,
+            Some(node) if node.left.0.is_some() => node.left.pop_min(),
+            Some(node) => {
+                let value = node.value;
+                let right = node.right.0.take();
+                self.0 = right;
+                Some(value)
+            }
+        }
+    }
+
+    fn push_values(&self, values: &mut Vec<Value>) {
+        if let Some(node) = &self.0 {
+            node.left.push_values(values);
+            values.push(node.value);
+            node.right.push_values(values);
+        }
+    }
+}
+
+impl Model for NaiveBst {
+    fn apply(&mut self, op: Op) -> Outcome {
+        let values = self.values();
+
+        match op {
+            Op::Insert(value) => Outcome::Bool(self.insert(value)),
+            Op::Remove(value) => Outcome::Bool(self.remove(value)),
+            Op::Contains(value) => Outcome::Bool(values.contains(&value)),
+            Op::Rank(value) => Outcome::Count(values.iter().filter(|&&x| x < value).count()),
+            Op::Select(index) => Outcome::Value(values.get(index).cloned()),
+            Op::PopFirst => Outcome::Value(self.pop_min()),
+            Op::PopLast => Outcome::Value(values.last().cloned().filter(|&last| self.remove(last))),
+        }
+    }
+
+    fn values(&self) -> Vec<Value> {
+        let mut values = Vec::new();
+        self.push_values(&mut values);
+        values
+    }
+}
+
+/// The most an AVL tree with `len` values may be tall: about
+/// `1.44 * log2(len + 2)`, rounded up a little.
+fn max_height(len: usize) -> f64 {
+    1.45 * ((len + 2) as f64).log2()
+}
+
+quickcheck! {
+    fn models_agree(ops: Vec<Op>) -> bool {
+        let mut set = AvlTreeSet::new();
+        let mut models: Vec<Box<dyn Model>> =
+            vec![Box::new(BTreeSet::new()), Box::new(SortedVec::default()), Box::new(NaiveBst::default())];
+
+        ops.into_iter().all(|op| {
+            let outcome = set.apply(op);
+
+            models.iter_mut().all(|model| model.apply(op) == outcome)
+                && set.check_invariants().is_ok()
+                && set.height() as f64 <= max_height(set.len())
+        }) && models.iter().all(|model| model.values() == set.values())
+    }
+}
+
+/// How many values `sequential_inserts_stay_shallow` inserts. The
+/// `paranoid` feature checks the whole tree after every insertion, so it
+/// gets far fewer to stay quick.
+const SEQUENTIAL_LEN: Value = if cfg!(feature = "paranoid") { 1 << 10 } else { Value::MAX };
+
+#[test]
+fn sequential_inserts_stay_shallow() {
+    let mut set = AvlTreeSet::new();
+
+    for value in 0..SEQUENTIAL_LEN {
+        set.insert(value);
+        assert!(set.height() as f64 <= max_height(set.len()), "too tall at {} values", set.len());
+    }
+}